@@ -0,0 +1,50 @@
+//! Measure the generation of puzzles across difficulties and board sizes.
+//!
+//! Run with `cargo bench --bench build`, and with `--features rayon` to compare the generation of
+//! puzzles with a unique solution on several threads. All puzzles are generated from a fixed
+//! seed, so that every run measures the same puzzles.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use netwalk::puzzle::{Builder, Difficulty, Options};
+
+const SEED: u64 = 42;
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert] {
+        for board_size in [10, 20, 40] {
+            let options = Options { board_size, difficulty, ..Default::default() };
+            let builder = Builder::new().with_options(options).with_seed(SEED);
+            group.bench_with_input(
+                BenchmarkId::new(difficulty.to_string(), board_size),
+                &builder,
+                |b, builder| b.iter(|| black_box(builder.build())),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn build_unique(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_unique");
+    // A single puzzle with a unique solution takes up to several seconds.
+    group.sample_size(10);
+    for board_size in [10, 20] {
+        let options = Options {
+            board_size,
+            difficulty: Difficulty::Hard,
+            unique_solution: true,
+            ..Default::default()
+        };
+        let builder = Builder::new().with_options(options).with_seed(SEED);
+        group.bench_with_input(BenchmarkId::from_parameter(board_size), &builder, |b, builder| {
+            b.iter(|| black_box(builder.build()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build, build_unique);
+criterion_main!(benches);
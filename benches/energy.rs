@@ -0,0 +1,39 @@
+//! Measure the calculation of the tiles which are connected to the source.
+//!
+//! Run with `cargo bench --bench energy`. The jumbled puzzle is the common case while playing,
+//! in the solved puzzle the energy reaches every tile.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use netwalk::puzzle::{Builder, Options};
+
+const SEED: u64 = 42;
+
+fn calc_energy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calc_energy");
+    for wrapping in [false, true] {
+        for board_size in [10, 20] {
+            let options = Options { board_size, wrapping, ..Default::default() };
+            let jumbled = Builder::new().with_options(options).with_seed(SEED).build();
+            let mut solved = jumbled.clone();
+            solved.solve();
+
+            let board = if wrapping { "wrapping" } else { "bounded" };
+            for (state, puzzle) in [("jumbled", jumbled), ("solved", solved)] {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{board}/{state}"), board_size),
+                    &puzzle,
+                    |b, puzzle| {
+                        let mut puzzle = puzzle.clone();
+                        b.iter(|| black_box(&mut puzzle).calc_energy())
+                    },
+                );
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, calc_energy);
+criterion_main!(benches);
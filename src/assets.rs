@@ -1,8 +1,9 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use eframe::egui;
 use strum::IntoEnumIterator;
+use thiserror::Error;
 
 use crate::puzzle::Orientation;
 
@@ -53,9 +54,38 @@ fn filename_from_asset_type(asset_type: AssetType) -> &'static str {
     assets.get(&asset_type).expect("path to asset not found")
 }
 
+/// Return the image data of an asset type which is embedded into the binary.
+fn embedded_bytes_from_asset_type(asset_type: AssetType) -> &'static [u8] {
+    let assets: HashMap<AssetType, &'static [u8]> = HashMap::from([
+        (AssetType::Straight, include_bytes!("../assets/40/straight.png").as_slice()),
+        (AssetType::StraightPowered, include_bytes!("../assets/40/straight_powered.png")),
+        (AssetType::Corner, include_bytes!("../assets/40/corner.png")),
+        (AssetType::CornerPowered, include_bytes!("../assets/40/corner_powered.png")),
+        (AssetType::TIntersection, include_bytes!("../assets/40/t_intersection.png")),
+        (
+            AssetType::TIntersectionPowered,
+            include_bytes!("../assets/40/t_intersection_powered.png"),
+        ),
+        (AssetType::CrossIntersection, include_bytes!("../assets/40/cross_intersection.png")),
+        (
+            AssetType::CrossIntersectionPowered,
+            include_bytes!("../assets/40/cross_intersection_powered.png"),
+        ),
+        (AssetType::DeadEnd, include_bytes!("../assets/40/dead_end.png")),
+        (AssetType::DeadEndPowered, include_bytes!("../assets/40/dead_end_powered.png")),
+        (AssetType::Wall, include_bytes!("../assets/40/wall.png")),
+        (AssetType::Source, include_bytes!("../assets/40/source.png")),
+        (AssetType::SourcePowered, include_bytes!("../assets/40/source_powered.png")),
+        (AssetType::Drain, include_bytes!("../assets/40/drain.png")),
+        (AssetType::DrainPowered, include_bytes!("../assets/40/drain_powered.png")),
+    ]);
+    assets.get(&asset_type).expect("embedded asset not found")
+}
+
 #[derive(Clone, Default)]
 pub struct Assets {
     assets: HashMap<AssetType, Vec<egui::TextureHandle>>,
+    images: HashMap<AssetType, Vec<image::RgbaImage>>, // the decoded images of the textures
 }
 
 impl Assets {
@@ -63,33 +93,74 @@ impl Assets {
     pub fn new() -> Self {
         Assets {
             assets: HashMap::new(),
+            images: HashMap::new(),
         }
     }
 
-    /// Load all assets from hardcoded paths.
-    pub fn load_all(&mut self, context: &egui::Context) {
+    /// Load all assets embedded into the binary.
+    pub fn load_all(&mut self, context: &egui::Context) -> Result<(), AssetError> {
         for asset_type in AssetType::iter() {
-            self.load(asset_type, context);
+            self.load(asset_type, context)?;
         }
+        Ok(())
     }
 
     /// Load a specific asset type.
-    #[doc(hidden)]
-    fn load(&mut self, asset_type: AssetType, context: &egui::Context) {
-        let root: PathBuf = PathBuf::from("assets/40");
-        let path = root.join(filename_from_asset_type(asset_type));
+    pub fn load(&mut self, asset_type: AssetType, context: &egui::Context) -> Result<(), AssetError> {
+        let image = image::load_from_memory(embedded_bytes_from_asset_type(asset_type))
+            .map_err(|source| AssetError::Decode {
+                asset_type,
+                path: PathBuf::from(filename_from_asset_type(asset_type)),
+                source,
+            })?;
+        self.insert_image(asset_type, &image, context);
+        Ok(())
+    }
 
-        let image = image::ImageReader::open(path)
-            .expect("could not load image")
-            .decode()
-            .expect("could not decode image");
+    /// Load all assets from the image files in `dir`, e.g. a custom tileset. The files must have
+    /// the same names as the embedded assets (`straight.png`, etc.). If a file is missing, the
+    /// embedded asset is used instead, but a directory which does not exist or contains none of
+    /// the files is an error, e.g. a mistyped path.
+    pub fn load_from_dir(&mut self, dir: &Path, context: &egui::Context) -> Result<(), AssetError> {
+        if !dir.is_dir() {
+            return Err(AssetError::MissingDir(dir.to_path_buf()));
+        }
+        let mut found = false;
+        for asset_type in AssetType::iter() {
+            let path = dir.join(filename_from_asset_type(asset_type));
+            match std::fs::read(&path) {
+                Ok(bytes) => {
+                    let image = image::load_from_memory(&bytes)
+                        .map_err(|source| AssetError::Decode { asset_type, path, source })?;
+                    self.insert_image(asset_type, &image, context);
+                    found = true;
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                    self.load(asset_type, context)?;
+                }
+                Err(source) => return Err(AssetError::Io { asset_type, path, source }),
+            }
+        }
+        if !found {
+            return Err(AssetError::NoAssets(dir.to_path_buf()));
+        }
+        Ok(())
+    }
 
+    /// Create the textures of all four rotations of an image and keep the rotated images.
+    #[doc(hidden)]
+    fn insert_image(
+        &mut self,
+        asset_type: AssetType,
+        image: &image::DynamicImage,
+        context: &egui::Context,
+    ) {
         let size = [image.width() as _, image.height() as _];
 
-        let assets = Orientation::iter()
+        let (assets, images) = Orientation::iter()
             .map(|rotation| {
                 let image = match rotation {
-                    Orientation::Basic => &image,
+                    Orientation::Basic => image,
                     Orientation::Ccw90 => &image.rotate270(),
                     Orientation::Ccw180 => &image.rotate180(),
                     Orientation::Ccw270 => &image.rotate90(),
@@ -98,16 +169,81 @@ impl Assets {
                 let pixels = image_buffer.as_flat_samples();
                 let color_image = egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
 
-                context.load_texture("texture", color_image, Default::default())
+                (context.load_texture("texture", color_image, Default::default()), image_buffer)
             })
-            .collect();
+            .unzip();
         self.assets.insert(asset_type, assets);
+        self.images.insert(asset_type, images);
     }
 
     pub fn get_rotated(&self, asset_type: AssetType, rotation: Orientation) -> Option<egui::TextureHandle> {
         self.assets
             .get(&asset_type)
-            .and_then(|handles| handles.get(rotation as usize))
+            .and_then(|handles| handles.get(rotation.quarters() as usize))
             .cloned()
     }
+
+    /// Return the decoded image of a texture, e.g. to render the game board offscreen.
+    pub fn get_rotated_image(&self, asset_type: AssetType, rotation: Orientation) -> Option<&image::RgbaImage> {
+        self.images
+            .get(&asset_type)
+            .and_then(|images| images.get(rotation.quarters() as usize))
+    }
+}
+
+/// An error loading an asset.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("could not read asset {asset_type:?} from '{}'", path.display())]
+    Io {
+        asset_type: AssetType,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("could not decode asset {asset_type:?} from '{}'", path.display())]
+    Decode {
+        asset_type: AssetType,
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("tileset directory '{}' does not exist", .0.display())]
+    MissingDir(PathBuf),
+    #[error("tileset directory '{}' contains none of the asset files", .0.display())]
+    NoAssets(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_assets_decode() {
+        for asset_type in AssetType::iter() {
+            let image = image::load_from_memory(embedded_bytes_from_asset_type(asset_type));
+            assert!(image.is_ok(), "{}", filename_from_asset_type(asset_type));
+        }
+    }
+
+    #[test]
+    fn load_from_dir_needs_asset_files() {
+        let context = egui::Context::default();
+        let dir = std::env::temp_dir().join(format!("netwalk-tileset-{}", std::process::id()));
+        let mut assets = Assets::new();
+
+        let result = assets.load_from_dir(&dir, &context);
+        assert!(matches!(result, Err(AssetError::MissingDir(path)) if path == dir));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let result = assets.load_from_dir(&dir, &context);
+        assert!(matches!(result, Err(AssetError::NoAssets(path)) if path == dir));
+
+        // A single file is enough, the others are embedded.
+        let filename = filename_from_asset_type(AssetType::Straight);
+        std::fs::write(dir.join(filename), embedded_bytes_from_asset_type(AssetType::Straight)).unwrap();
+        let result = assets.load_from_dir(&dir, &context);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_ok());
+    }
 }
@@ -0,0 +1,75 @@
+//! Sound effects, played when a tile has been rotated and when a puzzle is solved.
+//!
+//! This module is only built with the feature `audio`.
+
+use std::io::Cursor;
+
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, strum::EnumIter)]
+pub enum Sound {
+    Rotation,
+    Solved,
+}
+
+/// Return the WAV data of a sound which is embedded into the binary.
+fn embedded_bytes_from_sound(sound: Sound) -> &'static [u8] {
+    match sound {
+        Sound::Rotation => include_bytes!("../assets/sounds/rotation.wav"),
+        Sound::Solved => include_bytes!("../assets/sounds/solved.wav"),
+    }
+}
+
+/// The audio output of the game.
+pub struct Audio {
+    stream: rodio::OutputStream,
+}
+
+impl Audio {
+    /// Open the default audio output device and check that all embedded sounds can be decoded.
+    pub fn new() -> Result<Self, AudioError> {
+        for sound in Sound::iter() {
+            rodio::Decoder::new(Cursor::new(embedded_bytes_from_sound(sound)))
+                .map_err(|source| AudioError::Decode { sound, source })?;
+        }
+
+        let mut stream = rodio::OutputStreamBuilder::open_default_stream()?;
+        // Dropping the stream on exit is expected, do not print a message.
+        stream.log_on_drop(false);
+        Ok(Self { stream })
+    }
+
+    /// Start playing `sound` with a volume from 0 (silent) to 1 (unchanged). Sounds may overlap.
+    pub fn play(&self, sound: Sound, volume: f32) {
+        let source = rodio::Decoder::new(Cursor::new(embedded_bytes_from_sound(sound)))
+            .expect("embedded sounds are checked in Audio::new");
+        self.stream.mixer().add(rodio::Source::amplify(source, volume.clamp(0., 1.)));
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("could not open the audio output")]
+    Stream(#[from] rodio::StreamError),
+    #[error("could not decode sound {sound:?}")]
+    Decode {
+        sound: Sound,
+        #[source]
+        source: rodio::decoder::DecoderError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_sounds_decode() {
+        for sound in Sound::iter() {
+            let decoder = rodio::Decoder::new(Cursor::new(embedded_bytes_from_sound(sound)));
+            assert!(decoder.is_ok(), "{sound:?}");
+        }
+    }
+}
@@ -16,6 +16,21 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Return all directions in counter-clockwise order, starting with `Right`.
+    pub fn all() -> [Direction; 4] {
+        [Direction::Right, Direction::Up, Direction::Left, Direction::Down]
+    }
+
+    /// Return the direction after a clockwise quarter turn, e.g. `Down` for `Right`.
+    pub fn rotate_cw(self) -> Direction {
+        Self::all()[(self as usize + 3) % 4]
+    }
+
+    /// Return the direction after a counter-clockwise quarter turn, e.g. `Up` for `Right`.
+    pub fn rotate_ccw(self) -> Direction {
+        Self::all()[(self as usize + 1) % 4]
+    }
+
     pub(crate) fn to_vec2(self) -> Vec2 {
         match self {
             Direction::Up => Vec2::new(0, -1),
@@ -97,6 +112,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn all() {
+        assert!(Direction::all().into_iter().eq(Direction::iter()));
+    }
+
+    #[test]
+    fn rotation() {
+        assert_eq!(Direction::Right.rotate_cw(), Direction::Down);
+        assert_eq!(Direction::Down.rotate_cw(), Direction::Left);
+        assert_eq!(Direction::Right.rotate_ccw(), Direction::Up);
+        assert_eq!(Direction::Up.rotate_ccw(), Direction::Left);
+        for direction in Direction::all() {
+            let turned = (0..4).fold(direction, |d, _| d.rotate_cw());
+            assert_eq!(turned, direction);
+            assert_eq!(direction.rotate_cw().rotate_ccw(), direction);
+            assert_eq!(direction.rotate_ccw().rotate_ccw(), -direction);
+        }
+    }
+
     #[test]
     fn opposite() {
         assert_eq!(-Direction::Up, Direction::Down);
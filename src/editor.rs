@@ -0,0 +1,360 @@
+//! An editor to hand-author puzzles.
+//!
+//! The tiles are edited in the orientation of the solution: a click cycles the kind of the pipes
+//! of a tile, the mouse wheel rotates them and a right click places the source on the tile. A
+//! click close to the edge of a tile toggles a wall along that edge. Tiles are only rotated away
+//! from the solution when the puzzle is played or its code is copied.
+
+use eframe::egui;
+
+use crate::assets::Assets;
+use crate::game::{fit_tile_size, BoardLayout, Game, Settings, TileSprite, WallSprite};
+use crate::grid::{Direction, Vec2};
+use crate::puzzle::{Builder, Kind, Options, Puzzle, Tile, ValidationError};
+
+pub struct Editor {
+    assets: Assets,
+    settings: Settings,
+    puzzle: Puzzle, // the solution, all tiles are in their solved orientation
+    wall_sprites: Vec<WallSprite>,
+    validation: Result<(), ValidationError>, // of `puzzle`, which runs the solver, so cached
+    tile_size: f32,
+    scroll: f32, // accumulated mouse wheel movement which has not yet resulted in a rotation
+    message: Option<String>, // the result of copying the puzzle code
+}
+
+impl Editor {
+    const INNER_MARGIN: f32 = 10.;
+    const TOOLBAR_HEIGHT: f32 = 45.;
+
+    /// The width of the area along the edges of a tile in which a click toggles a wall, relative
+    /// to the tile size.
+    const EDGE_FRACTION: f32 = 0.2;
+
+    // Mouse wheel movement (in points) required for a quarter rotation, as in the game
+    const SCROLL_PER_QUARTER_ROTATION: f32 = 40.;
+
+    /// Create an editor for a board of the given options. The editor starts with a generated
+    /// puzzle in its solved state, which is quicker to adapt than an empty board.
+    pub fn new(options: Options, assets: Assets, settings: Settings) -> Self {
+        let mut puzzle = Builder::new().with_options(options).build();
+        puzzle.solve();
+        let wall_sprites = Game::create_wall_sprites(&puzzle, &assets);
+        let validation = puzzle.validate();
+        let tile_size = *settings.tile_size_range().start();
+
+        Self {
+            assets,
+            settings,
+            puzzle,
+            wall_sprites,
+            validation,
+            tile_size,
+            scroll: 0.,
+            message: None,
+        }
+    }
+
+    /// Return the puzzle as authored, i.e. in its solved state.
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    /// Return a playable puzzle, with tiles rotated away from the authored solution, or the
+    /// reason why the puzzle cannot be played.
+    pub fn export(&self) -> Result<Puzzle, ValidationError> {
+        self.validation?;
+        Ok(Builder::new().with_options(*self.puzzle.options()).jumble(&self.puzzle))
+    }
+
+    /// Replace the pipes of the tile at `coord` by the next kind, keeping the orientation.
+    fn cycle_kind(&mut self, coord: Vec2) {
+        let tile = self.puzzle.grid()[coord];
+        self.replace_tile(coord, Tile::new(next_kind(tile.kind()), tile.orientation()));
+    }
+
+    /// Rotate the tile at `coord` by a quarter turn.
+    fn rotate(&mut self, coord: Vec2, clockwise: bool) {
+        let tile = self.puzzle.grid()[coord];
+        let orientation = if clockwise {
+            tile.orientation().next_cw()
+        } else {
+            tile.orientation().next_ccw()
+        };
+        self.replace_tile(coord, Tile::new(tile.kind(), orientation));
+    }
+
+    fn replace_tile(&mut self, coord: Vec2, tile: Tile) {
+        self.puzzle.set_tile(coord, tile).expect("coord must be on the game board");
+        self.puzzle_changed();
+    }
+
+    fn set_source(&mut self, coord: Vec2) {
+        self.puzzle.set_source(coord).expect("coord must be on the game board");
+        self.puzzle_changed();
+    }
+
+    fn toggle_wall(&mut self, coord: Vec2, direction: Direction) {
+        self.puzzle
+            .toggle_wall_between(coord, direction)
+            .expect("coord must be on the game board");
+        self.wall_sprites = Game::create_wall_sprites(&self.puzzle, &self.assets);
+        self.puzzle_changed();
+    }
+
+    /// Validate the puzzle again after an edit and clear the message about the previous puzzle.
+    fn puzzle_changed(&mut self) {
+        self.validation = self.puzzle.validate();
+        self.message = None;
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Vec<EditorEvent> {
+        let available = ui.available_size()
+            - egui::Vec2::new(Self::INNER_MARGIN, Self::INNER_MARGIN + Self::TOOLBAR_HEIGHT);
+        self.tile_size = fit_tile_size(
+            available,
+            self.puzzle.grid().rows(),
+            self.puzzle.grid().cols(),
+            self.settings.tile_size_range(),
+        );
+
+        ui.vertical_centered(|ui| {
+            egui::ScrollArea::both()
+                .scroll_source(egui::scroll_area::ScrollSource::SCROLL_BAR)
+                .max_height(ui.available_height() - Self::TOOLBAR_HEIGHT)
+                .show(ui, |ui| self.update_board(ui));
+            ui.add_space(15.);
+            self.update_toolbar(ui)
+        })
+        .inner
+    }
+
+    fn update_board(&mut self, ui: &mut egui::Ui) {
+        let grid = self.puzzle.grid();
+        let extent = egui::Vec2::new(grid.cols() as f32, grid.rows() as f32) * self.tile_size;
+        ui.allocate_ui(extent + egui::Vec2::splat(Self::INNER_MARGIN), |ui| {
+            let top_left = ui.max_rect().left_top() + egui::Vec2::splat(Self::INNER_MARGIN / 2.);
+            let layout = BoardLayout::new(&self.puzzle, top_left, self.tile_size);
+
+            for coord in self.puzzle.grid().indices_iter() {
+                let tile = &self.puzzle.grid()[coord];
+                let rect = layout.tile_rect(coord);
+                TileSprite::draw(tile, tile.orientation().to_angle(), rect, &self.assets, &self.settings, ui);
+            }
+
+            // The whole board is a single widget, the tile under the pointer is looked up.
+            let response = ui.interact(layout.board_rect(), egui::Id::new("editor-board"), egui::Sense::click());
+            let hovered_tile = response.hover_pos().and_then(|pos| layout.screen_to_grid(pos));
+            let clicked_tile = response.interact_pointer_pos()
+                .and_then(|pos| Some((pos, layout.screen_to_grid(pos)?)));
+            let mut edit = None;
+            if let Some((pos, coord)) = clicked_tile {
+                if response.clicked() {
+                    let edge = edge_direction(pos - layout.tile_rect(coord).min, self.tile_size);
+                    edit = Some(match edge {
+                        Some(direction) => Edit::ToggleWall(coord, direction),
+                        None => Edit::CycleKind(coord),
+                    });
+                } else if response.secondary_clicked() {
+                    edit = Some(Edit::SetSource(coord));
+                }
+            }
+
+            for wall in &self.wall_sprites {
+                wall.draw(&layout, &self.settings, ui);
+            }
+
+            if let Err(ValidationError::UnconnectedTile(coord)) = self.validation {
+                let tint = egui::Color32::from_rgba_unmultiplied(220, 40, 40, 70);
+                ui.painter().rect_filled(layout.tile_rect(coord), 0., tint);
+            }
+
+            match hovered_tile {
+                Some(coord) => self.update_scroll(coord, ui),
+                None => self.scroll = 0.,
+            }
+            match edit {
+                Some(Edit::CycleKind(coord)) => self.cycle_kind(coord),
+                Some(Edit::SetSource(coord)) => self.set_source(coord),
+                Some(Edit::ToggleWall(coord, direction)) => self.toggle_wall(coord, direction),
+                None => (),
+            }
+        });
+    }
+
+    /// Rotate the hovered tile by a quarter turn per mouse wheel notch, like in the game.
+    fn update_scroll(&mut self, coord: Vec2, ui: &mut egui::Ui) {
+        let (delta, command) = ui.input(|i| (i.raw_scroll_delta.y, i.modifiers.command));
+        if command {
+            return;
+        }
+
+        self.scroll += delta;
+        while self.scroll.abs() >= Self::SCROLL_PER_QUARTER_ROTATION {
+            let clockwise = self.scroll < 0.;
+            self.scroll -= Self::SCROLL_PER_QUARTER_ROTATION.copysign(self.scroll);
+            self.rotate(coord, clockwise);
+        }
+    }
+
+    fn update_toolbar(&mut self, ui: &mut egui::Ui) -> Vec<EditorEvent> {
+        let mut events = vec![];
+        let validation = self.validation;
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 10.;
+            if ui.button(egui::RichText::new(egui_phosphor::regular::X.to_string()).size(12.))
+                .on_hover_text("Close the editor")
+                .clicked()
+            {
+                events.push(EditorEvent::Close);
+            }
+            if ui.add_enabled(
+                validation.is_ok(),
+                egui::Button::new(egui::RichText::new(
+                    egui_phosphor::regular::PLAY.to_string()).size(12.)))
+                .on_hover_text("Play the puzzle")
+                .clicked() &&
+                let Ok(puzzle) = self.export()
+            {
+                events.push(EditorEvent::Play(Box::new(puzzle)));
+            }
+            if ui.add_enabled(
+                validation.is_ok(),
+                egui::Button::new(egui::RichText::new(
+                    egui_phosphor::regular::COPY.to_string()).size(12.)))
+                .on_hover_text("Copy the puzzle code")
+                .clicked() &&
+                let Ok(puzzle) = self.export()
+            {
+                ui.ctx().copy_text(puzzle.to_code());
+                self.message = Some("Copied the puzzle code".to_string());
+            }
+            match (&validation, &self.message) {
+                (Err(error), _) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+                }
+                (Ok(()), Some(message)) => {
+                    ui.label(message);
+                }
+                (Ok(()), None) => {
+                    ui.label("Click: pipes, wheel: rotate, right click: source, edge: wall");
+                }
+            }
+        });
+        events
+    }
+}
+
+/// Return the next kind of pipes when cycling through all kinds.
+fn next_kind(kind: Kind) -> Kind {
+    match kind {
+        Kind::DeadEnd => Kind::Straight,
+        Kind::Straight => Kind::Corner,
+        Kind::Corner => Kind::TIntersection,
+        Kind::TIntersection => Kind::CrossIntersection,
+        Kind::CrossIntersection => Kind::DeadEnd,
+    }
+}
+
+/// Return the edge of a tile which is close to `offset`, the position relative to the top left
+/// corner of the tile, or `None` if `offset` is closer to the center.
+fn edge_direction(offset: egui::Vec2, tile_size: f32) -> Option<Direction> {
+    let distances = [
+        (tile_size - offset.x, Direction::Right),
+        (offset.y, Direction::Up),
+        (offset.x, Direction::Left),
+        (tile_size - offset.y, Direction::Down),
+    ];
+    distances
+        .into_iter()
+        .filter(|(distance, _)| *distance < tile_size * Editor::EDGE_FRACTION)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, direction)| direction)
+}
+
+/// A change of the puzzle requested by a click, applied after the board has been drawn.
+#[derive(Copy, Clone, Debug)]
+enum Edit {
+    CycleKind(Vec2),
+    SetSource(Vec2),
+    ToggleWall(Vec2, Direction),
+}
+
+pub enum EditorEvent {
+    Play(Box<Puzzle>), // the exported puzzle, see `Editor::export`
+    Close,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::Feature;
+
+    fn editor() -> Editor {
+        let context = egui::Context::default();
+        let mut assets = Assets::new();
+        assets.load_all(&context).unwrap();
+        let options = Options { board_size: 4, ..Default::default() };
+        Editor::new(options, assets, Settings::default())
+    }
+
+    #[test]
+    fn click_close_to_edge() {
+        assert_eq!(edge_direction(egui::Vec2::new(20., 20.), 40.), None);
+        assert_eq!(edge_direction(egui::Vec2::new(38., 20.), 40.), Some(Direction::Right));
+        assert_eq!(edge_direction(egui::Vec2::new(20., 2.), 40.), Some(Direction::Up));
+        assert_eq!(edge_direction(egui::Vec2::new(1., 3.), 40.), Some(Direction::Left));
+        assert_eq!(edge_direction(egui::Vec2::new(20., 39.), 40.), Some(Direction::Down));
+    }
+
+    #[test]
+    fn edits_keep_the_source() {
+        let mut editor = editor();
+        assert!(editor.export().is_ok());
+
+        let source = *editor.puzzle().source();
+        for _ in 0..5 {
+            editor.cycle_kind(source);
+        }
+        editor.rotate(source, true);
+        let tile = editor.puzzle().grid()[source];
+        assert_eq!(tile.feature(), Feature::Source);
+        assert_eq!(tile.orientation(), tile.solved_orientation());
+
+        let corner = Vec2::new(0, 0);
+        editor.set_source(corner);
+        assert_eq!(*editor.puzzle().source(), corner);
+        assert_eq!(editor.puzzle().grid()[corner].feature(), Feature::Source);
+        let sources = editor.puzzle().grid().iter().filter(|tile| tile.feature() == Feature::Source);
+        assert_eq!(sources.count(), 1);
+    }
+
+    #[test]
+    fn export_jumbles_the_solution() {
+        let mut editor = editor();
+        // Cut the source off from the rest of the board.
+        let source = *editor.puzzle().source();
+        for direction in Direction::all() {
+            if editor.puzzle().connected(source, direction) {
+                editor.toggle_wall(source, direction);
+            }
+        }
+        assert!(matches!(editor.export(), Err(ValidationError::UnconnectedTile(_))));
+        assert_eq!(editor.validation, editor.puzzle().validate());
+        for direction in Direction::all() {
+            if editor.puzzle().walls_around(source)[direction as usize] {
+                editor.toggle_wall(source, direction);
+            }
+        }
+
+        assert_eq!(editor.validation, Ok(()));
+
+        let solution = editor.puzzle().clone();
+        let mut puzzle = editor.export().unwrap();
+        assert!(!puzzle.solved());
+        assert!(puzzle.expected_moves() >= 1);
+        assert!(Puzzle::from_code(&puzzle.to_code()).unwrap() == puzzle);
+        puzzle.solve();
+        assert!(puzzle == solution);
+    }
+}
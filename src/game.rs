@@ -1,12 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use eframe::{egui, Storage};
+use rand::seq::IndexedRandom;
 
 use crate::assets::{AssetType, Assets};
+#[cfg(feature = "audio")]
+use crate::audio::{Audio, Sound};
 use crate::grid::{Direction, Grid, Vec2};
-use crate::modals::{PauseModal, PauseModalEvent, PuzzleSolvedModal, PuzzleSolvedModalEvent};
-use crate::puzzle::{Feature, Kind, Alignment, Puzzle, Orientation, Tile};
-
+use crate::high_scores::Record;
+use crate::modals::{
+    format_duration, ConfirmNewGameModal, ConfirmNewGameModalEvent, PauseModal, PauseModalEvent,
+    PuzzleSolvedModal, PuzzleSolvedModalEvent, SettingsModal, SettingsModalEvent, TimeUpModal,
+    TimeUpModalEvent, TutorialModal, TutorialModalEvent,
+};
+use crate::puzzle::{Feature, Kind, Alignment, Puzzle, Options, Orientation, Tile};
+
+// The size of a tile in images of the game board, and the resolution of the embedded tileset.
 const TILE_SIZE: f32 = 40.;
 
 pub struct Game {
@@ -14,15 +23,42 @@ pub struct Game {
     starting_position: Puzzle,
     puzzle: Puzzle,
     tile_widgets: Grid<TileSprite>,
+    locked: Grid<bool>, // locked tiles cannot be rotated by the player
     wall_sprites: Vec<WallSprite>,
     state: GameState,
     timer: Timer,
     move_counter: MoveCounter,
+    history: History,
+    assistance: Assistance,
+    cursor: Option<Vec2>, // the tile focused with the keyboard, None until the keyboard is used
     settings: Settings,
+    previous_best: Option<Record>,
+    export_message: Option<String>, // the result of saving an image, shown in the pause modal
+    // A request for a new game which awaits confirmation, and the state to return to on cancel
+    pending_new_game: Option<(GameEvent, GameState)>, // also a request to quit to the menu
+    settings_modal: Option<SettingsModal>, // opened from the pause modal
+    solved_reported: bool, // whether GameEvent::Solved has been pushed
+    auto_solve: Option<VecDeque<Vec2>>, // the tiles the solver still rotates, while it plays
+    path_hint: Option<PathHint>, // highlighted for a while after it was requested
+    show_tutorial: bool, // shown on first run and reopened from the status bar
+    tile_size: f32, // the size of a tile on screen, fitted to the available space in each frame
+    #[cfg(feature = "audio")]
+    audio: Option<std::rc::Rc<Audio>>,
 }
 
 impl Game {
     const INNER_MARGIN: f32 = 10.;
+    // The space below the game board taken by the status bar
+    const STATUS_BAR_HEIGHT: f32 = 45.;
+    // The range of the zoom factor in percent
+    const MIN_ZOOM: u32 = 50;
+    const MAX_ZOOM: u32 = 400;
+    // The change of the zoom factor per key press
+    const ZOOM_STEP: f32 = 1.1;
+    const MOVE_HISTORY_WIDTH: f32 = 160.;
+    // How long a path hint is shown, in seconds
+    const PATH_HINT_DURATION: f64 = 4.;
+    const STORAGE_KEY: &'static str = "game";
 
     /// Create a new game.
     pub fn new(puzzle: Puzzle, assets: Assets, settings: Settings) -> Self {
@@ -31,34 +67,124 @@ impl Game {
         let starting_position = puzzle.clone();
 
         let wall_sprites = Self::create_wall_sprites(&puzzle, &assets);
+        let show_tutorial = !settings.tutorial_seen;
 
         Self {
             assets,
             starting_position,
             puzzle,
             tile_widgets: Grid::<TileSprite>::with_size(rows, cols, TileSprite::default()),
+            locked: Grid::<bool>::with_size(rows, cols, false),
             wall_sprites,
             state: GameState::BeforeStart,
             timer: Timer::default(),
             move_counter: MoveCounter::default(),
+            history: History::default(),
+            assistance: Assistance::default(),
+            cursor: None,
             settings,
+            previous_best: None,
+            export_message: None,
+            pending_new_game: None,
+            settings_modal: None,
+            solved_reported: false,
+            auto_solve: None,
+            path_hint: None,
+            show_tutorial,
+            tile_size: TILE_SIZE,
+            #[cfg(feature = "audio")]
+            audio: None,
+        }
+    }
+
+    /// Supply the best results achieved so far with the options of this game. They are shown
+    /// when the puzzle is solved.
+    pub fn with_previous_best(mut self, previous_best: Option<Record>) -> Self {
+        self.previous_best = previous_best;
+        self
+    }
+
+    /// Supply the audio output for sound effects. Without it, the game is silent.
+    #[cfg(feature = "audio")]
+    pub fn with_audio(mut self, audio: Option<std::rc::Rc<Audio>>) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    pub fn options(&self) -> &Options {
+        self.puzzle.options()
+    }
+
+    /// Restore a game previously stored with `save`. Return `None` if there is no stored game or
+    /// it cannot be read.
+    pub fn restore(storage: &dyn Storage, assets: Assets) -> Option<Self> {
+        let saved: SavedGame = eframe::get_value(storage, Self::STORAGE_KEY)?;
+        if !saved.is_consistent() {
+            return None;
+        }
+        let settings = Settings::read(storage);
+
+        let mut game = Self::new(saved.starting_position, assets, settings);
+        game.puzzle = saved.puzzle;
+        game.puzzle.calc_energy();
+        for (locked, saved_locked) in game.locked.iter_mut().zip(saved.locked.iter()) {
+            *locked = *saved_locked;
+        }
+        game.timer.total = saved.elapsed;
+        game.move_counter.move_count = saved.move_count;
+        game.assistance = saved.assistance;
+        if saved.move_count > 0 {
+            game.state = GameState::Running;
+            // The time is unknown before the first frame, the timer starts counting with its
+            // first update.
+            game.timer.running = true;
         }
+
+        Some(game)
+    }
+
+    /// Store the game, so it can be restored with `restore` on the next launch. A solved puzzle
+    /// is not stored; instead a previously stored game is removed.
+    pub fn save(&self, storage: &mut dyn Storage) {
+        if matches!(self.state, GameState::Ended { .. }) {
+            Self::clear_saved(storage);
+            return;
+        }
+
+        let saved = SavedGame {
+            starting_position: self.starting_position.clone(),
+            puzzle: self.puzzle.clone(),
+            locked: self.locked.clone(),
+            elapsed: self.timer.duration(),
+            move_count: self.move_counter.get(),
+            assistance: self.assistance,
+        };
+        eframe::set_value(storage, Self::STORAGE_KEY, &saved);
+    }
+
+    /// Remove a game stored with `save`.
+    pub fn clear_saved(storage: &mut dyn Storage) {
+        storage.set_string(Self::STORAGE_KEY, String::new());
     }
 
     /// Create wall sprite from the puzzle's wall objects. If playing on a torus, create the
     /// wall sprites along the seam twice (left and right, top and bottom).
-    fn create_wall_sprites(puzzle: &Puzzle, assets: &Assets) -> Vec<WallSprite> {
-        puzzle.walls().iter().flat_map(|wall| {
+    ///
+    /// The sprites store positions on the grid, the tile size is supplied when they are drawn.
+    pub(crate) fn create_wall_sprites(puzzle: &Puzzle, assets: &Assets) -> Vec<WallSprite> {
+        let rows = puzzle.grid().rows() as i32;
+        let cols = puzzle.grid().cols() as i32;
+        puzzle.walls_sorted().into_iter().flat_map(|wall| {
             let basic_sprite = WallSprite::new(wall.position(), wall.orientation(), assets);
 
             if puzzle.options().wrapping {
                 if wall.position().x == 0 && wall.orientation() == Alignment::Vertical {
-                    let pos = wall.position() + Vec2::new(puzzle.options().board_size as i32, 0);
+                    let pos = wall.position() + Vec2::new(cols, 0);
                     let dual_sprite = WallSprite::new(pos, wall.orientation(), assets);
                     return vec![basic_sprite, dual_sprite];
                 }
                 if wall.position().y == 0 && wall.orientation() == Alignment::Horizontal {
-                    let pos = wall.position() + Vec2::new(0, puzzle.options().board_size as i32);
+                    let pos = wall.position() + Vec2::new(0, rows);
                     let dual_sprite = WallSprite::new(pos, wall.orientation(), assets);
                     return vec![basic_sprite, dual_sprite];
                 }
@@ -68,229 +194,1009 @@ impl Game {
         }).collect()
     }
 
+    /// Render the game board offscreen, e.g. to share a screenshot of the current state.
+    ///
+    /// If `show_power` is false, all tiles are drawn as unpowered, which hides whether the puzzle
+    /// is solved.
+    pub fn render_to_image(&self, show_power: bool) -> image::RgbaImage {
+        let tile_size = TILE_SIZE as u32;
+        let board_size = self.puzzle.size() as u32 * tile_size;
+        let background = egui::Visuals::dark().panel_fill.to_array();
+        let mut canvas = image::RgbaImage::from_pixel(board_size, board_size, image::Rgba(background));
+
+        let mut draw = |asset_type: AssetType, rotation: Orientation, x: i64, y: i64| {
+            let image = self.assets
+                .get_rotated_image(asset_type, rotation)
+                .expect("image not found");
+            if image.dimensions() == (tile_size, tile_size) {
+                image::imageops::overlay(&mut canvas, image, x, y);
+            } else {
+                // Custom tilesets may have a different resolution.
+                let image = image::imageops::resize(
+                    image, tile_size, tile_size, image::imageops::FilterType::Triangle);
+                image::imageops::overlay(&mut canvas, &image, x, y);
+            }
+        };
+
+        for (index, tile) in self.puzzle.grid().indexed_iter() {
+            let (x, y) = (index.x as i64 * tile_size as i64, index.y as i64 * tile_size as i64);
+            let powered = show_power && tile.powered();
+            draw(link_asset_type(tile.kind(), powered), tile.orientation(), x, y);
+            if let Some(asset_type) = feature_asset_type(tile.feature(), powered) {
+                draw(asset_type, Orientation::Basic, x, y);
+            }
+        }
+
+        // Walls are centered on the edge between two tiles. The dual walls of a wrapping board
+        // and the parts of walls beyond the board are clipped.
+        let half = tile_size as i64 / 2;
+        let size = self.puzzle.size() as i64;
+        for wall in self.puzzle.walls_sorted() {
+            let (offset, rotation) = match wall.orientation() {
+                Alignment::Horizontal => ((0, -half), Orientation::Ccw90),
+                Alignment::Vertical => ((-half, 0), Orientation::Basic),
+            };
+            let x = wall.position().x as i64 * tile_size as i64 + offset.0;
+            let y = wall.position().y as i64 * tile_size as i64 + offset.1;
+            draw(AssetType::Wall, rotation, x, y);
+            if self.puzzle.options().wrapping {
+                match wall.orientation() {
+                    Alignment::Horizontal if wall.position().y == 0 => {
+                        draw(AssetType::Wall, rotation, x, y + size * tile_size as i64)
+                    }
+                    Alignment::Vertical if wall.position().x == 0 => {
+                        draw(AssetType::Wall, rotation, x + size * tile_size as i64, y)
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        canvas
+    }
+
+    /// Save an image of the game board as PNG file in the current working directory. Return the
+    /// path of the file.
+    fn save_image(&self, show_power: bool) -> Result<std::path::PathBuf, image::ImageError> {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = std::env::current_dir()
+            .unwrap_or_default()
+            .join(format!("netwalk-{seconds}.png"));
+        self.render_to_image(show_power).save(&path)?;
+        Ok(path)
+    }
+
     /// Restart the puzzle.
     pub fn restart(&mut self) {
         self.puzzle = self.starting_position.clone();
-        let size = self.puzzle.grid().rows();
-        self.tile_widgets = Grid::<TileSprite>::with_size(size, size, TileSprite::default());
+        self.tile_widgets.fill(TileSprite::default());
+        self.locked.fill(false);
         self.state = GameState::BeforeStart;
         self.timer = Timer::default();
         self.move_counter = MoveCounter::default();
+        self.history = History::default();
+        self.assistance = Assistance::default();
+        self.solved_reported = false;
+        self.auto_solve = None;
+        self.path_hint = None;
     }
 
-    /// Calculate the score.
-    pub fn calc_score(&self) -> u32 {
-        let weights = HashMap::from([
-            (Kind::DeadEnd, 4),
-            (Kind::Corner, 4),
-            (Kind::Straight, 2),
-            (Kind::TIntersection, 4),
-            (Kind::CrossIntersection, 0),
-        ]);
-        let mut score: usize = self.puzzle.grid().iter()
-            .map(|tile| {
-                weights
-                    .get(&tile.kind())
-                    .expect("link type must be in map of weights")
+    /// Return true if the tile at `coord` is locked, or `None` if `coord` is not on the grid.
+    /// Locked tiles cannot be rotated by the player, but hints and the solver unlock them.
+    pub fn is_locked(&self, coord: Vec2) -> Option<bool> {
+        self.locked.get(coord).copied()
+    }
+
+    /// Lock or unlock the tile at `coord`. The power of the tiles is not affected.
+    ///
+    /// # Panics
+    /// This function panics if `coord` is not on the grid.
+    pub fn set_locked(&mut self, coord: Vec2, locked: bool) {
+        *self.locked.get_mut(coord).expect("coord must be on the grid") = locked;
+    }
+
+    /// Lock every tile which is in the orientation of the generator's solution, so only the
+    /// remaining tiles can be rotated. Crosses look the same in every orientation and stay
+    /// unlocked.
+    ///
+    /// This reveals which tiles are correct, just like the setting to show mistakes, and reduces
+    /// the score the same way.
+    pub fn lock_correct_tiles(&mut self) {
+        if !matches!(self.state, GameState::BeforeStart | GameState::Running) {
+            return;
+        }
+        for (index, tile) in self.puzzle.grid().indexed_iter() {
+            // A rotating tile is judged once it has stopped.
+            if tile.is_correct() && !tile.kind().is_cross_intersection() &&
+                self.tile_widgets[index].animation.is_none() {
+                self.locked[index] = true;
+            }
+        }
+        self.assistance.mistakes_shown = true;
+    }
+
+    /// Rotate one incorrect tile into the orientation of the generator's solution and mark it as
+    /// a hint. Each hint reduces the final score. Does nothing if the game has ended or every
+    /// tile which is not currently rotating is already correct.
+    pub fn hint(&mut self) {
+        if matches!(self.state, GameState::Ended { .. } | GameState::Paused { .. }) {
+            return;
+        }
+
+        let candidates = self.puzzle.grid().indexed_iter()
+            .filter(|(index, tile)| {
+                !tile.is_correct() && self.tile_widgets[*index].animation.is_none()
             })
-            .sum();
-        score -= 2 * self.puzzle.walls().len();
-        if !self.puzzle.options().wrapping {
-            score -= self.puzzle.grid().rows() + self.puzzle.grid().cols();
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if let Some(&index) = candidates.choose(&mut rand::rng()) {
+            let quarters = quarters_to_solution(self.puzzle.grid()[index], self.settings.rotate_clockwise);
+            self.locked[index] = false;
+            let sprite = &mut self.tile_widgets[index];
+            sprite.hinted = true;
+            sprite.start_rotation(quarters, self.settings.rotate_clockwise, self.settings.animation_time());
+            self.assistance.hints_used += 1;
+        }
+    }
+
+    /// Highlight the tiles along the solution from the source to a random drain which is not
+    /// powered, without rotating them. This is a softer hint than `hint` and reduces the final
+    /// score by half as much. Does nothing if the game has ended or every drain is powered.
+    pub fn hint_path(&mut self) {
+        if matches!(self.state, GameState::Ended { .. } | GameState::Paused { .. }) {
+            return;
         }
-        let mut score = score as f32
-            * (self.puzzle.expected_moves() as f32
-                / (self.puzzle.grid().rows() * self.puzzle.grid().cols()) as f32);
-        score = score * score / self.timer.duration().as_secs() as f32;
 
-        score.round() as u32
+        let drains = self.puzzle.drains().into_iter()
+            .filter(|&drain| !self.puzzle.grid()[drain].powered())
+            .collect::<Vec<_>>();
+        if let Some(&drain) = drains.choose(&mut rand::rng()) &&
+            let Some(tiles) = self.puzzle.solution_path(drain) {
+            self.path_hint = Some(PathHint { tiles, shown_at: None });
+            self.assistance.path_hints_used += 1;
+        }
+    }
+
+    /// Start playing the solution, or stop it if it is already playing. The solver rotates one
+    /// tile after another with the rotation animation, so the pace follows the animation setting.
+    ///
+    /// Once the solver has started, the game counts as solved by assist and scores zero, even if
+    /// the solver is stopped before the puzzle is solved.
+    pub fn toggle_auto_solve(&mut self) {
+        if self.auto_solve.take().is_some() {
+            return;
+        }
+        if matches!(self.state, GameState::BeforeStart | GameState::Running) {
+            self.auto_solve = Some(VecDeque::new());
+            self.assistance.auto_solved = true;
+        }
+    }
+
+    /// Start the rotation of the next tile of the solution once no tile is rotating anymore.
+    fn advance_auto_solve(&mut self, ui: &mut egui::Ui) {
+        let Some(queue) = self.auto_solve.as_mut() else {
+            return;
+        };
+        ui.ctx().request_repaint();
+        if self.tile_widgets.iter().any(|sprite| sprite.animation.is_some()) {
+            return;
+        }
+        // The player may still rotate tiles, so check the puzzle again once the queue is empty.
+        if queue.is_empty() {
+            queue.extend(self.puzzle.solution_moves().into_iter().map(|(coord, _)| coord));
+        }
+        let Some(coord) = queue.pop_front() else {
+            self.auto_solve = None;
+            return;
+        };
+        let quarters = quarters_to_solution(self.puzzle.grid()[coord], self.settings.rotate_clockwise);
+        if quarters > 0 {
+            self.locked[coord] = false;
+            self.tile_widgets[coord].start_rotation(quarters, self.settings.rotate_clockwise, self.settings.animation_time());
+        }
+    }
+
+    /// Revert the last rotation. Does nothing if there is nothing to undo or the game has ended.
+    pub fn undo(&mut self) {
+        if matches!(self.state, GameState::Ended { .. }) {
+            return;
+        }
+        if let Some(rotation) = self.history.undo() {
+            let tile = &mut self.puzzle.grid_mut()[rotation.coord];
+            for _ in 0..(4 - rotation.quarters % 4) {
+                tile.rotate();
+            }
+            self.move_counter.revert(rotation.counted, self.history.last_rotated_tile());
+            self.rotation_applied(rotation.coord);
+        }
+    }
+
+    /// Re-apply the last undone rotation. Does nothing if there is nothing to redo or the game
+    /// has ended.
+    pub fn redo(&mut self) {
+        if matches!(self.state, GameState::Ended { .. }) {
+            return;
+        }
+        if let Some(rotation) = self.history.redo() {
+            let tile = &mut self.puzzle.grid_mut()[rotation.coord];
+            for _ in 0..rotation.quarters {
+                tile.rotate();
+            }
+            self.move_counter.update(rotation.coord);
+            self.rotation_applied(rotation.coord);
+        }
+    }
+
+    /// Pause the game and show the pause modal, which continues the game when it is closed.
+    fn pause(&mut self) {
+        self.state = GameState::Paused { game_was_started: self.state != GameState::BeforeStart };
+        self.timer.stop();
+    }
+
+    /// Undo or redo rotations until `moves` rotations have been applied, e.g. after clicking an
+    /// entry of the move history. Does nothing if the game has ended.
+    pub fn go_to_move(&mut self, moves: usize) {
+        if matches!(self.state, GameState::Ended { .. }) {
+            return;
+        }
+        while self.history.applied() > moves && self.history.can_undo() {
+            self.undo();
+        }
+        while self.history.applied() < moves && self.history.can_redo() {
+            self.redo();
+        }
+    }
+
+    /// Move the keyboard cursor with the arrow keys, rotate the focused tile with space or enter
+    /// and toggle its lock with L. The first key press shows the cursor on the source tile.
+    fn handle_cursor_keys(&mut self, ui: &mut egui::Ui) {
+        let arrows = [
+            (egui::Key::ArrowRight, Direction::Right),
+            (egui::Key::ArrowUp, Direction::Up),
+            (egui::Key::ArrowLeft, Direction::Left),
+            (egui::Key::ArrowDown, Direction::Down),
+        ];
+        let wrapping = self.puzzle.options().wrapping;
+        for (key, direction) in arrows {
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key)) {
+                self.cursor = Some(match self.cursor {
+                    Some(cursor) => move_cursor(self.puzzle.grid(), cursor, direction, wrapping),
+                    None => *self.puzzle.source(),
+                });
+            }
+        }
+
+        let rotate = ui.input_mut(|i| {
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Space)
+                | i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+        });
+        let lock = ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::L));
+        if let Some(cursor) = self.cursor {
+            if rotate && !self.locked[cursor] {
+                self.tile_widgets[cursor]
+                    .queue_rotation(self.settings.rotate_clockwise, self.settings.animation_time());
+            }
+            if lock {
+                self.locked[cursor] = !self.locked[cursor];
+            }
+        }
+    }
+
+    /// Zoom the game board with Ctrl+scroll, pinch gestures and the +/- keys. Return true if the
+    /// zoom factor has changed.
+    fn handle_zoom(&mut self, ui: &egui::Ui) -> bool {
+        let (zoom_delta, zoom_in, zoom_out) = ui.input(|i| {
+            // Ctrl and +/- zoom the whole user interface.
+            let keys = !i.modifiers.command;
+            let zoom_in = keys && (i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals));
+            (i.zoom_delta(), zoom_in, keys && i.key_pressed(egui::Key::Minus))
+        });
+
+        let mut zoom = self.settings.zoom_percent as f32 * zoom_delta;
+        if zoom_in {
+            zoom *= Self::ZOOM_STEP;
+        }
+        if zoom_out {
+            zoom /= Self::ZOOM_STEP;
+        }
+        let zoom = (zoom.round() as u32).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let changed = zoom != self.settings.zoom_percent;
+        self.settings.zoom_percent = zoom;
+        changed
+    }
+
+    /// Play `sound` unless the sound effects are muted.
+    #[cfg(feature = "audio")]
+    fn play_sound(&self, sound: Sound) {
+        if let Some(audio) = &self.audio && !self.settings.mute {
+            audio.play(sound, self.settings.volume_percent as f32 / 100.);
+        }
+    }
+
+    /// Recalculate the energy flow after the tile at `coord` has been rotated and check if the
+    /// puzzle is solved.
+    fn rotation_applied(&mut self, coord: Vec2) {
+        let newly_powered = self.puzzle.calc_energy_after(coord);
+        self.start_power_pulses(coord, &newly_powered);
+        self.check_solved();
+    }
+
+    /// Let the tiles which have just become powered by the rotation of the tile at `rotated`
+    /// blend into their powered look, unless the power pulse is disabled in the settings.
+    ///
+    /// The energy enters the newly powered tiles through the rotated tile, so the pulse of each
+    /// tile is delayed by its distance from the rotated tile along the connections. The pulse
+    /// flows outward like the energy.
+    fn start_power_pulses(&mut self, rotated: Vec2, newly_powered: &[Vec2]) {
+        if !self.settings.power_pulse {
+            return;
+        }
+        let mut distances = HashMap::from([(rotated, 0)]);
+        let mut queue = VecDeque::from([rotated]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current];
+            for direction in Direction::all() {
+                let neighbor = self.puzzle.grid().normalized_coord(current + direction.to_vec2());
+                if newly_powered.contains(&neighbor) && !distances.contains_key(&neighbor)
+                    && self.puzzle.connected(current, direction)
+                {
+                    distances.insert(neighbor, distance + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        for &coord in newly_powered {
+            let steps = distances.get(&coord).copied().unwrap_or(0);
+            let delay = steps as f32 * TileSprite::POWER_PULSE_STEP;
+            // A negative time waits for the pulse to arrive.
+            self.tile_widgets[coord].power_pulse = Some(-delay);
+        }
+    }
+
+    /// End the game if the puzzle is solved.
+    fn check_solved(&mut self) {
+        if self.puzzle.solved() {
+            // A practice game has no score.
+            let score = if self.puzzle.options().practice { 0 } else { self.calc_score() };
+            self.state = GameState::Ended { score, solved: true };
+            self.auto_solve = None;
+        }
+    }
+
+    /// End the game unsolved with a score of zero if the puzzle has a time limit which is
+    /// exceeded. A practice game has no time limit.
+    fn check_time_limit(&mut self) {
+        if let Some(limit) = self.puzzle.options().time_limit && !self.puzzle.options().practice &&
+            self.state == GameState::Running && self.timer.duration() >= limit {
+            self.timer.stop();
+            self.state = GameState::Ended { score: 0, solved: false };
+        }
+    }
+
+    /// Calculate the score.
+    pub fn calc_score(&self) -> u32 {
+        calc_score(&self.puzzle, self.timer.duration(), self.assistance)
     }
 
     pub fn update(&mut self, ui: &mut egui::Ui) -> Vec<GameEvent> {
+        // The clock starts with the first move, or as soon as the board is visible. The tutorial
+        // covers the board until it is dismissed.
+        if self.state == GameState::BeforeStart && self.settings.timer_starts_immediately &&
+            !self.show_tutorial {
+            self.state = GameState::Running;
+            self.timer.start(ui.input(|i| i.time));
+        }
+
         if self.state == GameState::Running {
             self.timer.update(ui.input(|i| i.time));
+            self.check_time_limit();
             ui.ctx().request_repaint_after(std::time::Duration::from_millis(100));
         }
 
+        // The board cannot be changed while the tutorial covers it. The tutorial stops the timer,
+        // so moves behind it would not be timed.
+        let board_input = !self.show_tutorial;
+
+        if self.state == GameState::Running && board_input {
+            let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+            let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+            if ui.input_mut(|i| i.consume_shortcut(&undo)) {
+                self.undo();
+            }
+            if ui.input_mut(|i| i.consume_shortcut(&redo)) {
+                self.redo();
+            }
+        }
+
+        let mut shortcut_events = Vec::new();
+        if matches!(self.state, GameState::BeforeStart | GameState::Running) {
+            if self.settings.show_mistakes {
+                self.assistance.mistakes_shown = true;
+            }
+            if board_input {
+                self.handle_shortcuts(&mut shortcut_events, ui);
+                self.handle_cursor_keys(ui);
+                self.advance_auto_solve(ui);
+            }
+        }
+
+        if self.handle_zoom(ui) {
+            shortcut_events.push(GameEvent::SettingsChanged(self.settings.clone()));
+        }
+        let available = ui.available_size()
+            - egui::Vec2::new(Self::INNER_MARGIN, Self::INNER_MARGIN + Self::STATUS_BAR_HEIGHT);
+        let fitted_size = fit_tile_size(
+            available,
+            self.puzzle.grid().rows(),
+            self.puzzle.grid().cols(),
+            self.settings.tile_size_range(),
+        );
+        self.tile_size = (fitted_size * self.settings.zoom_percent as f32 / 100.).floor().max(1.);
+
+        if self.settings.show_move_history &&
+            let Some(moves) = self.update_move_history(ui) {
+            self.go_to_move(moves);
+        }
+
         let mut events = ui
             .vertical_centered(|ui| {
-                self.update_game_board(ui);
+                // A zoomed board may exceed the window. The mouse wheel rotates tiles, so the board
+                // is only scrolled with the scroll bars.
+                egui::ScrollArea::both()
+                    .scroll_source(egui::scroll_area::ScrollSource::SCROLL_BAR)
+                    .max_height(ui.available_height() - Self::STATUS_BAR_HEIGHT)
+                    .show(ui, |ui| self.update_game_board(ui));
                 ui.add_space(15.);
                 let events = self.update_status_bar(ui);
                 for event in &events {
-                    if event == &GameEvent::Pause {
-                        self.state = GameState::Paused {
-                            game_was_started: self.state != GameState::BeforeStart,
-                        };
-                        self.timer.stop();
+                    match event {
+                        GameEvent::Pause => self.pause(),
+                        GameEvent::Undo => self.undo(),
+                        GameEvent::Redo => self.redo(),
+                        GameEvent::Hint => self.hint(),
+                        GameEvent::PathHint => self.hint_path(),
+                        GameEvent::AutoSolve => self.toggle_auto_solve(),
+                        GameEvent::LockCorrect => self.lock_correct_tiles(),
+                        GameEvent::Tutorial => {
+                            self.show_tutorial = true;
+                            self.timer.stop();
+                        }
+                        _ => (),
                     }
                 }
                 events
             })
             .inner;
+        events.extend(shortcut_events);
 
-        if let GameState::Paused { game_was_started } = self.state {
-            let response = PauseModal::new().update(ui);
+        let mut confirmed = false; // the abandon modal was just confirmed, do not ask again
+
+        if self.show_tutorial {
+            if let Some(TutorialModalEvent::Dismiss) = TutorialModal::new().update(ui) {
+                self.show_tutorial = false;
+                if self.state == GameState::Running {
+                    self.timer.start(ui.input(|i| i.time));
+                }
+                if !self.settings.tutorial_seen {
+                    self.settings.tutorial_seen = true;
+                    events.push(GameEvent::SettingsChanged(self.settings.clone()));
+                }
+            }
+        } else if let Some((pending_event, previous_state)) = &self.pending_new_game {
+            let previous_state = *previous_state;
+            let quit = *pending_event == GameEvent::Close;
+            let restart = *pending_event == GameEvent::Restart;
+            match ConfirmNewGameModal::new().with_quit(quit).with_restart(restart).update(ui) {
+                None => {}
+                Some(ConfirmNewGameModalEvent::Discard) => {
+                    let (event, _) = self.pending_new_game.take().expect("checked above");
+                    events.push(event);
+                    confirmed = true;
+                }
+                Some(ConfirmNewGameModalEvent::Cancel) => {
+                    self.pending_new_game = None;
+                    self.state = previous_state;
+                    if previous_state == GameState::Running {
+                        self.timer.start(ui.input(|i| i.time));
+                    }
+                }
+            }
+        } else if let Some(settings_modal) = self.settings_modal.as_mut() {
+            match settings_modal.update(ui) {
+                None => {}
+                Some(SettingsModalEvent::Apply(settings)) => {
+                    self.settings = settings;
+                    self.settings_modal = None;
+                    events.push(GameEvent::SettingsChanged(self.settings.clone()));
+                }
+                Some(SettingsModalEvent::Cancel) => self.settings_modal = None,
+            }
+        } else if let GameState::Paused { game_was_started } = self.state {
+            let response = PauseModal::new(
+                self.timer.duration(),
+                self.move_counter.get(),
+                self.puzzle.expected_moves(),
+                self.puzzle.powered_fraction(),
+            )
+            .with_export_power(self.settings.export_power)
+            .with_message(self.export_message.as_deref())
+            .with_description(match (self.puzzle.date(), self.puzzle.seed()) {
+                (Some(date), _) => Some(format!("Daily puzzle of {date}")),
+                (None, Some(seed)) => Some(format!("Seed {seed}")),
+                (None, None) => None,
+            })
+            .update(ui);
             match response {
                 None => {}
                 Some(PauseModalEvent::Continue) => {
+                    self.export_message = None;
                     if game_was_started {
                         self.state = GameState::Running;
-                        self.timer.start();
+                        self.timer.start(ui.input(|i| i.time));
                     } else {
                         self.state = GameState::BeforeStart;
                     }
                 }
+                Some(PauseModalEvent::CopyCode) => {
+                    ui.ctx().copy_text(self.starting_position.to_code());
+                }
+                Some(PauseModalEvent::SaveImage) => {
+                    self.export_message = Some(match self.save_image(self.settings.export_power) {
+                        Ok(path) => format!("Saved to {}", path.display()),
+                        Err(error) => format!("Could not save image: {error}"),
+                    });
+                }
+                Some(PauseModalEvent::ExportPowerChanged(export_power)) => {
+                    self.settings.export_power = export_power;
+                    events.push(GameEvent::SettingsChanged(self.settings.clone()));
+                }
+                Some(PauseModalEvent::Settings) => {
+                    self.settings_modal = Some(SettingsModal::new(self.settings.clone()));
+                }
                 Some(PauseModalEvent::NewGame) => events.push(GameEvent::NewGame),
+                Some(PauseModalEvent::Quit) => events.push(GameEvent::Close),
                 Some(PauseModalEvent::Restart) => {
                     self.restart();
                 }
             }
-        } else if let GameState::Ended { score } = self.state {
-            let response = PuzzleSolvedModal::new(
+        } else if let GameState::Ended { solved: false, .. } = self.state {
+            let time_limit = self.puzzle.options().time_limit.unwrap_or_default();
+            let response = TimeUpModal::new(time_limit, self.puzzle.powered_fraction()).update(ui);
+            match response {
+                None => {}
+                Some(TimeUpModalEvent::NewGame) => events.push(GameEvent::NewGame),
+                Some(TimeUpModalEvent::Restart) => self.restart(),
+            }
+        } else if let GameState::Ended { score, .. } = self.state {
+            if !self.solved_reported {
+                self.solved_reported = true;
+                #[cfg(feature = "audio")]
+                self.play_sound(Sound::Solved);
+                events.push(GameEvent::Solved {
+                    time: self.timer.duration(),
+                    moves: self.move_counter.get(),
+                    expected_moves: self.puzzle.expected_moves(),
+                    score,
+                    code: self.starting_position.to_code(),
+                    assisted: self.assistance.auto_solved,
+                });
+            }
+            let mut modal = PuzzleSolvedModal::new(
                 self.timer.duration(),
                 self.move_counter.get(),
                 self.puzzle.expected_moves(),
                 score,
+                *self.puzzle.options(),
+                self.starting_position.to_code(),
             )
-            .update(ui);
-            if let Some(PuzzleSolvedModalEvent::NewGame) = response {
-                events.push(GameEvent::NewGame);
+            .with_previous_best(self.previous_best)
+            .with_assisted(self.assistance.auto_solved)
+            .with_practice(self.puzzle.options().practice)
+            .with_par_time(self.puzzle.par_time());
+            match modal.update(ui) {
+                None => {}
+                Some(PuzzleSolvedModalEvent::NewGame) => events.push(GameEvent::NewGame),
+                Some(PuzzleSolvedModalEvent::CopyResult) => ui.ctx().copy_text(modal.result_text()),
             }
         }
 
+        if !confirmed {
+            self.confirm_new_game(&mut events);
+        }
+        if events.contains(&GameEvent::Restart) {
+            self.restart();
+        }
         events
     }
 
+    /// Handle the keyboard shortcuts of a game which has not ended. Shortcuts without a modifier
+    /// only apply if no modifier is held, so they do not fire along with those of the system.
+    fn handle_shortcuts(&mut self, events: &mut Vec<GameEvent>, ui: &mut egui::Ui) {
+        let pressed = |modifiers, key| ui.input_mut(|i| i.consume_key(modifiers, key));
+        let pause = pressed(egui::Modifiers::NONE, egui::Key::Escape)
+            || pressed(egui::Modifiers::NONE, egui::Key::P);
+        if pause {
+            self.pause();
+            events.push(GameEvent::Pause);
+        }
+        if pressed(egui::Modifiers::SHIFT, egui::Key::H) {
+            self.hint_path();
+        }
+        if pressed(egui::Modifiers::NONE, egui::Key::H) {
+            self.hint();
+        }
+        if pressed(egui::Modifiers::SHIFT, egui::Key::L) {
+            self.lock_correct_tiles();
+        }
+        // Restarting and a new game are confirmed first, see `confirm_new_game`.
+        if pressed(egui::Modifiers::NONE, egui::Key::R) {
+            events.push(GameEvent::Restart);
+        }
+        if pressed(egui::Modifiers::NONE, egui::Key::N) {
+            events.push(GameEvent::NewGameWithOptions(*self.puzzle.options()));
+        }
+    }
+
+    /// Hold back a request for a new game, to restart or to quit to the menu in `events` while
+    /// the current game is in progress, until the player confirms to abandon it. The game is
+    /// paused in the meantime.
+    fn confirm_new_game(&mut self, events: &mut Vec<GameEvent>) {
+        // With `Settings::timer_starts_immediately`, the game runs before the first move. Without
+        // a move there is no progress to lose.
+        let in_progress =
+            matches!(self.state, GameState::Running | GameState::Paused { game_was_started: true })
+                && self.move_counter.get() > 0;
+        if !in_progress || self.pending_new_game.is_some() {
+            return;
+        }
+        let Some(position) = events.iter()
+            .position(|event| {
+                matches!(
+                    event,
+                    GameEvent::NewGame | GameEvent::NewGameWithOptions(_) | GameEvent::Restart | GameEvent::Close
+                )
+            })
+        else {
+            return;
+        };
+
+        let event = events.remove(position);
+        self.pending_new_game = Some((event, self.state));
+        self.state = GameState::Paused { game_was_started: true };
+        self.timer.stop();
+    }
+
     fn update_game_board(&mut self, ui: &mut egui::Ui) {
-        let board_size = self.puzzle.size();
-        let desired_size =
-            egui::Vec2::splat(board_size as f32 * TILE_SIZE + Self::INNER_MARGIN);
+        let desired_size = self.board_extent() + egui::Vec2::splat(Self::INNER_MARGIN);
         ui.allocate_ui(desired_size, |ui| {
-            let top_left =
-                ui.max_rect().left_top().to_vec2() + egui::Vec2::splat(Self::INNER_MARGIN / 2.);
-
-            // Manipulate top_left to ensure there are only integer values in x or y (no half pixels).
-            // Rendering half-pixels does not work / does not play well with alpha blending texture
-            // (which is not necessary, but seems to happen). Unclear if this is a bug in egui or
-            // wgpu.
-            let top_left = egui::Vec2::new(top_left.x.floor(), top_left.y.floor());
+            let top_left = ui.max_rect().left_top() + egui::Vec2::splat(Self::INNER_MARGIN / 2.);
+            let layout = BoardLayout::new(&self.puzzle, top_left, self.tile_size);
 
-            let (hovered_tile, modified_tile) = self.draw_tiles(top_left, ui);
+            let (hovered_tile, modified_tiles) = self.draw_tiles(&layout, ui);
 
             for wall in &self.wall_sprites {
-                wall.draw(top_left, ui);
+                wall.draw(&layout, &self.settings, ui);
             }
 
             if self.puzzle.options().wrapping && self.settings.show_wrap_marker &&
                 let Some(hovered_tile) = hovered_tile {
-                self.apply_wrap_markers(hovered_tile, top_left, ui);
+                self.apply_wrap_markers(hovered_tile, &layout, ui);
             }
 
-            // Run updates
-            if let Some(updated_tile) = modified_tile {
+            // Run updates. Several rotations may finish in the same frame, e.g. a hint while the
+            // player rotates another tile. The puzzle is only checked once all are applied.
+            for &(updated_tile, quarters) in &modified_tiles {
                 if self.state == GameState::BeforeStart {
-                    self.timer.start();
+                    self.timer.start(ui.input(|i| i.time));
                     self.state = GameState::Running;
                 }
 
-                self.move_counter.update(updated_tile);
-                self.puzzle.calc_energy();
-
-                if self.puzzle.solved() {
-                    let score = self.calc_score();
-                    self.state = GameState::Ended { score }
-                }
+                let counted = self.move_counter.update(updated_tile);
+                let orientation = self.puzzle.grid()[updated_tile].orientation();
+                self.history.push(Rotation { coord: updated_tile, quarters, orientation, counted });
+                let newly_powered = self.puzzle.calc_energy_after(updated_tile);
+                self.start_power_pulses(updated_tile, &newly_powered);
+            }
+            if !modified_tiles.is_empty() {
+                #[cfg(feature = "audio")]
+                self.play_sound(Sound::Rotation);
+                self.check_solved();
             }
         });
     }
 
-    fn draw_tiles(&mut self, top_left: egui::Vec2, ui: &mut egui::Ui) -> (Option<Vec2>, Option<Vec2>) {
+    /// Show the rotations of the player in a side panel, followed by the undone rotations which
+    /// can be redone. Return the number of rotations to undo or redo to if an entry was clicked.
+    fn update_move_history(&self, ui: &mut egui::Ui) -> Option<usize> {
+        let mut clicked = None;
+        egui::SidePanel::right("Move history")
+            .resizable(false)
+            .exact_width(Self::MOVE_HISTORY_WIDTH)
+            .show_inside(ui, |ui| {
+                ui.heading("Moves");
+                ui.separator();
+                let running = self.state == GameState::Running;
+                egui::ScrollArea::vertical()
+                    .id_salt("Move history")
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for (i, (rotation, undone)) in self.history.rotations().enumerate() {
+                            let tile = self.puzzle.grid()[rotation.coord];
+                            let text = format!(
+                                "{}. ({}, {}) {} {}°",
+                                i + 1,
+                                rotation.coord.x,
+                                rotation.coord.y,
+                                kind_name(tile.kind()),
+                                rotation.orientation.to_angle().to_degrees().round(),
+                            );
+                            let text = if undone {
+                                egui::RichText::new(text).weak()
+                            } else {
+                                egui::RichText::new(text)
+                            };
+                            let current = i + 1 == self.history.applied();
+                            let response = ui
+                                .add_enabled(running, egui::Button::selectable(current, text))
+                                .on_hover_text(if undone { "Redo up to this move" } else { "Undo up to this move" });
+                            if response.clicked() {
+                                clicked = Some(i + 1);
+                            }
+                        }
+                    });
+            });
+        clicked
+    }
+
+    /// Return the size of the game board on screen, without margins.
+    fn board_extent(&self) -> egui::Vec2 {
+        let grid = self.puzzle.grid();
+        egui::Vec2::new(grid.cols() as f32, grid.rows() as f32) * self.tile_size
+    }
+
+    /// Draw the tiles and handle clicks on them. Return the hovered tile and the tiles whose
+    /// rotation has finished in this frame, with their counter-clockwise quarter turns.
+    fn draw_tiles(&mut self, layout: &BoardLayout, ui: &mut egui::Ui) -> (Option<Vec2>, Vec<(Vec2, u32)>) {
         let mut hovered_tile = None;
-        let mut modified_tile = None;
-
-        for row in 0..self.puzzle.grid().rows() {
-            for col in 0..self.puzzle.grid().cols() {
-                let index = Vec2::new(row as i32, col as i32);
-                let tile = self
-                    .puzzle
-                    .grid_mut()
-                    .get_mut(index)
-                    .expect("(row, col) must be on the grid");
-                let widget = self
-                    .tile_widgets
-                    .get_mut(index)
-                    .expect("(row, col) must be on the grid");
-                let pos =
-                    egui::Pos2::new(index.x as f32 * 40., index.y as f32 * 40.) + top_left;
-                let response = widget.update(tile, index, pos, &self.assets, ui);
-                if response.modified {
-                    modified_tile = Some(index);
-                }
-                if response.hovered {
-                    hovered_tile = Some(index);
-                }
+        let mut modified_tiles = vec![];
+
+        // All animations advance on the same clock, once per frame, so rotations which were
+        // started together also finish together.
+        let dt = ui.input(|i| i.stable_dt);
+        for sprite in self.tile_widgets.iter_mut() {
+            if let Some(animation) = sprite.animation.as_mut() {
+                animation.advance(dt);
+            }
+            sprite.power_pulse = sprite.power_pulse
+                .map(|elapsed| elapsed + dt)
+                .filter(|&elapsed| elapsed < TileSprite::POWER_PULSE_TIME);
+        }
+
+        // The indices are (x, y) = (column, row), like everywhere else.
+        for index in self.puzzle.grid().indices_iter() {
+            let rect = layout.tile_rect(index);
+            let tile = self
+                .puzzle
+                .grid_mut()
+                .get_mut(index)
+                .expect("index must be on the grid");
+            let widget = self
+                .tile_widgets
+                .get_mut(index)
+                .expect("index must be on the grid");
+            let locked = &mut self.locked[index];
+            let response = widget.update(tile, locked, index, rect, &self.assets, &self.settings, ui);
+            if let Some(quarters) = response.rotated_quarters {
+                modified_tiles.push((index, quarters));
+            }
+            if response.hovered {
+                hovered_tile = Some(index);
+            }
+        }
+
+        if self.settings.highlight_component &&
+            let Some(hovered_tile) = hovered_tile {
+            let tint = ui.visuals().selection.bg_fill.gamma_multiply(0.35);
+            for coord in self.puzzle.connected_component(hovered_tile) {
+                ui.painter().rect_filled(layout.tile_rect(coord), 0., tint);
+            }
+        }
+
+        if self.settings.highlight_loops {
+            let tint = egui::Color32::from_rgba_unmultiplied(230, 120, 20, 70);
+            for coord in self.puzzle.cycle_tiles() {
+                ui.painter().rect_filled(layout.tile_rect(coord), 0., tint);
             }
         }
 
-        (hovered_tile, modified_tile)
+        self.draw_path_hint(layout, ui);
+
+        if let Some(cursor) = self.cursor {
+            let rect = layout.tile_rect(cursor);
+            let stroke = egui::Stroke::new(2., ui.visuals().selection.stroke.color);
+            ui.painter().rect_stroke(rect.shrink(1.), 2., stroke, egui::StrokeKind::Inside);
+        }
+
+        // A single repaint for all animations, including the ones started by a click above.
+        let animating = |sprite: &TileSprite| {
+            sprite.animation.is_some_and(|a| a.running()) || sprite.power_pulse.is_some()
+        };
+        if self.tile_widgets.iter().any(animating) {
+            ui.ctx().request_repaint_after(Animation::FRAME_TIME);
+        }
+
+        (hovered_tile, modified_tiles)
+    }
+
+    /// Draw a dim glow along the tiles of the path hint, which fades out during its last second.
+    /// Remove the hint once its time is over.
+    fn draw_path_hint(&mut self, layout: &BoardLayout, ui: &mut egui::Ui) {
+        let now = ui.input(|i| i.time);
+        let Some(hint) = self.path_hint.as_mut() else {
+            return;
+        };
+        // The hint is shown from its first frame on, not from the request.
+        let shown_at = *hint.shown_at.get_or_insert(now);
+        let remaining = Self::PATH_HINT_DURATION - (now - shown_at);
+        if remaining <= 0. {
+            self.path_hint = None;
+            return;
+        }
+
+        let alpha = remaining.min(1.) as f32;
+        let glow = egui::Color32::from_rgba_unmultiplied(255, 215, 0, (60. * alpha) as u8);
+        for &coord in &hint.tiles {
+            ui.painter().rect_filled(layout.tile_rect(coord).shrink(2.), 4., glow);
+        }
+        ui.ctx().request_repaint_after(Animation::FRAME_TIME);
     }
 
     fn update_status_bar(&mut self, ui: &mut egui::Ui) -> Vec<GameEvent> {
         let mut events = vec![];
-        let board_size = self.puzzle.size();
-        let desired_size =
-            egui::Vec2::splat(board_size as f32 * TILE_SIZE + Self::INNER_MARGIN);
+        let desired_size = egui::Vec2::new(
+            (self.board_extent().x + Self::INNER_MARGIN).min(ui.available_width()),
+            Self::STATUS_BAR_HEIGHT,
+        );
         ui.allocate_ui(desired_size, |ui| {
             ui.vertical(|ui| {
 
                 ui.horizontal(|ui| {
                     ui.spacing_mut().item_spacing.x = 10.;
                     if ui.button(egui::RichText::new(
-                        egui_phosphor::regular::DOTS_THREE_VERTICAL.to_string()).size(12.)).clicked()
+                        egui_phosphor::regular::DOTS_THREE_VERTICAL.to_string()).size(12.))
+                        .on_hover_text("Pause (Esc, P)")
+                        .clicked()
                     {
                         events.push(GameEvent::Pause)
                     }
-                    ui.label(format!("{}/{}", self.move_counter.get(), self.puzzle.expected_moves()));
-                    ui.label(format!("{}", self.timer));
+                    let running = self.state == GameState::Running;
+                    if ui.add_enabled(
+                        running && self.history.can_undo(),
+                        egui::Button::new(egui::RichText::new(
+                            egui_phosphor::regular::ARROW_U_UP_LEFT.to_string()).size(12.)))
+                        .on_hover_text("Undo (Ctrl+Z)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::Undo)
+                    }
+                    if ui.add_enabled(
+                        running && self.history.can_redo(),
+                        egui::Button::new(egui::RichText::new(
+                            egui_phosphor::regular::ARROW_U_UP_RIGHT.to_string()).size(12.)))
+                        .on_hover_text("Redo (Ctrl+Y)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::Redo)
+                    }
+                    if ui.add_enabled(
+                        matches!(self.state, GameState::BeforeStart | GameState::Running),
+                        egui::Button::new(egui::RichText::new(
+                            egui_phosphor::regular::LIGHTBULB.to_string()).size(12.)))
+                        .on_hover_text("Hint (H)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::Hint)
+                    }
+                    if ui.add_enabled(
+                        matches!(self.state, GameState::BeforeStart | GameState::Running),
+                        egui::Button::new(egui::RichText::new(
+                            egui_phosphor::regular::PATH.to_string()).size(12.)))
+                        .on_hover_text("Show the path from the source to a drain (Shift+H)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::PathHint)
+                    }
+                    if ui.add_enabled(
+                        matches!(self.state, GameState::BeforeStart | GameState::Running),
+                        egui::Button::new(egui::RichText::new(
+                            egui_phosphor::regular::LOCK.to_string()).size(12.)))
+                        .on_hover_text("Lock all correct tiles, counts as showing mistakes (Shift+L)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::LockCorrect)
+                    }
+                    let (icon, hover_text) = match self.auto_solve {
+                        Some(_) => (egui_phosphor::regular::STOP, "Stop solving"),
+                        None => (egui_phosphor::regular::MAGIC_WAND, "Solve step by step, scores zero"),
+                    };
+                    if ui.add_enabled(
+                        matches!(self.state, GameState::BeforeStart | GameState::Running),
+                        egui::Button::new(egui::RichText::new(icon.to_string()).size(12.)))
+                        .on_hover_text(hover_text)
+                        .clicked()
+                    {
+                        events.push(GameEvent::AutoSolve)
+                    }
+                    if ui.button(egui::RichText::new(
+                        egui_phosphor::regular::QUESTION.to_string()).size(12.))
+                        .on_hover_text("How to play")
+                        .clicked()
+                    {
+                        events.push(GameEvent::Tutorial)
+                    }
+                    if ui.button(egui::RichText::new(
+                        egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE.to_string()).size(12.))
+                        .on_hover_text("Restart this puzzle (R)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::Restart)
+                    }
+                    if ui.button(egui::RichText::new(
+                        egui_phosphor::regular::SHUFFLE.to_string()).size(12.))
+                        .on_hover_text("New puzzle with the same options (N)")
+                        .clicked()
+                    {
+                        events.push(GameEvent::NewGameWithOptions(*self.puzzle.options()))
+                    }
+                    // A practice game is played without the pressure of moves and time.
+                    if self.puzzle.options().practice {
+                        ui.weak("Practice");
+                    } else {
+                        ui.label(format!("{}/{}", self.move_counter.get(), self.puzzle.expected_moves()));
+                        match self.puzzle.options().time_limit {
+                            Some(limit) => {
+                                ui.label(format_duration(self.timer.remaining(limit)))
+                                    .on_hover_text("Remaining time");
+                            }
+                            None => {
+                                ui.label(format!("{}", self.timer));
+                            }
+                        }
+                    }
                 });
-                if self.puzzle.options().wrapping &&
-                    ui.checkbox(&mut self.settings.show_wrap_marker, "Show wrap marker").clicked() {
-                        events.push(GameEvent::SettingsChanged(self.settings));
-                }
+                let powered_fraction = self.puzzle.powered_fraction();
+                ui.add(egui::ProgressBar::new(powered_fraction).desired_height(4.))
+                    .on_hover_text(format!("{:.0}% of the tiles are powered", powered_fraction * 100.));
             })
         });
 
         events
     }
 
-    fn apply_wrap_markers(&mut self, hovered_tile: Vec2, top_left: egui::Vec2, ui: &mut egui::Ui) {
-        let x = hovered_tile.x;
-        let y = hovered_tile.y;
-
-        if x <= 0 {
-            let opposite_x = self.puzzle.grid().cols() as i32 - 1;
-            self.draw_wrap_marker(Vec2::new(opposite_x, y), Direction::Left, top_left, ui);
-        }
-        if x >= self.puzzle.grid().cols() as i32 - 1 {
-            let opposite_x = 0;
-            self.draw_wrap_marker(Vec2::new(opposite_x, y), Direction::Right, top_left, ui);
-        }
-        if y <= 0 {
-            let opposite_y = self.puzzle.grid().rows() as i32 - 1;
-            self.draw_wrap_marker(Vec2::new(x, opposite_y), Direction::Up, top_left, ui);
-        }
-        if y >= self.puzzle.grid().rows() as i32 - 1 {
-            let opposite_y = 0;
-            self.draw_wrap_marker(Vec2::new(x, opposite_y), Direction::Down, top_left, ui);
+    fn apply_wrap_markers(&mut self, hovered_tile: Vec2, layout: &BoardLayout, ui: &mut egui::Ui) {
+        // The tile beyond each edge of the hovered tile, which is on the opposite edge of the
+        // board if the hovered tile is on the edge.
+        for direction in Direction::all() {
+            let beyond = hovered_tile + direction.to_vec2();
+            if layout.contains(beyond) {
+                continue;
+            }
+            if let Some(opposite) = layout.wrapped(beyond) {
+                self.draw_wrap_marker(opposite, direction, layout, ui);
+            }
         }
     }
 
-    fn draw_wrap_marker(&self, coord: Vec2, direction: Direction, top_left: egui::Vec2, ui: &mut egui::Ui) {
+    fn draw_wrap_marker(&self, coord: Vec2, direction: Direction, layout: &BoardLayout, ui: &mut egui::Ui) {
         // Direction "Up" here mean along the upper edge of the tile, etc.
-        let tile_size = TILE_SIZE;
+        let tile_size = layout.tile_size();
         let tile_size_2 = tile_size / 2.;
-        let triangle_size = 8.0f32;
+        let triangle_size = tile_size / 5.;
         let triangle_offset = triangle_size * 1.5;
 
         let points = Self::triangle_path(triangle_size, direction);
@@ -302,12 +1208,14 @@ impl Game {
         };
 
         let points_on_screen = points.iter()
-            .map(|&p| p + top_left + offset
-                + egui::Vec2::new(coord.x as f32 * tile_size, coord.y as f32 * tile_size))
+            .map(|&p| p + offset + layout.grid_to_screen(coord).to_vec2())
             .collect::<Vec<_>>();
 
-        let fill = egui::Color32::GRAY;
-        let stroke = egui::epaint::PathStroke::new(1.0, fill);
+        let (fill, stroke) = if self.settings.high_contrast_marks {
+            (self.settings.accent_color, egui::epaint::PathStroke::new(2.0, egui::Color32::BLACK))
+        } else {
+            (egui::Color32::GRAY, egui::epaint::PathStroke::new(1.0, egui::Color32::GRAY))
+        };
         ui.painter().add(egui::epaint::PathShape::convex_polygon(points_on_screen, fill, stroke));
     }
 
@@ -331,25 +1239,401 @@ impl Game {
             }
         }
     }
-}
+}
+
+/// Calculate the score of a solved puzzle from the tiles, the walls, the time taken and the
+/// assistance the player received.
+///
+/// The score is never negative; the penalties for walls and boundaries are clamped at zero. Each
+/// hint costs the share of the score of one expected move, a path hint half of that. Showing
+/// mistakes halves the score.
+/// A puzzle solved by the solver scores zero.
+fn calc_score(puzzle: &Puzzle, duration: std::time::Duration, assistance: Assistance) -> u32 {
+    if assistance.auto_solved {
+        return 0;
+    }
+    let weights = HashMap::from([
+        (Kind::DeadEnd, 4),
+        (Kind::Corner, 4),
+        (Kind::Straight, 2),
+        (Kind::TIntersection, 4),
+        (Kind::CrossIntersection, 0),
+    ]);
+    let mut score: i64 = puzzle.grid().iter()
+        .map(|tile| {
+            weights
+                .get(&tile.kind())
+                .expect("link type must be in map of weights")
+        })
+        .sum();
+    score -= 2 * puzzle.walls().len() as i64;
+    if !puzzle.options().wrapping {
+        score -= (puzzle.grid().rows() + puzzle.grid().cols()) as i64;
+    }
+    let score = score.max(0);
+
+    let mut score = score as f32
+        * (puzzle.expected_moves() as f32
+            / (puzzle.grid().rows() * puzzle.grid().cols()) as f32);
+    // Avoid a division by zero for puzzles solved in less than a second.
+    score = score * score / duration.as_secs().max(1) as f32;
+
+    let hints = assistance.hints_used as f32 + assistance.path_hints_used as f32 / 2.;
+    let hint_penalty = hints / puzzle.expected_moves().max(1) as f32;
+    score *= (1. - hint_penalty).max(0.);
+    if assistance.mistakes_shown {
+        score /= 2.;
+    }
+
+    score.round() as u32
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settings {
+    pub(crate) show_wrap_marker: bool,
+    pub(crate) rotate_clockwise: bool,
+    pub(crate) wheel_rotation: bool, // rotate the hovered tile with the mouse wheel
+    pub(crate) show_mistakes: bool, // tint tiles which differ from the generator's solution, reduces the score
+    pub(crate) highlight_component: bool, // tint the tiles connected to the hovered tile
+    pub(crate) highlight_loops: bool, // tint the tiles which form closed loops
+    pub(crate) export_power: bool, // show powered tiles in saved images of the game board
+    pub(crate) animation_ms: u32, // duration of a quarter rotation, 0 rotates tiles without animation
+    pub(crate) color_scheme: ColorScheme,
+    pub(crate) min_tile_size: u32, // the board is fitted to the window within these tile sizes
+    pub(crate) max_tile_size: u32,
+    pub(crate) zoom_percent: u32, // scales the fitted tile size, changed with Ctrl+scroll and +/-
+    pub(crate) mute: bool, // silence the sound effects of the feature `audio`
+    pub(crate) volume_percent: u32,
+    pub(crate) tutorial_seen: bool, // the tutorial is shown automatically until dismissed once
+    pub(crate) show_move_history: bool, // list the rotations in a side panel beside the board
+    pub(crate) timer_starts_immediately: bool, // start the clock when the board is shown, not on the first move
+    pub(crate) high_contrast_marks: bool, // draw wrap markers and wall outlines in the accent color
+    pub(crate) accent_color: egui::Color32,
+    pub(crate) power_pulse: bool, // briefly blend newly powered tiles from their unpowered look
+    tileset_dir: Option<std::path::PathBuf>, // custom tile textures, None for the embedded ones
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_wrap_marker: false,
+            rotate_clockwise: false,
+            wheel_rotation: true,
+            show_mistakes: false,
+            highlight_component: false,
+            highlight_loops: false,
+            export_power: true,
+            animation_ms: 75,
+            color_scheme: ColorScheme::Default,
+            min_tile_size: 20,
+            max_tile_size: 80,
+            zoom_percent: 100,
+            mute: false,
+            volume_percent: 50,
+            tutorial_seen: false,
+            show_move_history: false,
+            timer_starts_immediately: false,
+            high_contrast_marks: false,
+            accent_color: egui::Color32::from_rgb(255, 210, 0),
+            power_pulse: true,
+            tileset_dir: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn read(storage: &dyn Storage) -> Self {
+        let mut settings = Self::default();
+
+        if let Some(s) = storage.get_string("show_wrap_marker") &&
+            let Ok(value) = s.parse::<bool>() { settings.show_wrap_marker = value };
+        if let Some(s) = storage.get_string("rotate_clockwise") &&
+            let Ok(value) = s.parse::<bool>() { settings.rotate_clockwise = value };
+        if let Some(s) = storage.get_string("wheel_rotation") &&
+            let Ok(value) = s.parse::<bool>() { settings.wheel_rotation = value };
+        if let Some(s) = storage.get_string("show_mistakes") &&
+            let Ok(value) = s.parse::<bool>() { settings.show_mistakes = value };
+        if let Some(s) = storage.get_string("highlight_component") &&
+            let Ok(value) = s.parse::<bool>() { settings.highlight_component = value };
+        if let Some(s) = storage.get_string("highlight_loops") &&
+            let Ok(value) = s.parse::<bool>() { settings.highlight_loops = value };
+        if let Some(s) = storage.get_string("export_power") &&
+            let Ok(value) = s.parse::<bool>() { settings.export_power = value };
+        if let Some(s) = storage.get_string("animation_ms") &&
+            let Ok(value) = s.parse::<u32>() { settings.animation_ms = value };
+        if let Some(s) = storage.get_string("color_scheme") &&
+            let Ok(value) = s.parse::<ColorScheme>() { settings.color_scheme = value };
+        if let Some(s) = storage.get_string("min_tile_size") &&
+            let Ok(value) = s.parse::<u32>() { settings.min_tile_size = value };
+        if let Some(s) = storage.get_string("max_tile_size") &&
+            let Ok(value) = s.parse::<u32>() { settings.max_tile_size = value };
+        if let Some(s) = storage.get_string("zoom_percent") &&
+            let Ok(value) = s.parse::<u32>() { settings.zoom_percent = value };
+        if let Some(s) = storage.get_string("mute") &&
+            let Ok(value) = s.parse::<bool>() { settings.mute = value };
+        if let Some(s) = storage.get_string("volume_percent") &&
+            let Ok(value) = s.parse::<u32>() { settings.volume_percent = value };
+        if let Some(s) = storage.get_string("tutorial_seen") &&
+            let Ok(value) = s.parse::<bool>() { settings.tutorial_seen = value };
+        if let Some(s) = storage.get_string("show_move_history") &&
+            let Ok(value) = s.parse::<bool>() { settings.show_move_history = value };
+        if let Some(s) = storage.get_string("timer_starts_immediately") &&
+            let Ok(value) = s.parse::<bool>() { settings.timer_starts_immediately = value };
+        if let Some(s) = storage.get_string("high_contrast_marks") &&
+            let Ok(value) = s.parse::<bool>() { settings.high_contrast_marks = value };
+        if let Some(s) = storage.get_string("accent_color") &&
+            let Ok(value) = egui::Color32::from_hex(&s) { settings.accent_color = value };
+        if let Some(s) = storage.get_string("power_pulse") &&
+            let Ok(value) = s.parse::<bool>() { settings.power_pulse = value };
+        if let Some(s) = storage.get_string("tileset_dir") && !s.is_empty() {
+            settings.tileset_dir = Some(s.into());
+        }
+
+        settings
+    }
+
+    pub fn write(&self, storage: &mut dyn Storage) {
+        storage.set_string("show_wrap_marker", self.show_wrap_marker.to_string());
+        storage.set_string("rotate_clockwise", self.rotate_clockwise.to_string());
+        storage.set_string("wheel_rotation", self.wheel_rotation.to_string());
+        storage.set_string("show_mistakes", self.show_mistakes.to_string());
+        storage.set_string("highlight_component", self.highlight_component.to_string());
+        storage.set_string("highlight_loops", self.highlight_loops.to_string());
+        storage.set_string("export_power", self.export_power.to_string());
+        storage.set_string("animation_ms", self.animation_ms.to_string());
+        storage.set_string("color_scheme", self.color_scheme.to_string());
+        storage.set_string("min_tile_size", self.min_tile_size.to_string());
+        storage.set_string("max_tile_size", self.max_tile_size.to_string());
+        storage.set_string("zoom_percent", self.zoom_percent.to_string());
+        storage.set_string("mute", self.mute.to_string());
+        storage.set_string("volume_percent", self.volume_percent.to_string());
+        storage.set_string("tutorial_seen", self.tutorial_seen.to_string());
+        storage.set_string("show_move_history", self.show_move_history.to_string());
+        storage.set_string("timer_starts_immediately", self.timer_starts_immediately.to_string());
+        storage.set_string("high_contrast_marks", self.high_contrast_marks.to_string());
+        storage.set_string("accent_color", self.accent_color.to_hex());
+        storage.set_string("power_pulse", self.power_pulse.to_string());
+        let tileset_dir = self.tileset_dir.as_ref()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        storage.set_string("tileset_dir", tileset_dir);
+    }
+
+    /// Return the duration of the animation of a quarter rotation. Zero disables animations.
+    pub fn animation_time(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.animation_ms as u64)
+    }
+
+    /// Return the range of tile sizes on screen. A maximum below the minimum is ignored.
+    pub fn tile_size_range(&self) -> std::ops::RangeInclusive<f32> {
+        self.min_tile_size as f32..=self.max_tile_size.max(self.min_tile_size) as f32
+    }
+
+    /// Return the directory of a custom tileset, if any.
+    pub fn tileset_dir(&self) -> Option<&std::path::Path> {
+        self.tileset_dir.as_deref()
+    }
+
+    pub fn set_tileset_dir(&mut self, dir: Option<std::path::PathBuf>) {
+        self.tileset_dir = dir;
+    }
+}
+
+/// The colors of the links of powered and unpowered tiles.
+///
+/// The tileset draws powered links in orange and unpowered links in dark red, which differ mostly
+/// in hue. The other schemes increase the difference in brightness, which remains visible with
+/// color vision deficiencies.
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, strum::Display, strum::EnumIter,
+    strum::EnumString,
+)]
+pub(crate) enum ColorScheme {
+    #[default]
+    Default,
+    Deuteranopia,
+    Protanopia,
+    #[strum(to_string = "High contrast", serialize = "HighContrast")]
+    HighContrast,
+}
+
+impl ColorScheme {
+    /// Return whether the links of a tile which is `powered` are drawn with the powered texture,
+    /// and the tint the texture is multiplied with.
+    fn link_style(self, powered: bool) -> (bool, egui::Color32) {
+        if powered {
+            return (true, egui::Color32::WHITE);
+        }
+        match self {
+            ColorScheme::Default => (false, egui::Color32::WHITE),
+            // Darken the red, which is perceived close to the orange.
+            ColorScheme::Deuteranopia => (false, egui::Color32::from_gray(140)),
+            // Red appears almost black, so use a dim orange instead.
+            ColorScheme::Protanopia => (true, egui::Color32::from_gray(110)),
+            ColorScheme::HighContrast => (true, egui::Color32::from_gray(60)),
+        }
+    }
+}
+
+/// The part of a game which is stored between application launches.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedGame {
+    starting_position: Puzzle,
+    puzzle: Puzzle,
+    locked: Grid<bool>,
+    elapsed: std::time::Duration,
+    move_count: u32,
+    #[serde(default)]
+    assistance: Assistance,
+}
+
+impl SavedGame {
+    /// Return true if both puzzles are well-formed and of the same size as the locks. A damaged
+    /// save would otherwise panic on the first frame.
+    fn is_consistent(&self) -> bool {
+        let grid = self.puzzle.grid();
+        let same_size = |rows, cols| rows == grid.rows() && cols == grid.cols();
+        self.starting_position.is_consistent() && self.puzzle.is_consistent() &&
+            same_size(self.starting_position.grid().rows(), self.starting_position.grid().cols()) &&
+            same_size(self.locked.rows(), self.locked.cols()) &&
+            self.locked.iter().count() == grid.rows() * grid.cols()
+    }
+}
+
+/// The tiles along the solution from the source to a drain, see `Game::hint_path`.
+#[derive(Clone, Debug)]
+struct PathHint {
+    tiles: Vec<Vec2>,
+    shown_at: Option<f64>, // the time of the first frame which shows the hint
+}
+
+/// The help a player received while solving a puzzle. It reduces the score.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Assistance {
+    hints_used: u32,
+    #[serde(default)]
+    path_hints_used: u32, // see `Game::hint_path`
+    mistakes_shown: bool, // true if mistakes were shown at any time during the game
+    #[serde(default)]
+    auto_solved: bool, // true if the solver played, see `Game::toggle_auto_solve`
+}
+
+/// Return the number of quarter turns in the given direction which rotate `tile` into the
+/// orientation of the generator's solution.
+fn quarters_to_solution(mut tile: Tile, clockwise: bool) -> u32 {
+    let mut quarters = 0;
+    while !tile.is_correct() {
+        if clockwise {
+            tile.rotate_cw();
+        } else {
+            tile.rotate();
+        }
+        quarters += 1;
+    }
+    quarters
+}
+
+/// Return the name of a kind of tile in the move history.
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::DeadEnd => "Dead end",
+        Kind::Straight => "Straight",
+        Kind::Corner => "Corner",
+        Kind::TIntersection => "T-piece",
+        Kind::CrossIntersection => "Cross",
+    }
+}
+
+/// Return the size of a tile on screen such that a board of `rows` and `cols` tiles fits into
+/// `available`, clamped to `range`. The size is rounded down to whole points, so tiles are not
+/// drawn at half pixels.
+pub(crate) fn fit_tile_size(available: egui::Vec2, rows: usize, cols: usize, range: std::ops::RangeInclusive<f32>) -> f32 {
+    let fit = (available.x / cols as f32).min(available.y / rows as f32).floor();
+    fit.clamp(*range.start(), range.end().max(*range.start()))
+}
+
+/// The placement of the game board on screen, which maps the coordinates of tiles to points on
+/// screen and back.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct BoardLayout {
+    top_left: egui::Pos2, // the top left corner of the tile (0, 0)
+    tile_size: f32,
+    rows: usize,
+    cols: usize,
+    wrapping: bool,
+}
+
+impl BoardLayout {
+    /// Place the board of `puzzle` with its top left corner at `top_left`, rounded down to whole
+    /// points. Textures drawn at half pixels do not play well with alpha blending (which is not
+    /// necessary, but seems to happen), unclear if this is a bug in egui or wgpu.
+    pub(crate) fn new(puzzle: &Puzzle, top_left: egui::Pos2, tile_size: f32) -> Self {
+        Self {
+            top_left: top_left.floor(),
+            tile_size,
+            rows: puzzle.grid().rows(),
+            cols: puzzle.grid().cols(),
+            wrapping: puzzle.options().wrapping,
+        }
+    }
+
+    pub(crate) fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
 
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
-pub struct Settings {
-    show_wrap_marker: bool,
-}
+    /// Return the top left corner of the tile at `coord` on screen. The coordinates may be
+    /// beyond the board, e.g. for the second sprite of a wall on the edge of a wrapping board.
+    pub(crate) fn grid_to_screen(&self, coord: Vec2) -> egui::Pos2 {
+        self.top_left + egui::Vec2::from(coord) * self.tile_size
+    }
 
-impl Settings {
-    pub fn read(storage: &dyn Storage) -> Self {
-        let mut settings = Self::default();
+    /// Return the area of the whole board on screen.
+    pub(crate) fn board_rect(&self) -> egui::Rect {
+        let extent = egui::Vec2::new(self.cols as f32, self.rows as f32) * self.tile_size;
+        egui::Rect::from_min_size(self.top_left, extent)
+    }
 
-        if let Some(s) = storage.get_string("show_wrap_marker") &&
-            let Ok(value) = s.parse::<bool>() { settings.show_wrap_marker = value };
+    /// Return the area of the tile at `coord` on screen, see `grid_to_screen`.
+    pub(crate) fn tile_rect(&self, coord: Vec2) -> egui::Rect {
+        egui::Rect::from_min_size(self.grid_to_screen(coord), egui::Vec2::splat(self.tile_size))
+    }
 
-        settings
+    /// Return the tile at `pos` on screen. Points on the edge between two tiles belong to the
+    /// right or lower one. Beyond the board, a wrapping board repeats and the tile of the board
+    /// is returned, otherwise `None`.
+    pub(crate) fn screen_to_grid(&self, pos: egui::Pos2) -> Option<Vec2> {
+        let offset = (pos - self.top_left) / self.tile_size;
+        self.wrapped(Vec2::new(offset.x.floor() as i32, offset.y.floor() as i32))
     }
 
-    pub fn write(&self, storage: &mut dyn Storage) {
-        storage.set_string("show_wrap_marker", self.show_wrap_marker.to_string());
+    /// Return true if `coord` is on the board.
+    pub(crate) fn contains(&self, coord: Vec2) -> bool {
+        (0..self.cols as i32).contains(&coord.x) && (0..self.rows as i32).contains(&coord.y)
+    }
+
+    /// Return `coord` if it is on the board, the corresponding tile of the board if the board
+    /// wraps, and `None` otherwise.
+    pub(crate) fn wrapped(&self, coord: Vec2) -> Option<Vec2> {
+        if self.contains(coord) {
+            Some(coord)
+        } else if self.wrapping {
+            Some(Vec2::new(coord.x.rem_euclid(self.cols as i32), coord.y.rem_euclid(self.rows as i32)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Return the position of the keyboard cursor after moving it from `cursor` in `direction`. On a
+/// wrapping board the cursor wraps around at the edges, otherwise it stays on the edge.
+fn move_cursor<T>(grid: &Grid<T>, cursor: Vec2, direction: Direction, wrapping: bool) -> Vec2 {
+    let target = cursor + direction.to_vec2();
+    if wrapping {
+        grid.normalized_coord(target)
+    } else if grid.contains_coord(target) {
+        target
+    } else {
+        cursor
     }
 }
 
@@ -358,240 +1642,376 @@ enum GameState {
     BeforeStart,
     Running,
     Paused { game_was_started: bool },
-    Ended { score: u32 },
+    Ended { score: u32, solved: bool }, // not solved if the time limit was exceeded
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum GameEvent {
     Close,
     Pause,
     NewGame,
+    NewGameWithOptions(Options),
     Restart,
+    Undo,
+    Redo,
+    Hint,
+    PathHint, // highlight the solution from the source to a drain
+    AutoSolve, // start or stop the solver
+    LockCorrect, // lock all tiles in the orientation of the solution
+    Tutorial, // show the explanation of the controls
+    // Pushed once when the puzzle is solved. The code identifies the puzzle, see `Puzzle::to_code`.
+    Solved {
+        time: std::time::Duration,
+        moves: u32,
+        expected_moves: u32,
+        score: u32,
+        code: String,
+        assisted: bool, // solved by the solver
+    },
     SettingsChanged(Settings),
 }
 
 #[derive(Copy, Clone, Debug)]
+/// The rotation animation of a tile.
+///
+/// The sprite is drawn at `tile.orientation().to_angle() + animation.angle()`. Once the animation
+/// has finished and `apply` has rotated the tile, `tile.orientation().to_angle()` equals the last
+/// drawn angle (modulo a full turn), so the sprite does not jump.
+///
+/// `Game::draw_tiles` advances all animations once per frame by the same time. Animations of the
+/// same length which start in the same frame therefore finish in the same frame.
 struct Animation {
-    angle: f32,
+    progress: f32, // the unsigned angle rotated so far, in radian
     time_per_quarter: std::time::Duration,
     target_quarters: u32,
+    sign: f32, // 1 for a counter-clockwise rotation, -1 for a clockwise rotation
     running: bool,
 }
 
 impl Animation {
-    const SECONDS_PER_FRAME: f64 = 1. / 60.;
+    // The time between two repaints while an animation is running
+    const FRAME_TIME: std::time::Duration = std::time::Duration::from_nanos(1_000_000_000 / 60);
 
-    fn new(time_per_quarter: std::time::Duration) -> Self {
+    fn new(time_per_quarter: std::time::Duration, clockwise: bool) -> Self {
         Animation {
-            angle: 0.,
+            progress: 0.,
             time_per_quarter,
             target_quarters: 1,
-            running: true,
+            sign: if clockwise { -1. } else { 1. },
+            running: !time_per_quarter.is_zero(),
         }
     }
 
+    /// The current angle of the animation in radian, positive in counter-clockwise direction. It
+    /// never goes beyond the target angle.
     fn angle(&self) -> f32 {
-        self.angle
+        let target_angle = self.target_quarters as f32 * std::f32::consts::FRAC_PI_2;
+        self.sign * self.progress.min(target_angle)
     }
 
-    fn running(&self) -> bool {
-        self.running
+    fn clockwise(&self) -> bool {
+        self.sign < 0.
     }
 
-    fn target_quarters(&self) -> u32 {
-        self.target_quarters
+    fn running(&self) -> bool {
+        self.running
     }
 
     fn add_quarter(&mut self) {
         self.target_quarters += 1;
     }
 
-    fn update(&mut self, ui: &mut egui::Ui) {
-        let dt = ui.input(|i| i.stable_dt);
-        let speed = std::f32::consts::PI / 2.0 / self.time_per_quarter.as_secs_f32();
-        self.angle += speed * dt;
-        let target_angle = self.target_quarters as f32 * std::f32::consts::PI / 2.0;
-        if self.angle > target_angle {
+    /// Advance the animation by `dt` seconds.
+    fn advance(&mut self, dt: f32) {
+        if self.time_per_quarter.is_zero() {
+            self.running = false;
+            return;
+        }
+        let speed = std::f32::consts::FRAC_PI_2 / self.time_per_quarter.as_secs_f32();
+        self.progress += speed * dt;
+        let target_angle = self.target_quarters as f32 * std::f32::consts::FRAC_PI_2;
+        if self.progress >= target_angle {
             self.running = false;
         }
     }
 
-    fn request_repaint(&mut self, ui: &mut egui::Ui) {
-        if self.running {
-            ui.ctx()
-                .request_repaint_after(std::time::Duration::from_secs_f64(Self::SECONDS_PER_FRAME));
+    /// Rotate `tile` by the target quarters in the direction of the animation. Return the
+    /// equivalent number of counter-clockwise quarter turns.
+    fn apply(&self, tile: &mut Tile) -> u32 {
+        for _ in 0..self.target_quarters {
+            if self.clockwise() {
+                tile.rotate_cw();
+            } else {
+                tile.rotate();
+            }
+        }
+        if self.clockwise() {
+            (4 - self.target_quarters % 4) % 4
+        } else {
+            self.target_quarters
         }
     }
 }
 
-impl Default for Animation {
-    fn default() -> Self {
-        Animation::new(std::time::Duration::from_millis(150))
-    }
-}
 
 // Stores only the animation state, the rest is stored in game.puzzle
 #[derive(Copy, Clone, Debug, Default)]
-struct TileSprite {
+pub(crate) struct TileSprite {
     animation: Option<Animation>,
-    locked: bool,
+    hinted: bool, // rotated into the correct orientation by a hint
+    power_pulse: Option<f32>, // seconds since the pulse reached the tile, negative before
+    scroll: f32, // accumulated mouse wheel movement which has not yet resulted in a rotation
 }
 
 impl TileSprite {
-    const TILE_SIZE: f32 = 40.;
-    // Maximum speed should be circa 75 milliseconds per 90 degrees (circa 4-5 animation frames)
-    // Minimum speed should be circa 250 milliseconds per 90 degrees
-    const ANIMATION_TIME_PER_QUARTER_ROTATION: std::time::Duration =
-        std::time::Duration::from_millis(75);
-
+    // Mouse wheel movement (in points) required for a quarter rotation, circa one wheel notch
+    const SCROLL_PER_QUARTER_ROTATION: f32 = 40.;
+    // Duration of the blend of a newly powered tile from its unpowered to its powered look
+    const POWER_PULSE_TIME: f32 = 0.15;
+    // Time the power pulse takes from one tile to the next
+    const POWER_PULSE_STEP: f32 = 0.03;
+
+    #[allow(clippy::too_many_arguments)]
     fn update(
         &mut self,
         tile: &mut Tile,
+        locked: &mut bool,
         index: Vec2,
-        location: egui::Pos2,
+        rect: egui::Rect,
         assets: &Assets,
+        settings: &Settings,
         ui: &mut egui::Ui,
     ) -> TileResponse {
-        let mut modified = false;
-        if let Some(animation) = self.animation.as_mut() {
-            animation.update(ui);
-            if !animation.running() {
-                for _ in 0..animation.target_quarters() {
-                    tile.rotate();
-                }
-                self.animation = None;
-                modified = true;
-            }
-        };
+        let time_per_quarter = settings.animation_time();
+        // The animation has been advanced by `Game::draw_tiles`.
+        let mut rotated_quarters = None;
+        self.finish_animation(tile, &mut rotated_quarters);
 
-        let rect = egui::Rect::from_min_size(location, egui::Vec2::splat(Self::TILE_SIZE));
-        let link_texture = self.select_link_texture(tile, assets);
         let angle = tile.orientation().to_angle() + self.animation.map(|a| a.angle()).unwrap_or(0.);
-        ui.put(
-            rect,
-            egui::Image::from_texture(&link_texture).rotate(-angle, egui::Vec2::splat(0.5)),
-        );
-        if tile.feature() != Feature::None {
-            let feature_texture = self
-                .select_feature_texture(tile, assets)
-                .expect("texture not found");
-            ui.put(rect, egui::Image::from_texture(&feature_texture));
+        Self::draw(tile, angle, rect, assets, settings, ui);
+        // The pulse has been advanced by `Game::draw_tiles`. Fade out the unpowered pipes on top.
+        if let Some(elapsed) = self.power_pulse && tile.powered() {
+            let opacity = (1. - elapsed / Self::POWER_PULSE_TIME).min(1.);
+            ui.put(rect, Self::link_image(tile.kind(), false, angle, opacity, assets, settings));
         }
 
-        if self.locked {
+        if settings.show_mistakes && !tile.is_correct() {
+            let tint = egui::Color32::from_rgba_unmultiplied(220, 40, 40, 70);
+            ui.painter().rect_filled(rect, 0., tint);
+        }
+        if self.hinted {
+            let glow = egui::Color32::from_rgba_unmultiplied(255, 215, 0, 90);
+            ui.painter().rect_stroke(rect.shrink(1.), 4., (2., glow), egui::StrokeKind::Inside);
+        }
+        if *locked {
             let painter = ui.painter();
             painter.rect_filled(rect, 0., egui::Rgba::from_black_alpha(0.5));
         }
         let id = format!("tile-{}-{}", index.x, index.y);
         let response = ui.interact(rect, egui::Id::from(id), egui::Sense::click());
         if response.secondary_clicked() && response.interact_pointer_pos().is_some() {
-            self.locked = !self.locked;
+            *locked = !*locked;
         }
-        if response.clicked() && response.interact_pointer_pos().is_some() && !self.locked {
+        if response.clicked() && response.interact_pointer_pos().is_some() && !*locked {
             // To be super-precise, we would need to distinguish between just clicked
             // (first click starts the timer) and modified (after rotation finished)
-            if let Some(animation) = self.animation.as_mut() {
-                animation.add_quarter();
-            } else {
-                self.animation = Some(Animation::new(Self::ANIMATION_TIME_PER_QUARTER_ROTATION));
-            }
+            self.queue_rotation(settings.rotate_clockwise, time_per_quarter);
         }
 
-        if let Some(animation) = self.animation.as_mut() {
-            animation.request_repaint(ui);
+        if settings.wheel_rotation && response.hovered() && !*locked {
+            self.update_scroll(time_per_quarter, ui);
+        } else {
+            self.scroll = 0.;
         }
 
+        // Without animation, a rotation is applied in the frame of the click.
+        self.finish_animation(tile, &mut rotated_quarters);
+
         let hovered = response.hovered();
 
         TileResponse {
             hovered,
-            modified,
+            rotated_quarters,
+        }
+    }
+
+    /// Draw the pipes of `tile`, rotated counter-clockwise by `angle` in radian, and its feature
+    /// into `rect`. Also used by the editor, which has no animations.
+    pub(crate) fn draw(
+        tile: &Tile,
+        angle: f32,
+        rect: egui::Rect,
+        assets: &Assets,
+        settings: &Settings,
+        ui: &mut egui::Ui,
+    ) {
+        ui.put(rect, Self::link_image(tile.kind(), tile.powered(), angle, 1., assets, settings));
+        if tile.feature() != Feature::None {
+            let feature_texture = Self::select_feature_texture(tile, assets)
+                .expect("texture not found");
+            ui.put(rect, egui::Image::from_texture(&feature_texture));
         }
     }
 
-    fn select_link_texture(&self, tile: &Tile, assets: &Assets) -> egui::TextureHandle {
-        let link = tile.kind();
-        let powered = tile.powered();
+    /// Rotate the tile by a quarter turn per mouse wheel notch. Scrolling up rotates
+    /// counter-clockwise, scrolling down rotates clockwise. The mouse wheel is ignored while the
+    /// command key is held.
+    fn update_scroll(&mut self, time_per_quarter: std::time::Duration, ui: &mut egui::Ui) {
+        let (delta, command) = ui.input(|i| (i.raw_scroll_delta.y, i.modifiers.command));
+        if command {
+            return;
+        }
 
-        let asset_type = if powered {
-            match link {
-                Kind::DeadEnd => AssetType::DeadEndPowered,
-                Kind::Straight => AssetType::StraightPowered,
-                Kind::Corner => AssetType::CornerPowered,
-                Kind::TIntersection => AssetType::TIntersectionPowered,
-                Kind::CrossIntersection => AssetType::CrossIntersectionPowered,
+        self.scroll += delta;
+        while self.scroll.abs() >= Self::SCROLL_PER_QUARTER_ROTATION {
+            let clockwise = self.scroll < 0.;
+            self.scroll -= Self::SCROLL_PER_QUARTER_ROTATION.copysign(self.scroll);
+            match self.animation.as_mut() {
+                Some(animation) if animation.clockwise() == clockwise => animation.add_quarter(),
+                Some(_) => (), // do not reverse a running animation
+                None => self.start_rotation(1, clockwise, time_per_quarter),
             }
+        }
+    }
+
+    /// Rotate the tile by another quarter turn, extending a running animation if there is one.
+    fn queue_rotation(&mut self, clockwise: bool, time_per_quarter: std::time::Duration) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.add_quarter();
         } else {
-            match link {
-                Kind::DeadEnd => AssetType::DeadEnd,
-                Kind::Straight => AssetType::Straight,
-                Kind::Corner => AssetType::Corner,
-                Kind::TIntersection => AssetType::TIntersection,
-                Kind::CrossIntersection => AssetType::CrossIntersection,
-            }
-        };
+            self.start_rotation(1, clockwise, time_per_quarter);
+        }
+    }
+
+    /// Start a rotation animation by `quarters` quarter turns in the given direction. With a
+    /// `time_per_quarter` of zero, the rotation is applied without animation.
+    fn start_rotation(&mut self, quarters: u32, clockwise: bool, time_per_quarter: std::time::Duration) {
+        let mut animation = Animation::new(time_per_quarter, clockwise);
+        for _ in 1..quarters {
+            animation.add_quarter();
+        }
+        self.animation = Some(animation);
+    }
+
+    /// Apply a finished animation to `tile` and add its counter-clockwise quarter turns to
+    /// `rotated_quarters`.
+    fn finish_animation(&mut self, tile: &mut Tile, rotated_quarters: &mut Option<u32>) {
+        if let Some(animation) = self.animation.take_if(|animation| !animation.running()) {
+            let quarters = animation.apply(tile);
+            *rotated_quarters = Some((rotated_quarters.unwrap_or(0) + quarters) % 4);
+        }
+    }
+
+    /// Return the image of the pipes of a tile of `kind`, rotated counter-clockwise by `angle` in
+    /// radian and drawn with `opacity` from 0 (invisible) to 1.
+    fn link_image(
+        kind: Kind,
+        powered: bool,
+        angle: f32,
+        opacity: f32,
+        assets: &Assets,
+        settings: &Settings,
+    ) -> egui::Image<'static> {
+        let (powered_texture, tint) = settings.color_scheme.link_style(powered);
+        let link_texture = Self::select_link_texture(kind, powered_texture, assets);
+        egui::Image::from_texture(&link_texture)
+            .tint(tint.gamma_multiply(opacity))
+            .rotate(-angle, egui::Vec2::splat(0.5))
+    }
 
+    fn select_link_texture(kind: Kind, powered: bool, assets: &Assets) -> egui::TextureHandle {
         assets
-            .get_rotated(asset_type, Orientation::Basic)
+            .get_rotated(link_asset_type(kind, powered), Orientation::Basic)
             .expect("texture not found")
-            .clone()
     }
 
-    fn select_feature_texture(&self, tile: &Tile, assets: &Assets) -> Option<egui::TextureHandle> {
-        let drain = if tile.powered() { AssetType::DrainPowered } else { AssetType::Drain };
-        let source = if tile.powered() { AssetType::SourcePowered } else { AssetType::Source };
+    fn select_feature_texture(tile: &Tile, assets: &Assets) -> Option<egui::TextureHandle> {
+        feature_asset_type(tile.feature(), tile.powered()).map(|asset_type| {
+            assets
+                .get_rotated(asset_type, Orientation::Basic)
+                .expect("texture not found")
+        })
+    }
+}
 
-        match tile.feature() {
-            Feature::None => None,
-            Feature::Drain => Some(
-                assets
-                    .get_rotated(drain, Orientation::Basic)
-                    .expect("texture not found"),
-            ),
-            Feature::Source => Some(
-                assets
-                    .get_rotated(source, Orientation::Basic)
-                    .expect("texture not found"),
-            ),
+/// Return the asset type of the pipes of a tile.
+fn link_asset_type(kind: Kind, powered: bool) -> AssetType {
+    if powered {
+        match kind {
+            Kind::DeadEnd => AssetType::DeadEndPowered,
+            Kind::Straight => AssetType::StraightPowered,
+            Kind::Corner => AssetType::CornerPowered,
+            Kind::TIntersection => AssetType::TIntersectionPowered,
+            Kind::CrossIntersection => AssetType::CrossIntersectionPowered,
+        }
+    } else {
+        match kind {
+            Kind::DeadEnd => AssetType::DeadEnd,
+            Kind::Straight => AssetType::Straight,
+            Kind::Corner => AssetType::Corner,
+            Kind::TIntersection => AssetType::TIntersection,
+            Kind::CrossIntersection => AssetType::CrossIntersection,
         }
     }
 }
 
+/// Return the asset type of the source or drain of a tile, if any.
+fn feature_asset_type(feature: Feature, powered: bool) -> Option<AssetType> {
+    match (feature, powered) {
+        (Feature::None, _) => None,
+        (Feature::Drain, false) => Some(AssetType::Drain),
+        (Feature::Drain, true) => Some(AssetType::DrainPowered),
+        (Feature::Source, false) => Some(AssetType::Source),
+        (Feature::Source, true) => Some(AssetType::SourcePowered),
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 struct TileResponse {
     hovered: bool,
-    modified: bool,
+    rotated_quarters: Option<u32>, // counter-clockwise turns, set once the animation has finished
 }
 
 #[derive(Clone, Eq, PartialEq)]
-struct WallSprite {
-    position: egui::Pos2,
+pub(crate) struct WallSprite {
+    position: Vec2,
+    orientation: Alignment,
     texture: egui::TextureHandle,
 }
 
 impl WallSprite {
-    fn new(position: Vec2, orientation: Alignment, assets: &Assets) -> Self {
-        let (offset, rotation) = match orientation {
-            Alignment::Horizontal => {
-                (-egui::Vec2::new(0.0, TILE_SIZE / 2.), Orientation::Ccw90)
-            }
-            Alignment::Vertical => {
-                (-egui::Vec2::new(TILE_SIZE / 2., 0.), Orientation::Basic)
-            }
+    pub(crate) fn new(position: Vec2, orientation: Alignment, assets: &Assets) -> Self {
+        let rotation = match orientation {
+            Alignment::Horizontal => Orientation::Ccw90,
+            Alignment::Vertical => Orientation::Basic,
         };
-
-        let position = egui::pos2(position.x as f32, position.y as f32)
-            * TILE_SIZE + offset;
         let texture = assets
             .get_rotated(AssetType::Wall, rotation)
             .expect("texture not found");
 
-        Self { position, texture }
+        Self { position, orientation, texture }
     }
 
-    fn draw(&self, top_left: egui::Vec2, ui: &mut egui::Ui) {
-        let rect = egui::Rect::from_min_size(self.position + top_left, egui::Vec2::splat(TILE_SIZE));
+    /// Draw the wall centered on the edge of its tile. With `Settings::high_contrast_marks`, a
+    /// thick line in the accent color is drawn along the edge on top of the texture.
+    pub(crate) fn draw(&self, layout: &BoardLayout, settings: &Settings, ui: &mut egui::Ui) {
+        let tile_size = layout.tile_size();
+        let offset = match self.orientation {
+            Alignment::Horizontal => -egui::Vec2::new(0.0, tile_size / 2.),
+            Alignment::Vertical => -egui::Vec2::new(tile_size / 2., 0.),
+        };
+        let rect = layout.tile_rect(self.position).translate(offset);
         ui.put(rect, egui::Image::from_texture(&self.texture));
+
+        if settings.high_contrast_marks {
+            let tile = layout.tile_rect(self.position);
+            let edge = match self.orientation {
+                Alignment::Horizontal => [tile.left_top(), tile.right_top()],
+                Alignment::Vertical => [tile.left_top(), tile.left_bottom()],
+            };
+            let stroke = egui::Stroke::new((tile_size / 10.).max(3.), settings.accent_color);
+            ui.painter().line_segment(edge, stroke);
+        }
     }
 }
 
@@ -607,9 +2027,18 @@ impl Timer {
         self.total
     }
 
-    /// Does nothing if the timer is already running.
-    fn start(&mut self) {
-        self.running = true;
+    /// Return the time left until `limit` is reached, zero if it has been exceeded.
+    fn remaining(&self, limit: std::time::Duration) -> std::time::Duration {
+        limit.saturating_sub(self.total)
+    }
+
+    /// Start counting at `secs_since_unknown`, which must use the same clock as `update`. Does
+    /// nothing if the timer is already running.
+    fn start(&mut self, secs_since_unknown: f64) {
+        if !self.running {
+            self.running = true;
+            self.last_value = Some(secs_since_unknown);
+        }
     }
 
     fn stop(&mut self) {
@@ -637,10 +2066,15 @@ impl std::fmt::Display for Timer {
     }
 }
 
-// A move consists of rotating a tile once. This can be done by multiple mouse button clicks.
-// But rotating another tile in-between counts separately, i.e., rotating tile A, then tile B,
-// then tile A again counts as rotating tile A twice (2 moves), or three moves in total.
-
+/// Counts the moves of the player, to be compared with `Puzzle::expected_moves`.
+///
+/// A move is an uninterrupted series of rotations of one tile, which may take several clicks.
+/// Rotating another tile in-between starts a new move, i.e. rotating tile A, then tile B, then
+/// tile A again counts as rotating tile A twice (2 moves), or three moves in total.
+///
+/// The expected number of moves is the number of tiles the generator rotated away from the
+/// solution. A player who turns each of these tiles into its final orientation in one go needs
+/// exactly that many moves; returning to a tile is deliberately counted as an extra move.
 #[derive(Default)]
 struct MoveCounter {
     move_count: u32,
@@ -652,11 +2086,918 @@ impl MoveCounter {
         self.move_count
     }
 
-    fn update(&mut self, updated_tile: Vec2) {
+    /// Count the rotation of `updated_tile`. Return true if the rotation counts as a new move.
+    fn update(&mut self, updated_tile: Vec2) -> bool {
         if self.last_rotated_tile.is_some_and(|t| t == updated_tile) {
-            return;
+            return false;
         }
         self.move_count += 1;
         self.last_rotated_tile = Some(updated_tile);
+        true
+    }
+
+    /// Revert a rotation previously passed to `update`. `counted` is the value returned by
+    /// `update` and `last_rotated_tile` the tile rotated before the reverted rotation.
+    fn revert(&mut self, counted: bool, last_rotated_tile: Option<Vec2>) {
+        if counted {
+            self.move_count -= 1;
+        }
+        self.last_rotated_tile = last_rotated_tile;
+    }
+}
+
+/// A committed rotation of a single tile, i.e. the result of a finished rotation animation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Rotation {
+    coord: Vec2,
+    quarters: u32, // number of counter-clockwise quarter turns
+    orientation: Orientation, // the orientation of the tile after the rotation
+    counted: bool, // whether the rotation was counted as a new move by the MoveCounter
+}
+
+/// The undo and redo stacks of rotations.
+#[derive(Default)]
+struct History {
+    undo_stack: Vec<Rotation>,
+    redo_stack: Vec<Rotation>,
+}
+
+impl History {
+    /// Record a new rotation. This clears the redo stack.
+    fn push(&mut self, rotation: Rotation) {
+        self.undo_stack.push(rotation);
+        self.redo_stack.clear();
+    }
+
+    fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Return the rotation to revert and move it to the redo stack.
+    fn undo(&mut self) -> Option<Rotation> {
+        let rotation = self.undo_stack.pop()?;
+        self.redo_stack.push(rotation);
+        Some(rotation)
+    }
+
+    /// Return the rotation to re-apply and move it back to the undo stack.
+    fn redo(&mut self) -> Option<Rotation> {
+        let rotation = self.redo_stack.pop()?;
+        self.undo_stack.push(rotation);
+        Some(rotation)
+    }
+
+    /// Return the number of rotations which have not been undone.
+    fn applied(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Iterate over the applied rotations in the order they were made, followed by the undone
+    /// rotations in the order they are redone. The flag is true for undone rotations.
+    fn rotations(&self) -> impl Iterator<Item = (&Rotation, bool)> {
+        self.undo_stack.iter().map(|rotation| (rotation, false))
+            .chain(self.redo_stack.iter().rev().map(|rotation| (rotation, true)))
+    }
+
+    /// Return the tile of the most recent rotation which has not been undone.
+    fn last_rotated_tile(&self) -> Option<Vec2> {
+        self.undo_stack.last().map(|rotation| rotation.coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::puzzle::{Builder, Difficulty, Options, Wall};
+
+    /// Create a game of `puzzle`, with the textures loaded into the returned context.
+    fn test_game(settings: Settings, puzzle: Puzzle) -> (egui::Context, Game) {
+        let context = egui::Context::default();
+        let mut assets = Assets::new();
+        assets.load_all(&context).unwrap();
+        (context, Game::new(puzzle, assets, settings))
+    }
+
+    /// Run a frame of `game` with `input` and return the events of the game.
+    fn run_input(
+        context: &egui::Context,
+        game: &mut Game,
+        input: egui::RawInput,
+    ) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let _ = context.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| events.extend(game.update(ui)));
+        });
+        events
+    }
+
+    /// Run a frame of `game` at `time` in seconds and return the events of the game.
+    fn run_frame(context: &egui::Context, game: &mut Game, time: f64) -> Vec<GameEvent> {
+        run_input(context, game, egui::RawInput { time: Some(time), ..Default::default() })
+    }
+
+    /// Run a frame of `game` in which `key` is pressed with `modifiers` and return the events of
+    /// the game.
+    fn press_key(
+        context: &egui::Context,
+        game: &mut Game,
+        modifiers: egui::Modifiers,
+        key: egui::Key,
+    ) -> Vec<GameEvent> {
+        let mut input = egui::RawInput::default();
+        let key = egui::Event::Key { key, physical_key: None, pressed: true, repeat: false, modifiers };
+        input.events.push(key);
+        run_input(context, game, input)
+    }
+
+    #[test]
+    fn animation_ends_at_new_orientation() {
+        use std::f32::consts::TAU;
+
+        for clockwise in [false, true] {
+            for quarters in 1..=5 {
+                let mut tile = Builder::new().build().grid()[Vec2::new(0, 0)];
+                let start_angle = tile.orientation().to_angle();
+
+                let mut animation = Animation::new(std::time::Duration::from_millis(100), clockwise);
+                for _ in 1..quarters {
+                    animation.add_quarter();
+                }
+                let mut last_angle = start_angle;
+                while animation.running() {
+                    animation.advance(1. / 60.);
+                    let angle = start_angle + animation.angle();
+                    // The sprite always spins in the direction of the rotation.
+                    assert!(if clockwise { angle <= last_angle } else { angle >= last_angle });
+                    last_angle = angle;
+                }
+                animation.apply(&mut tile);
+
+                let difference = (last_angle - tile.orientation().to_angle()).rem_euclid(TAU);
+                assert!(difference < 1e-4 || TAU - difference < 1e-4, "{clockwise} {quarters}");
+            }
+        }
+    }
+
+    #[test]
+    fn instant_rotation() {
+        let mut tile = Builder::new().build().grid()[Vec2::new(0, 0)];
+        let orientation = tile.orientation();
+        let mut sprite = TileSprite::default();
+        sprite.queue_rotation(true, std::time::Duration::ZERO);
+        sprite.queue_rotation(true, std::time::Duration::ZERO);
+
+        let mut rotated_quarters = None;
+        sprite.finish_animation(&mut tile, &mut rotated_quarters);
+        assert!(sprite.animation.is_none());
+        assert_eq!(rotated_quarters, Some(2));
+        assert_eq!(tile.orientation(), orientation.next_cw().next_cw());
+    }
+
+    #[test]
+    fn concurrent_rotations_finish_together() {
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings, Builder::new().build());
+        run_frame(&context, &mut game, 0.);
+
+        let (a, b) = (Vec2::new(0, 0), Vec2::new(2, 1));
+        let orientations = [a, b].map(|coord| game.puzzle.grid()[coord].orientation());
+        game.tile_widgets[a].start_rotation(1, false, game.settings.animation_time());
+        game.tile_widgets[b].start_rotation(1, false, game.settings.animation_time());
+        let mut time = 0.;
+        while game.tile_widgets.iter().any(|sprite| sprite.animation.is_some()) {
+            time += 1. / 60.;
+            run_frame(&context, &mut game, time);
+            // Both animations have always made the same progress.
+            let angles = [a, b].map(|coord| game.tile_widgets[coord].animation.map(|a| a.angle()));
+            assert_eq!(angles[0], angles[1]);
+        }
+
+        // Both rotations are recorded, although they finished in the same frame.
+        assert_eq!(game.history.applied(), 2);
+        assert_eq!(game.move_counter.get(), 2);
+        assert_eq!(game.puzzle.grid()[a].orientation(), orientations[0].next_ccw());
+        assert_eq!(game.puzzle.grid()[b].orientation(), orientations[1].next_ccw());
+        let powered = game.puzzle.grid().map(|tile| tile.powered());
+        game.puzzle.calc_energy();
+        assert_eq!(game.puzzle.grid().map(|tile| tile.powered()), powered);
+    }
+
+    #[test]
+    fn newly_powered_tiles_pulse() {
+        let pulsing = |game: &Game| {
+            game.tile_widgets.indexed_iter()
+                .filter(|(_, sprite)| sprite.power_pulse.is_some())
+                .map(|(coord, _)| coord)
+                .collect::<Vec<_>>()
+        };
+
+        for power_pulse in [true, false] {
+            let settings = Settings { tutorial_seen: true, power_pulse, ..Default::default() };
+            let (context, mut game) = test_game(settings, Builder::new().with_seed(4).build());
+            run_frame(&context, &mut game, 0.);
+            assert!(pulsing(&game).is_empty());
+
+            // Turn the source of the solution away and back, which powers the board again.
+            let coord = *game.puzzle.source();
+            game.puzzle.solve();
+            game.puzzle.grid_mut()[coord].rotate();
+            game.puzzle.calc_energy();
+            let before = game.puzzle.grid().map(|tile| tile.powered());
+            game.tile_widgets[coord].start_rotation(3, false, std::time::Duration::ZERO);
+            run_frame(&context, &mut game, 0.1);
+            let gained = game.puzzle.grid().indexed_iter()
+                .filter(|&(coord, tile)| tile.powered() && !before[coord])
+                .map(|(coord, _)| coord)
+                .collect::<Vec<_>>();
+            assert!(!gained.is_empty());
+            if power_pulse {
+                assert_eq!(pulsing(&game), gained);
+                // The pulse flows outward from the rotated tile, one step per connection.
+                let delay = |tile| match game.tile_widgets[tile].power_pulse {
+                    _ if tile == coord => 0.,
+                    pulse => -pulse.unwrap(),
+                };
+                let step = TileSprite::POWER_PULSE_STEP;
+                assert!(gained.iter().any(|&tile| delay(tile) > step));
+                for &tile in gained.iter().filter(|&&tile| tile != coord) {
+                    assert!(delay(tile) > 0.);
+                    let earlier = Direction::all().into_iter().any(|direction| {
+                        let neighbor = game.puzzle.grid().normalized_coord(tile + direction.to_vec2());
+                        game.puzzle.connected(tile, direction)
+                            && (neighbor == coord || gained.contains(&neighbor))
+                            && (delay(neighbor) + step - delay(tile)).abs() < 1e-6
+                    });
+                    assert!(earlier, "{tile:?}");
+                }
+            } else {
+                assert!(pulsing(&game).is_empty());
+            }
+
+            // The pulse is over after a while.
+            for i in 1..=120 {
+                run_frame(&context, &mut game, 0.1 + f64::from(i) / 60.);
+            }
+            assert!(pulsing(&game).is_empty());
+        }
+    }
+
+    #[test]
+    fn render_board_to_image() {
+        let options = Options {
+            board_size: 4,
+            wrapping: true,
+            ..Default::default()
+        };
+        let mut puzzle = Builder::new().with_options(options).build();
+        puzzle.solve();
+        let (_context, game) = test_game(Settings::default(), puzzle);
+
+        let powered = game.render_to_image(true);
+        let unpowered = game.render_to_image(false);
+        assert_eq!(powered.dimensions(), (160, 160));
+        assert_eq!(unpowered.dimensions(), (160, 160));
+        assert_ne!(powered, unpowered);
+    }
+
+    #[test]
+    fn undo_and_redo_move_count() {
+        let a = Vec2::new(0, 0);
+        let b = Vec2::new(1, 0);
+        let mut move_counter = MoveCounter::default();
+        let mut history = History::default();
+        for coord in [a, a, b] {
+            let counted = move_counter.update(coord);
+            history.push(Rotation { coord, quarters: 1, orientation: Orientation::Ccw90, counted });
+        }
+        assert_eq!(move_counter.get(), 2);
+
+        let rotation = history.undo().unwrap();
+        assert_eq!(rotation.coord, b);
+        move_counter.revert(rotation.counted, history.last_rotated_tile());
+        assert_eq!(move_counter.get(), 1);
+
+        let rotation = history.undo().unwrap();
+        assert_eq!(rotation.coord, a);
+        move_counter.revert(rotation.counted, history.last_rotated_tile());
+        assert_eq!(move_counter.get(), 1);
+
+        // Rotating tile A again continues the previous move
+        let rotation = history.redo().unwrap();
+        assert!(!move_counter.update(rotation.coord));
+        assert_eq!(move_counter.get(), 1);
+        assert!(history.can_redo());
+
+        // A new rotation clears the redo stack
+        let counted = move_counter.update(b);
+        history.push(Rotation { coord: b, quarters: 2, orientation: Orientation::Ccw180, counted });
+        assert!(!history.can_redo());
+        assert_eq!(move_counter.get(), 2);
+    }
+
+    #[test]
+    fn go_to_move_of_history() {
+        let (_context, mut game) = test_game(Settings::default(), Builder::new().build());
+        game.state = GameState::Running;
+        let start = game.puzzle.clone();
+
+        // Rotate like the player, as in `update_game_board`.
+        let coords = [Vec2::new(0, 0), Vec2::new(1, 0), Vec2::new(0, 0), Vec2::new(2, 1)];
+        for coord in coords {
+            game.puzzle.grid_mut()[coord].rotate();
+            let counted = game.move_counter.update(coord);
+            let orientation = game.puzzle.grid()[coord].orientation();
+            game.history.push(Rotation { coord, quarters: 1, orientation, counted });
+            game.rotation_applied(coord);
+        }
+        let end = game.puzzle.clone();
+        assert_eq!(game.move_counter.get(), 4);
+
+        game.go_to_move(1);
+        assert_eq!(game.history.applied(), 1);
+        assert_eq!(game.move_counter.get(), 1);
+        let undone = game.history.rotations().map(|(_, undone)| undone).collect::<Vec<_>>();
+        assert_eq!(undone, [false, true, true, true]);
+        let listed = game.history.rotations().map(|(rotation, _)| rotation.coord).collect::<Vec<_>>();
+        assert_eq!(listed, coords);
+
+        game.go_to_move(0);
+        assert!(game.puzzle == start);
+        game.go_to_move(10);
+        assert_eq!(game.history.applied(), 4);
+        assert!(game.puzzle == end);
+        assert_eq!(game.move_counter.get(), 4);
+    }
+
+    #[test]
+    fn returning_to_a_tile_is_a_new_move() {
+        let a = Vec2::new(0, 0);
+        let b = Vec2::new(1, 0);
+        let mut move_counter = MoveCounter::default();
+        assert!(move_counter.update(a));
+        assert!(!move_counter.update(a));
+        assert!(move_counter.update(b));
+        assert!(move_counter.update(a));
+        assert_eq!(move_counter.get(), 3);
+
+        // Solving a puzzle by rotating each tile in one go takes the expected number of moves.
+        let options = Options { board_size: 6, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        let mut move_counter = MoveCounter::default();
+        for (coord, tile) in puzzle.grid().indexed_iter() {
+            for _ in 0..tile.quarters_to_solution() {
+                move_counter.update(coord);
+            }
+        }
+        assert_eq!(move_counter.get(), puzzle.expected_moves());
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let grid = Grid::<u8>::with_size(3, 4, 0);
+        let corner = Vec2::new(0, 0);
+        assert_eq!(move_cursor(&grid, corner, Direction::Right, false), Vec2::new(1, 0));
+        assert_eq!(move_cursor(&grid, corner, Direction::Left, false), corner);
+        assert_eq!(move_cursor(&grid, corner, Direction::Up, false), corner);
+        assert_eq!(move_cursor(&grid, corner, Direction::Left, true), Vec2::new(3, 0));
+        assert_eq!(move_cursor(&grid, corner, Direction::Up, true), Vec2::new(0, 2));
+        assert_eq!(move_cursor(&grid, Vec2::new(3, 2), Direction::Down, true), Vec2::new(3, 0));
+    }
+
+    #[test]
+    fn new_game_needs_confirmation_while_in_progress() {
+        let (_context, mut game) = test_game(Settings::default(), Builder::new().build());
+
+        let mut events = vec![GameEvent::NewGame];
+        game.confirm_new_game(&mut events);
+        assert_eq!(events, [GameEvent::NewGame]);
+
+        // A game which runs before the first move has no progress yet.
+        game.state = GameState::Running;
+        let mut events = vec![GameEvent::NewGame];
+        game.confirm_new_game(&mut events);
+        assert_eq!(events, [GameEvent::NewGame]);
+
+        game.move_counter.update(Vec2::new(0, 0));
+        let mut events = vec![GameEvent::Undo, GameEvent::NewGame];
+        game.confirm_new_game(&mut events);
+        assert_eq!(events, [GameEvent::Undo]);
+        assert_eq!(game.pending_new_game, Some((GameEvent::NewGame, GameState::Running)));
+        assert_eq!(game.state, GameState::Paused { game_was_started: true });
+
+        // Quitting to the menu from the pause modal needs a confirmation, too.
+        game.pending_new_game = None;
+        let mut events = vec![GameEvent::Close];
+        game.confirm_new_game(&mut events);
+        assert!(events.is_empty());
+        let paused = GameState::Paused { game_was_started: true };
+        assert_eq!(game.pending_new_game, Some((GameEvent::Close, paused)));
+    }
+
+    #[test]
+    fn color_schemes_distinguish_power() {
+        for color_scheme in ColorScheme::iter() {
+            assert_ne!(color_scheme.link_style(true), color_scheme.link_style(false));
+            assert_eq!(color_scheme.to_string().parse::<ColorScheme>(), Ok(color_scheme));
+        }
+    }
+
+    #[test]
+    fn solved_event_is_pushed_once() {
+        let puzzle = Builder::new().build();
+        let code = puzzle.to_code();
+        let expected_moves = puzzle.expected_moves();
+        // The tutorial of the first run would hold back the solved modal.
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings, puzzle);
+        game.state = GameState::Running;
+        game.puzzle.solve();
+        game.rotation_applied(Vec2::new(0, 0));
+
+        let events = (0..3)
+            .flat_map(|i| run_frame(&context, &mut game, f64::from(i)))
+            .collect::<Vec<_>>();
+        let solved = events.iter()
+            .filter(|event| matches!(event, GameEvent::Solved { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(solved.len(), 1);
+        let GameEvent::Solved { moves, expected_moves: expected, code: solved_code, .. } = solved[0]
+        else {
+            unreachable!();
+        };
+        assert_eq!((*moves, *expected, solved_code), (0, expected_moves, &code));
+    }
+
+    #[test]
+    fn auto_solve_plays_solution() {
+        let options = Options { board_size: 5, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        let settings = Settings { animation_ms: 0, tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings, puzzle);
+
+        // Stopping the solver right away still forfeits the score.
+        game.toggle_auto_solve();
+        game.toggle_auto_solve();
+        assert!(game.auto_solve.is_none());
+        assert!(game.assistance.auto_solved);
+
+        game.toggle_auto_solve();
+        let events = (0..100)
+            .flat_map(|i| run_frame(&context, &mut game, f64::from(i) / 60.))
+            .collect::<Vec<_>>();
+        assert!(game.puzzle.solved());
+        assert!(game.auto_solve.is_none());
+        assert_eq!(game.state, GameState::Ended { score: 0, solved: true });
+        assert!(events.iter().any(|event| matches!(event, GameEvent::Solved { assisted: true, .. })));
+    }
+
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn settings_are_persisted() {
+        let settings = Settings {
+            high_contrast_marks: true,
+            accent_color: egui::Color32::from_rgb(0, 170, 255),
+            timer_starts_immediately: true,
+            power_pulse: false,
+            ..Default::default()
+        };
+        let mut storage = MemoryStorage::default();
+        settings.write(&mut storage);
+        assert_eq!(Settings::read(&storage), settings);
+
+        // Unreadable values fall back to the defaults.
+        storage.set_string("accent_color", "blue".to_string());
+        assert_eq!(Settings::read(&storage).accent_color, Settings::default().accent_color);
+    }
+
+    #[test]
+    fn locks_are_saved_and_cleared_on_restart() {
+        let (_context, mut game) = test_game(Settings::default(), Builder::new().build());
+        let coord = Vec2::new(1, 2);
+        let powered = game.puzzle.powered_fraction();
+        game.set_locked(coord, true);
+        assert_eq!(game.is_locked(coord), Some(true));
+        assert_eq!(game.is_locked(Vec2::new(3, 0)), None);
+        game.puzzle.calc_energy();
+        assert_eq!(game.puzzle.powered_fraction(), powered);
+
+        let mut storage = MemoryStorage::default();
+        game.save(&mut storage);
+        let mut restored = Game::restore(&storage, game.assets.clone()).unwrap();
+        assert_eq!(restored.is_locked(coord), Some(true));
+        assert_eq!(restored.is_locked(Vec2::new(0, 0)), Some(false));
+
+        restored.restart();
+        assert_eq!(restored.is_locked(coord), Some(false));
+    }
+
+    #[test]
+    fn damaged_save_is_not_restored() {
+        let (_context, game) = test_game(Settings::default(), Builder::new().build());
+        let assets = game.assets.clone();
+        let larger = Builder::new().with_options(Options { board_size: 6, ..Default::default() }).build();
+        let saved = |puzzle: &Puzzle, locked: &Grid<bool>| SavedGame {
+            starting_position: game.starting_position.clone(),
+            puzzle: puzzle.clone(),
+            locked: locked.clone(),
+            elapsed: std::time::Duration::ZERO,
+            move_count: 1,
+            assistance: Assistance::default(),
+        };
+
+        let mut storage = MemoryStorage::default();
+        eframe::set_value(&mut storage, Game::STORAGE_KEY, &saved(&game.puzzle, &game.locked));
+        assert!(Game::restore(&storage, assets.clone()).is_some());
+
+        eframe::set_value(&mut storage, Game::STORAGE_KEY, &saved(&larger, &game.locked));
+        assert!(Game::restore(&storage, assets.clone()).is_none());
+
+        let locked = Grid::<bool>::with_size(6, 6, false);
+        eframe::set_value(&mut storage, Game::STORAGE_KEY, &saved(&game.puzzle, &locked));
+        assert!(Game::restore(&storage, assets.clone()).is_none());
+
+        // The first tile is cut from the list of tiles of the starting position.
+        eframe::set_value(&mut storage, Game::STORAGE_KEY, &saved(&game.puzzle, &game.locked));
+        let stored = storage.get_string(Game::STORAGE_KEY).unwrap();
+        let first_tile = stored.find("data:[").unwrap() + "data:[".len();
+        let second_tile = first_tile + stored[first_tile..].find("),(").unwrap() + "),".len();
+        let truncated = format!("{}{}", &stored[..first_tile], &stored[second_tile..]);
+        storage.set_string(Game::STORAGE_KEY, truncated);
+        assert!(eframe::get_value::<SavedGame>(&storage, Game::STORAGE_KEY).is_some());
+        assert!(Game::restore(&storage, assets).is_none());
+    }
+
+    #[test]
+    fn lock_correct_tiles() {
+        let options = Options { board_size: 6, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        let (_context, mut game) = test_game(Settings::default(), puzzle);
+        game.lock_correct_tiles();
+
+        for (index, tile) in game.puzzle.grid().indexed_iter() {
+            let expected = tile.is_correct() && tile.kind() != Kind::CrossIntersection;
+            assert_eq!(game.is_locked(index), Some(expected));
+        }
+        assert!(game.assistance.mistakes_shown);
+    }
+
+    #[test]
+    fn tutorial_on_first_run() {
+        let (_context, game) = test_game(Settings::default(), Builder::new().build());
+        assert!(game.show_tutorial);
+
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (_context, game) = test_game(settings, Builder::new().build());
+        assert!(!game.show_tutorial);
+    }
+
+    #[test]
+    fn no_shortcuts_behind_the_tutorial() {
+        let (context, mut game) = test_game(Settings::default(), Builder::new().build());
+        assert!(game.show_tutorial);
+        game.state = GameState::Running;
+        let press = |game: &mut Game, modifiers, key| press_key(&context, game, modifiers, key);
+
+        // A move to undo and a cursor on it, as if the tutorial was reopened during the game.
+        let coord = Vec2::new(0, 0);
+        game.puzzle.grid_mut()[coord].rotate();
+        let counted = game.move_counter.update(coord);
+        let orientation = game.puzzle.grid()[coord].orientation();
+        game.history.push(Rotation { coord, quarters: 1, orientation, counted });
+        game.rotation_applied(coord);
+        game.cursor = Some(coord);
+        let position = game.puzzle.clone();
+
+        press(&mut game, egui::Modifiers::NONE, egui::Key::H);
+        press(&mut game, egui::Modifiers::SHIFT, egui::Key::H);
+        press(&mut game, egui::Modifiers::SHIFT, egui::Key::L);
+        press(&mut game, egui::Modifiers::NONE, egui::Key::L);
+        press(&mut game, egui::Modifiers::NONE, egui::Key::Space);
+        press(&mut game, egui::Modifiers::NONE, egui::Key::Enter);
+        press(&mut game, egui::Modifiers::COMMAND, egui::Key::Z);
+        press(&mut game, egui::Modifiers::NONE, egui::Key::Escape);
+        assert!(press(&mut game, egui::Modifiers::NONE, egui::Key::N).is_empty());
+        assert_eq!(game.state, GameState::Running);
+        assert_eq!(game.assistance.hints_used, 0);
+        assert_eq!(game.assistance.path_hints_used, 0);
+        assert!(game.path_hint.is_none());
+        assert!(game.puzzle.grid().indices_iter().all(|coord| game.is_locked(coord) == Some(false)));
+        assert!(game.tile_widgets.iter().all(|sprite| sprite.animation.is_none()));
+        assert_eq!(game.history.applied(), 1);
+        assert!(game.puzzle == position);
+
+        // The solver waits for the tutorial, too.
+        game.toggle_auto_solve();
+        run_frame(&context, &mut game, 1.);
+        assert!(game.tile_widgets.iter().all(|sprite| sprite.animation.is_none()));
+        assert!(game.puzzle == position);
+    }
+
+    #[test]
+    fn tile_size_fits_board() {
+        let range = 20. ..=80.;
+        // The smaller dimension limits the size, which is rounded down.
+        assert_eq!(fit_tile_size(egui::Vec2::new(500., 300.), 8, 8, range.clone()), 37.);
+        assert_eq!(fit_tile_size(egui::Vec2::new(300., 500.), 8, 12, range.clone()), 25.);
+        // The board is not shrunk below the minimum or grown beyond the maximum.
+        assert_eq!(fit_tile_size(egui::Vec2::new(200., 200.), 40, 40, range.clone()), 20.);
+        assert_eq!(fit_tile_size(egui::Vec2::new(2000., 2000.), 5, 5, range), 80.);
+        assert_eq!(fit_tile_size(egui::Vec2::new(-10., 100.), 5, 5, 20. ..=10.), 20.);
+    }
+
+    #[test]
+    fn board_layout_maps_both_ways() {
+        let options = Options { board_size: 4, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        // The top left corner is rounded down to whole points.
+        let layout = BoardLayout::new(&puzzle, egui::pos2(10.5, 20.7), 40.);
+        assert_eq!(layout.grid_to_screen(Vec2::new(0, 0)), egui::pos2(10., 20.));
+        assert_eq!(layout.grid_to_screen(Vec2::new(2, 1)), egui::pos2(90., 60.));
+        assert_eq!(layout.tile_rect(Vec2::new(3, 3)).max, egui::pos2(170., 180.));
+        assert_eq!(layout.board_rect(), egui::Rect::from_min_max(egui::pos2(10., 20.), egui::pos2(170., 180.)));
+
+        for coord in puzzle.grid().indices_iter() {
+            let rect = layout.tile_rect(coord);
+            assert_eq!(layout.screen_to_grid(rect.min), Some(coord));
+            assert_eq!(layout.screen_to_grid(rect.center()), Some(coord));
+            assert_eq!(layout.screen_to_grid(rect.max - egui::vec2(0.01, 0.01)), Some(coord));
+        }
+        // The edge between two tiles belongs to the right one.
+        assert_eq!(layout.screen_to_grid(egui::pos2(50., 30.)), Some(Vec2::new(1, 0)));
+        assert_eq!(layout.screen_to_grid(egui::pos2(9.9, 30.)), None);
+        assert_eq!(layout.screen_to_grid(egui::pos2(30., 180.)), None);
+
+        // A wrapping board repeats beyond its edges.
+        let options = Options { wrapping: true, ..options };
+        let puzzle = Builder::new().with_options(options).build();
+        let layout = BoardLayout::new(&puzzle, egui::pos2(10., 20.), 40.);
+        assert_eq!(layout.screen_to_grid(egui::pos2(9.9, 30.)), Some(Vec2::new(3, 0)));
+        assert_eq!(layout.screen_to_grid(egui::pos2(30., 180.)), Some(Vec2::new(0, 0)));
+        assert_eq!(layout.wrapped(Vec2::new(-5, 9)), Some(Vec2::new(3, 1)));
+    }
+
+    #[test]
+    fn zoom_with_keys() {
+        let (context, mut game) = test_game(Settings::default(), Builder::new().build());
+        let press = |game: &mut Game, key| press_key(&context, game, egui::Modifiers::NONE, key);
+
+        let events = press(&mut game, egui::Key::Plus);
+        assert!(matches!(events[..], [GameEvent::SettingsChanged(ref settings)] if settings.zoom_percent == 110));
+        for _ in 0..20 {
+            press(&mut game, egui::Key::Minus);
+        }
+        assert_eq!(game.settings.zoom_percent, Game::MIN_ZOOM);
+        // The zoom factor is clamped, so further presses do not change the settings.
+        assert!(press(&mut game, egui::Key::Minus).is_empty());
+    }
+
+    #[test]
+    fn pause_with_keys() {
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings, Builder::new().build());
+        game.state = GameState::Running;
+        let press = |game: &mut Game, key| press_key(&context, game, egui::Modifiers::NONE, key);
+
+        // Esc in the pause modal continues the game instead of pausing it again.
+        assert_eq!(press(&mut game, egui::Key::Escape), [GameEvent::Pause]);
+        assert_eq!(game.state, GameState::Paused { game_was_started: true });
+        assert!(press(&mut game, egui::Key::Escape).is_empty());
+        assert_eq!(game.state, GameState::Running);
+
+        assert_eq!(press(&mut game, egui::Key::P), [GameEvent::Pause]);
+        press(&mut game, egui::Key::P);
+        assert_eq!(game.state, GameState::Running);
+
+        // Before the first move, continuing returns to the state before the start.
+        game.state = GameState::BeforeStart;
+        press(&mut game, egui::Key::Escape);
+        assert_eq!(game.state, GameState::Paused { game_was_started: false });
+        press(&mut game, egui::Key::Escape);
+        assert_eq!(game.state, GameState::BeforeStart);
+    }
+
+    #[test]
+    fn shortcuts_of_restart_and_new_game() {
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings, Builder::new().build());
+        game.state = GameState::Running;
+        let press = |game: &mut Game, modifiers, key| press_key(&context, game, modifiers, key);
+
+        // Shortcuts of the system do not trigger those of the game.
+        assert!(press(&mut game, egui::Modifiers::COMMAND, egui::Key::R).is_empty());
+        assert!(press(&mut game, egui::Modifiers::COMMAND, egui::Key::N).is_empty());
+        assert_eq!(game.state, GameState::Running);
+
+        // Without a move, restarting loses nothing.
+        assert_eq!(press(&mut game, egui::Modifiers::NONE, egui::Key::R), [GameEvent::Restart]);
+        assert_eq!(game.state, GameState::BeforeStart);
+
+        // Otherwise restarting needs a confirmation, like a new game.
+        game.state = GameState::Running;
+        game.move_counter.update(Vec2::new(0, 0));
+        assert!(press(&mut game, egui::Modifiers::NONE, egui::Key::R).is_empty());
+        assert_eq!(game.pending_new_game, Some((GameEvent::Restart, GameState::Running)));
+        assert_eq!(game.move_counter.get(), 1);
+
+        game.pending_new_game = None;
+        game.state = GameState::Running;
+        assert!(press(&mut game, egui::Modifiers::NONE, egui::Key::N).is_empty());
+        let new_game = GameEvent::NewGameWithOptions(*game.puzzle.options());
+        assert_eq!(game.pending_new_game, Some((new_game, GameState::Running)));
+    }
+
+    #[test]
+    fn timer_starts_on_first_move_or_immediately() {
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let (context, mut game) = test_game(settings.clone(), Builder::new().build());
+        run_frame(&context, &mut game, 1.);
+        run_frame(&context, &mut game, 2.);
+        assert_eq!(game.state, GameState::BeforeStart);
+        assert_eq!(game.timer.duration(), std::time::Duration::ZERO);
+
+        let settings = Settings { timer_starts_immediately: true, ..settings };
+        let (context, mut game) = test_game(settings.clone(), Builder::new().build());
+        run_frame(&context, &mut game, 1.);
+        run_frame(&context, &mut game, 3.);
+        assert_eq!(game.state, GameState::Running);
+        assert_eq!(game.timer.duration(), std::time::Duration::from_secs(2));
+        assert_eq!(game.move_counter.get(), 0);
+        game.restart();
+        assert_eq!(game.timer.duration(), std::time::Duration::ZERO);
+        run_frame(&context, &mut game, 4.);
+        assert_eq!(game.state, GameState::Running);
+
+        // The tutorial of the first run holds back the timer until it is dismissed.
+        let settings = Settings { tutorial_seen: false, ..settings };
+        let (context, mut game) = test_game(settings, Builder::new().build());
+        run_frame(&context, &mut game, 1.);
+        assert_eq!(game.state, GameState::BeforeStart);
+    }
+
+    #[test]
+    fn timer_sums_running_intervals() {
+        let mut timer = Timer::default();
+        // (start, frames while running, stop)
+        let intervals = [
+            (1.0, vec![1.016, 1.5, 2.25], 2.3),
+            (10.1, vec![10.2], 10.25),
+            (20.0, vec![], 20.0),
+            (30.5, vec![30.6, 31.7], 31.75),
+        ];
+        let mut expected = 0.;
+        for (start, frames, stop) in intervals {
+            timer.start(start);
+            // Starting a running timer again does not reset the interval.
+            timer.start(start + 0.001);
+            for frame in frames {
+                timer.update(frame);
+            }
+            timer.update(stop);
+            timer.stop();
+            // Updates of a stopped timer are ignored.
+            timer.update(stop + 1.);
+            expected += stop - start;
+        }
+        assert!((timer.duration().as_secs_f64() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn time_limit_ends_game() {
+        let options = Options {
+            board_size: 4,
+            time_limit: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let puzzle = Builder::new().with_options(options).build();
+        let (_context, mut game) = test_game(Settings::default(), puzzle);
+        game.state = GameState::Running;
+        game.timer.start(0.);
+
+        game.timer.update(59.);
+        game.check_time_limit();
+        assert_eq!(game.state, GameState::Running);
+        assert_eq!(game.timer.remaining(options.time_limit.unwrap()).as_secs(), 1);
+
+        game.timer.update(60.5);
+        game.check_time_limit();
+        assert_eq!(game.state, GameState::Ended { score: 0, solved: false });
+        assert_eq!(game.timer.remaining(options.time_limit.unwrap()), std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn practice_has_no_time_limit_and_no_score() {
+        let options = Options {
+            board_size: 4,
+            time_limit: Some(std::time::Duration::from_secs(60)),
+            practice: true,
+            ..Default::default()
+        };
+        let puzzle = Builder::new().with_options(options).build();
+        let (_context, mut game) = test_game(Settings::default(), puzzle);
+        game.state = GameState::Running;
+        game.timer.start(0.);
+        game.timer.update(120.);
+        game.check_time_limit();
+        assert_eq!(game.state, GameState::Running);
+
+        game.puzzle.solve();
+        game.rotation_applied(Vec2::new(0, 0));
+        assert_eq!(game.state, GameState::Ended { score: 0, solved: true });
+    }
+
+    #[test]
+    fn path_hint_fades_after_a_while() {
+        let settings = Settings { tutorial_seen: true, ..Default::default() };
+        let options = Options { board_size: 5, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).with_seed(7).build();
+        let (context, mut game) = test_game(settings, puzzle);
+
+        game.hint_path();
+        let tiles = game.path_hint.as_ref().map(|hint| hint.tiles.clone()).unwrap();
+        assert_eq!(tiles.first(), Some(game.puzzle.source()));
+        let drain = game.puzzle.grid()[*tiles.last().unwrap()];
+        assert!(drain.feature() == Feature::Drain && !drain.powered());
+        assert_eq!(game.assistance.path_hints_used, 1);
+        // Nothing is rotated.
+        assert!(game.tile_widgets.iter().all(|sprite| sprite.animation.is_none()));
+
+        run_frame(&context, &mut game, 10.);
+        run_frame(&context, &mut game, 10. + Game::PATH_HINT_DURATION / 2.);
+        assert!(game.path_hint.is_some());
+        run_frame(&context, &mut game, 10. + Game::PATH_HINT_DURATION);
+        assert!(game.path_hint.is_none());
+
+        // Once every drain is powered, there is nothing to show.
+        game.puzzle.solve();
+        game.hint_path();
+        assert!(game.path_hint.is_none());
+        assert_eq!(game.assistance.path_hints_used, 1);
+    }
+
+    #[test]
+    fn score_with_assistance() {
+        let options = Options {
+            board_size: 10,
+            difficulty: Difficulty::Easy,
+            wrapping: true,
+            ..Default::default()
+        };
+        let puzzle = Builder::new().with_options(options).build();
+        let duration = std::time::Duration::from_secs(1);
+
+        let assistance = |hints_used, mistakes_shown| {
+            Assistance { hints_used, mistakes_shown, ..Default::default() }
+        };
+
+        let score = calc_score(&puzzle, duration, Assistance::default());
+        assert!(score > 0);
+        assert!(calc_score(&puzzle, duration, assistance(1, false)) < score);
+        assert_eq!(calc_score(&puzzle, duration, assistance(puzzle.expected_moves(), false)), 0);
+        assert!(calc_score(&puzzle, duration, assistance(0, true)) < score);
+        // Two path hints cost as much as one hint.
+        let path_hints = Assistance { path_hints_used: 2, ..Default::default() };
+        assert_eq!(calc_score(&puzzle, duration, path_hints), calc_score(&puzzle, duration, assistance(1, false)));
+        let auto_solved = Assistance { auto_solved: true, ..Default::default() };
+        assert_eq!(calc_score(&puzzle, duration, auto_solved), 0);
+    }
+
+    #[test]
+    fn score_with_maximum_number_of_walls() {
+        let options = Options {
+            board_size: 3,
+            difficulty: Difficulty::Easy,
+            wrapping: false,
+            ..Default::default()
+        };
+        let mut puzzle = Builder::new().with_options(options).build();
+        let walls = puzzle.grid().indices_iter()
+            .flat_map(|index| {
+                [Wall::new(index, Alignment::Horizontal), Wall::new(index, Alignment::Vertical)]
+            })
+            .collect();
+        puzzle.set_walls(walls);
+
+        assert_eq!(calc_score(&puzzle, std::time::Duration::from_secs(10), Assistance::default()), 0);
+        assert_eq!(calc_score(&puzzle, std::time::Duration::ZERO, Assistance::default()), 0);
     }
 }
@@ -6,7 +6,7 @@ pub use crate::vec2::Vec2;
 
 /// A grid of tiles.
 /// The index of the top-left tile is (0, 0) and the tiles stored in row-major order.
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Grid<T> {
     rows: usize,
     cols: usize,
@@ -27,6 +27,18 @@ impl<T> Grid<T> {
         Grid { rows, cols, data }
     }
 
+    /// Create a grid whose elements are the results of `f` called with their coordinates.
+    pub fn from_fn(rows: usize, cols: usize, f: impl FnMut(Vec2) -> T) -> Grid<T> {
+        let indices_iter = IndicesIter { index: Vec2::default(), rows, cols };
+        Grid { rows, cols, data: indices_iter.map(f).collect() }
+    }
+
+    /// Return a grid of the same size whose elements are the results of `f` called with the
+    /// elements of this grid.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid { rows: self.rows, cols: self.cols, data: self.data.iter().map(f).collect() }
+    }
+
     pub fn rows(&self) -> usize {
         self.rows
     }
@@ -79,6 +91,13 @@ impl<T> Grid<T> {
         }
     }
 
+    /// Return the element at `coord`, which wraps around the edges of the grid like in
+    /// `wrapping_get`.
+    pub fn wrapping_get_mut(&mut self, coord: Vec2) -> &mut T {
+        let index = self.linear_index(self.normalized_coord(coord));
+        &mut self.data[index]
+    }
+
     pub fn indices_iter(&self) -> IndicesIter {
         IndicesIter {
             index: Vec2::default(),
@@ -87,6 +106,15 @@ impl<T> Grid<T> {
         }
     }
 
+    /// Return the coordinates of the rectangle with the corners `min` and `max`, both included,
+    /// in row-major order. The rectangle is clamped to the grid; it is empty if `min` lies right
+    /// of or below `max`.
+    pub fn coords_in_rect(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Vec2> + use<T> {
+        let (min_x, max_x) = (min.x.max(0), max.x.min(self.cols as i32 - 1));
+        let (min_y, max_y) = (min.y.max(0), max.y.min(self.rows as i32 - 1));
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| Vec2::new(x, y)))
+    }
+
     pub fn iter(&self) -> Iter<'_, T> {
         Iter {
             iter: self.data.iter(),
@@ -122,6 +150,27 @@ impl<T> Grid<T> {
         }
     }
 
+    /// Read-only indexed iterator over all four neighbors, wrapping around the edges of the grid.
+    /// The coordinates are normalized, see `normalized_coord`.
+    pub fn wrapping_neighbors(&self, coord: Vec2) -> WrappingNeighborsIter<'_, T> {
+        WrappingNeighborsIter {
+            grid: self,
+            center: coord,
+            direction: Direction::iter(),
+        }
+    }
+
+    /// Lay out the grid as text, one line per row and one character per element, given by `f`.
+    /// Useful for debugging, e.g. `println!("{}", grid.debug_string(|&b| if b { '#' } else { '.' }))`.
+    pub fn debug_string<F: Fn(&T) -> char>(&self, f: F) -> String {
+        let mut text = String::with_capacity(self.rows * (self.cols + 1));
+        for row in self.data.chunks(self.cols.max(1)) {
+            text.extend(row.iter().map(&f));
+            text.push('\n');
+        }
+        text
+    }
+
     /// Panics if `coord` is not on the  grid, i.e. self.contains_coord(coord) returns false.
     #[doc(hidden)]
     fn linear_index(&self, coord: Vec2) -> usize {
@@ -132,6 +181,81 @@ impl<T> Grid<T> {
     }
 }
 
+impl<T: Clone> Grid<T> {
+    /// Set every element to `value`, keeping the size of the grid.
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+
+    /// Change the size of the grid to `rows` × `cols` and set every element to `value`. The
+    /// allocation is reused where possible.
+    pub fn clear_to(&mut self, rows: usize, cols: usize, value: T) {
+        self.data.clear();
+        self.data.resize(rows * cols, value);
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    /// Return a copy of the block with the top left corner `min`, `size.x` columns wide and
+    /// `size.y` rows high, or `None` if the block does not lie within the grid entirely.
+    pub fn sub_grid(&self, min: Vec2, size: Vec2) -> Option<Grid<T>> {
+        let max = min + size;
+        if min.x < 0 || min.y < 0 || size.x < 0 || size.y < 0
+            || max.x > self.cols as i32 || max.y > self.rows as i32
+        {
+            return None;
+        }
+        Some(Grid::from_fn(size.y as usize, size.x as usize, |coord| self[min + coord].clone()))
+    }
+
+    /// Return the non-overlapping blocks of `size` together with their top left corners, in
+    /// row-major order, see `sub_grid`. Where the size of the grid is not a multiple of `size`,
+    /// the incomplete blocks along the right and the bottom edge are left out.
+    ///
+    /// # Panics
+    /// This function panics if `size` is zero or negative in either dimension.
+    pub fn blocks(&self, size: Vec2) -> impl Iterator<Item = (Vec2, Grid<T>)> + '_ {
+        assert!(size.x > 0 && size.y > 0, "block size {size:?} must be positive");
+        let block_rows = self.rows as i32 / size.y;
+        let block_cols = self.cols as i32 / size.x;
+        (0..block_rows).flat_map(move |row| (0..block_cols).map(move |col| {
+            let min = Vec2::new(col * size.x, row * size.y);
+            (min, self.sub_grid(min, size).expect("the block lies within the grid"))
+        }))
+    }
+
+    /// Return the grid mirrored along its main diagonal: the element at (x, y) moves to (y, x).
+    pub fn transpose(&self) -> Grid<T> {
+        Grid::from_fn(self.cols, self.rows, |coord| self[Vec2::new(coord.y, coord.x)].clone())
+    }
+
+    /// Return the grid rotated clockwise by a quarter turn: the element at (x, y) moves to
+    /// (rows - 1 - y, x).
+    pub fn rotate_cw(&self) -> Grid<T> {
+        let last_row = self.rows as i32 - 1;
+        Grid::from_fn(self.cols, self.rows, |coord| self[Vec2::new(coord.y, last_row - coord.x)].clone())
+    }
+
+    /// Return the grid rotated counter-clockwise by a quarter turn: the element at (x, y) moves
+    /// to (y, cols - 1 - x).
+    pub fn rotate_ccw(&self) -> Grid<T> {
+        let last_col = self.cols as i32 - 1;
+        Grid::from_fn(self.cols, self.rows, |coord| self[Vec2::new(last_col - coord.y, coord.x)].clone())
+    }
+
+    /// Return the grid mirrored left to right: the element at (x, y) moves to (cols - 1 - x, y).
+    pub fn mirror_horizontal(&self) -> Grid<T> {
+        let last_col = self.cols as i32 - 1;
+        Grid::from_fn(self.rows, self.cols, |coord| self[Vec2::new(last_col - coord.x, coord.y)].clone())
+    }
+
+    /// Return the grid mirrored top to bottom: the element at (x, y) moves to (x, rows - 1 - y).
+    pub fn mirror_vertical(&self) -> Grid<T> {
+        let last_row = self.rows as i32 - 1;
+        Grid::from_fn(self.rows, self.cols, |coord| self[Vec2::new(coord.x, last_row - coord.y)].clone())
+    }
+}
+
 impl<T> std::ops::Index<Vec2> for Grid<T> {
     type Output = T;
 
@@ -258,10 +382,35 @@ impl<'a, T> Iterator for NeighborsIter<'a, T> {
     }
 }
 
+pub struct WrappingNeighborsIter<'a, T> {
+    grid: &'a Grid<T>,
+    center: Vec2,
+    direction: DirectionIter,
+}
+
+impl<'a, T> Iterator for WrappingNeighborsIter<'a, T> {
+    type Item = (Vec2, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let direction = self.direction.next()?;
+        let n_pos = self.grid.normalized_coord(self.center + direction.to_vec2());
+        Some((n_pos, self.grid.wrapping_get(n_pos)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn equality() {
+        let grid = Grid::from_data(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(grid, Grid::from_fn(2, 3, |coord| coord.y * 3 + coord.x + 1));
+        assert_ne!(grid, Grid::from_data(2, 3, vec![1, 2, 3, 4, 5, 7]));
+        // The same data in another shape is a different grid.
+        assert_ne!(grid, Grid::from_data(3, 2, vec![1, 2, 3, 4, 5, 6]));
+    }
+
     #[test]
     fn general_functionality() {
         let grid = Grid::from_data(2, 2, vec![1, 2, 3, 4]);
@@ -277,6 +426,115 @@ mod tests {
         assert_eq!(grid.get(Vec2::default()), Some(&4));
     }
 
+    #[test]
+    fn from_fn_and_map() {
+        let grid = Grid::from_fn(2, 3, |index| index.x * 10 + index.y);
+        assert_eq!(grid.rows(), 2);
+        assert_eq!(grid.cols(), 3);
+        for (index, &value) in grid.indexed_iter() {
+            assert_eq!(value, index.x * 10 + index.y);
+        }
+
+        let mapped = grid.map(|value| value.to_string());
+        assert_eq!(mapped.rows(), 2);
+        assert_eq!(mapped.cols(), 3);
+        for (index, value) in mapped.indexed_iter() {
+            assert_eq!(*value, grid[index].to_string());
+        }
+        assert_eq!(mapped[Vec2::new(2, 1)], "21");
+    }
+
+    #[test]
+    fn debug_string() {
+        let grid = Grid::from_data(2, 3, vec![true, false, false, false, true, true]);
+        let text = grid.debug_string(|&value| if value { '#' } else { '.' });
+        assert_eq!(text, "#..\n.##\n");
+
+        let grid = Grid::from_fn(3, 2, |index| index.x + index.y);
+        assert_eq!(grid.debug_string(|&value| char::from_digit(value as u32, 10).unwrap()), "01\n12\n23\n");
+    }
+
+    #[test]
+    fn coords_in_rect() {
+        let grid = Grid::<u8>::with_size(3, 3, 0);
+        let coords = |min: (i32, i32), max: (i32, i32)| {
+            grid.coords_in_rect(min.into(), max.into()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(coords((1, 0), (2, 1)), [(1, 0).into(), (2, 0).into(), (1, 1).into(), (2, 1).into()]);
+        assert_eq!(coords((1, 1), (1, 1)), [Vec2::new(1, 1)]);
+        // Clamped to the grid.
+        assert_eq!(coords((-5, -5), (10, 10)), grid.indices_iter().collect::<Vec<_>>());
+        assert_eq!(coords((2, -1), (4, 0)), [Vec2::new(2, 0)]);
+        // Empty if the corners are swapped or the rectangle is off the grid.
+        assert!(coords((2, 2), (0, 0)).is_empty());
+        assert!(coords((3, 0), (5, 2)).is_empty());
+        assert!(coords((-3, -3), (-1, 2)).is_empty());
+    }
+
+    #[test]
+    fn transpose_and_rotate() {
+        // 1 2 3
+        // 4 5 6
+        let grid = Grid::from_data(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(grid.transpose(), Grid::from_data(3, 2, vec![1, 4, 2, 5, 3, 6]));
+        assert_eq!(grid.rotate_cw(), Grid::from_data(3, 2, vec![4, 1, 5, 2, 6, 3]));
+        assert_eq!(grid.rotate_ccw(), Grid::from_data(3, 2, vec![3, 6, 2, 5, 1, 4]));
+        assert_eq!(grid.transpose().transpose(), grid);
+        assert_eq!(grid.rotate_cw().rotate_ccw(), grid);
+
+        let mut rotated = grid.clone();
+        for _ in 0..4 {
+            rotated = rotated.rotate_cw();
+        }
+        assert_eq!(rotated, grid);
+        assert_eq!(grid.rotate_cw().rotate_cw(), grid.rotate_ccw().rotate_ccw());
+
+        assert_eq!(grid.mirror_horizontal(), Grid::from_data(2, 3, vec![3, 2, 1, 6, 5, 4]));
+        assert_eq!(grid.mirror_vertical(), Grid::from_data(2, 3, vec![4, 5, 6, 1, 2, 3]));
+        assert_eq!(grid.mirror_horizontal().mirror_vertical(), grid.rotate_cw().rotate_cw());
+    }
+
+    #[test]
+    fn fill_and_clear_to() {
+        let mut grid = Grid::from_data(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        grid.fill(7);
+        assert_eq!(grid, Grid::<i32>::with_size(2, 3, 7));
+
+        grid.clear_to(3, 1, 0);
+        assert_eq!(grid, Grid::<i32>::with_size(3, 1, 0));
+        assert_eq!(grid.get(Vec2::new(0, 2)), Some(&0));
+        assert_eq!(grid.get(Vec2::new(1, 0)), None);
+        grid.clear_to(2, 4, 1);
+        assert_eq!(grid.iter().count(), 8);
+        assert!(grid.iter().all(|&value| value == 1));
+    }
+
+    #[test]
+    fn sub_grid_and_blocks() {
+        // 1 2 3
+        // 4 5 6
+        // 7 8 9
+        let grid = Grid::from_data(3, 3, (1..=9).collect());
+        let block = grid.sub_grid(Vec2::new(1, 1), Vec2::new(2, 2));
+        assert_eq!(block, Some(Grid::from_data(2, 2, vec![5, 6, 8, 9])));
+        let block = grid.sub_grid(Vec2::new(0, 0), Vec2::new(2, 2));
+        assert_eq!(block, Some(Grid::from_data(2, 2, vec![1, 2, 4, 5])));
+        let block = grid.sub_grid(Vec2::new(1, 0), Vec2::new(2, 3));
+        assert_eq!(block, Some(Grid::from_data(3, 2, vec![2, 3, 5, 6, 8, 9])));
+        assert_eq!(grid.sub_grid(Vec2::new(0, 0), Vec2::new(3, 3)), Some(grid.clone()));
+        // Out of bounds.
+        assert_eq!(grid.sub_grid(Vec2::new(2, 2), Vec2::new(2, 2)), None);
+        assert_eq!(grid.sub_grid(Vec2::new(-1, 0), Vec2::new(2, 2)), None);
+        assert_eq!(grid.sub_grid(Vec2::new(1, 1), Vec2::new(-1, 1)), None);
+
+        // Only the top left 2×2 block is complete.
+        let blocks = grid.blocks(Vec2::new(2, 2)).collect::<Vec<_>>();
+        assert_eq!(blocks, [(Vec2::new(0, 0), Grid::from_data(2, 2, vec![1, 2, 4, 5]))]);
+        let columns = grid.blocks(Vec2::new(1, 3)).map(|(min, block)| (min.x, block.iter().sum::<i32>()));
+        assert!(columns.eq([(0, 12), (1, 15), (2, 18)]));
+    }
+
     #[test]
     #[should_panic]
     fn grid_from_data_invalid_length() {
@@ -316,6 +574,20 @@ mod tests {
         assert_eq!(grid[(0, 0).into()], 5);
     }
 
+    #[test]
+    fn wrapping_access() {
+        let mut grid = Grid::from_data(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(*grid.wrapping_get(Vec2::new(-1, 0)), 3);
+        assert_eq!(*grid.wrapping_get(Vec2::new(3, 2)), 1);
+
+        *grid.wrapping_get_mut(Vec2::new(-1, -1)) = 7;
+        assert_eq!(grid[Vec2::new(2, 1)], 7);
+        *grid.wrapping_get_mut(Vec2::new(4, 5)) += 10;
+        assert_eq!(grid[Vec2::new(1, 1)], 15);
+        *grid.wrapping_get_mut(Vec2::new(0, 0)) = 0;
+        assert_eq!(grid.iter().copied().collect::<Vec<_>>(), [0, 2, 3, 4, 15, 7]);
+    }
+
     #[test]
     #[should_panic]
     fn index_operator_out_of_bounds() {
@@ -392,4 +664,24 @@ mod tests {
         assert_eq!(it.next(), Some((Vec2::new(0, 1), &2))); // down
         assert_eq!(it.next(), None);
     }
+
+    #[test]
+    fn wrapping_neighbors_iterator() {
+        let grid = Grid::from_data(2, 2, vec![0, 1, 2, 3]);
+        let mut it = grid.wrapping_neighbors(Vec2 { x: 0, y: 0 });
+        // Note this depends on the iteration order of Direction.
+        assert_eq!(it.next(), Some((Vec2::new(1, 0), &1))); // right
+        assert_eq!(it.next(), Some((Vec2::new(0, 1), &2))); // up, wrapped
+        assert_eq!(it.next(), Some((Vec2::new(1, 0), &1))); // left, wrapped
+        assert_eq!(it.next(), Some((Vec2::new(0, 1), &2))); // down
+        assert_eq!(it.next(), None);
+
+        let neighbors = grid.wrapping_neighbors(Vec2 { x: 1, y: 1 }).collect::<Vec<_>>();
+        assert_eq!(neighbors, [
+            (Vec2::new(0, 1), &2),
+            (Vec2::new(1, 0), &1),
+            (Vec2::new(0, 1), &2),
+            (Vec2::new(1, 0), &1),
+        ]);
+    }
 }
@@ -0,0 +1,110 @@
+//! The best results achieved for each set of puzzle options.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use eframe::Storage;
+
+use crate::puzzle::{Difficulty, Options};
+
+/// The best time and the best score for each combination of board size, difficulty and wrapping.
+///
+/// Best time and best score are tracked independently, i.e. they may stem from different games.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct HighScores {
+    records: HashMap<Key, Record>,
+}
+
+impl HighScores {
+    const STORAGE_KEY: &'static str = "high_scores";
+
+    /// Read the high scores from `storage`. Missing or unreadable high scores start empty.
+    pub fn read(storage: &dyn Storage) -> Self {
+        eframe::get_value(storage, Self::STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn write(&self, storage: &mut dyn Storage) {
+        eframe::set_value(storage, Self::STORAGE_KEY, self);
+    }
+
+    /// Return the best results for puzzles created with `options`, if any puzzle was solved yet.
+    pub fn get(&self, options: &Options) -> Option<Record> {
+        self.records.get(&Key::from(options)).copied()
+    }
+
+    /// Record the result of a solved puzzle. Return true if either the time or the score is a
+    /// new best.
+    pub fn record(&mut self, options: &Options, time: Duration, score: u32) -> bool {
+        match self.records.get_mut(&Key::from(options)) {
+            Some(record) => {
+                let improved = record.is_beaten_by(time, score);
+                record.time = record.time.min(time);
+                record.score = record.score.max(score);
+                improved
+            }
+            None => {
+                self.records.insert(Key::from(options), Record { time, score });
+                true
+            }
+        }
+    }
+}
+
+/// The best time and the best score for one set of options.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Record {
+    pub time: Duration,
+    pub score: u32,
+}
+
+impl Record {
+    /// Return true if a game solved in `time` with `score` is better in at least one respect.
+    pub fn is_beaten_by(&self, time: Duration, score: u32) -> bool {
+        time < self.time || score > self.score
+    }
+}
+
+/// The options which make results comparable.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+struct Key {
+    board_size: u8,
+    difficulty: Difficulty,
+    wrapping: bool,
+}
+
+impl From<&Options> for Key {
+    fn from(options: &Options) -> Self {
+        Key {
+            board_size: options.board_size,
+            difficulty: options.difficulty,
+            wrapping: options.wrapping,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keeps_best_time_and_score() {
+        let options = Options {
+            board_size: 5,
+            difficulty: Difficulty::Medium,
+            wrapping: false,
+            ..Default::default()
+        };
+        let other = Options { wrapping: true, ..options };
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.get(&options), None);
+
+        assert!(high_scores.record(&options, Duration::from_secs(60), 100));
+        assert!(!high_scores.record(&options, Duration::from_secs(70), 90));
+        assert!(high_scores.record(&options, Duration::from_secs(50), 80));
+        assert_eq!(
+            high_scores.get(&options),
+            Some(Record { time: Duration::from_secs(50), score: 100 })
+        );
+        assert_eq!(high_scores.get(&other), None);
+    }
+}
@@ -2,9 +2,36 @@
 //!
 //! Start reading the documentation by having a look at the module [`puzzle`].
 //!
+//! The game is built with the default feature `gui`. Without it, the crate only contains the
+//! generation and solving of puzzles and does not depend on egui:
+//!
+//! ```
+//! use netwalk::puzzle::{Builder, Options};
+//!
+//! let options = Options {
+//!     board_size: 5,
+//!     ..Default::default()
+//! };
+//! let mut puzzle = Builder::new().with_options(options).build();
+//! assert!(!puzzle.solved());
+//! assert!(puzzle.count_solutions(1) == Some(1));
+//!
+//! puzzle.solve();
+//! assert!(puzzle.solved());
+//! println!("{puzzle}");
+//! ```
 
+#[cfg(feature = "gui")]
 pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "gui")]
+pub mod editor;
+#[cfg(feature = "gui")]
 pub mod game;
+#[cfg(feature = "gui")]
+pub mod high_scores;
+#[cfg(feature = "gui")]
 pub mod modals;
 pub mod puzzle;
 
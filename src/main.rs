@@ -1,19 +1,31 @@
 use eframe::{egui, Storage};
 
 use netwalk::assets::Assets;
+use netwalk::editor::{Editor, EditorEvent};
+#[cfg(feature = "audio")]
+use netwalk::audio::Audio;
 use netwalk::game::{Game, GameEvent, Settings};
-use netwalk::modals::{NewGameModal, NewGameModalEvent};
+use netwalk::high_scores::HighScores;
+use netwalk::modals::{
+    ErrorModal, ErrorModalEvent, NewGameModal, NewGameModalEvent, ResumeGameModal,
+    ResumeGameModalEvent,
+};
 use netwalk::puzzle::{self, Options};
 
 
 fn main() -> eframe::Result {
+    // The powered source tile doubles as the window icon.
+    let icon = eframe::icon_data::from_png_bytes(include_bytes!("../assets/40/source_powered.png"))
+        .expect("the embedded icon is a valid PNG");
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_min_inner_size([320.0, 240.0]),
+        viewport: egui::ViewportBuilder::default()
+            .with_min_inner_size([320.0, 240.0])
+            .with_icon(icon),
         ..Default::default()
     };
 
     eframe::run_native(
-        "Netwalk",
+        Application::TITLE,
         native_options,
         Box::new(|cc| Ok(Box::new(Application::new(cc)))),
     )
@@ -22,16 +34,26 @@ fn main() -> eframe::Result {
 struct Application {
     assets: Assets,
     settings: Settings,
+    high_scores: HighScores,
     state: ApplicationState,
     new_game_modal: NewGameModal,
+    title: String, // the window title, updated when the state changes
+    #[cfg(feature = "audio")]
+    audio: Option<std::rc::Rc<Audio>>,
 }
 
 impl Application {
+    const OPTIONS_STORAGE_KEY: &'static str = "new_game_options";
+    const TITLE: &'static str = "Netwalk";
+
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         cc.egui_ctx.set_theme(egui::Theme::Dark);
-        let settings = cc.storage
+        let settings: Settings = cc.storage
             .map(|storage| {Settings::read(storage)})
             .unwrap_or_default();
+        let high_scores = cc.storage
+            .map(HighScores::read)
+            .unwrap_or_default();
 
         // Increasing the pixel per point results in a larger font, but also larger game objects.
         // cc.egui_ctx.set_pixels_per_point(1.25);
@@ -45,58 +67,203 @@ impl Application {
         cc.egui_ctx.set_fonts(fonts);
 
         let mut assets = Assets::new();
-        assets.load_all(&cc.egui_ctx);
+        // Start with the options of the last game, e.g. to stay in practice mode.
+        let options = cc.storage
+            .and_then(|storage| eframe::get_value(storage, Self::OPTIONS_STORAGE_KEY))
+            .unwrap_or_default();
+        let mut new_game_modal = NewGameModal::new(options)
+            .with_tileset_dir(settings.tileset_dir());
+        let result = assets.load_all(&cc.egui_ctx);
+        if result.is_ok() && let Some(dir) = settings.tileset_dir() {
+            // A broken custom tileset falls back to the embedded one and shows the error.
+            let mut custom_assets = assets.clone();
+            match custom_assets.load_from_dir(dir, &cc.egui_ctx) {
+                Ok(()) => assets = custom_assets,
+                Err(error) => new_game_modal.set_tileset_error(Some(error.to_string())),
+            }
+        }
+        // The game is playable without sound, e.g. on a machine without audio output.
+        #[cfg(feature = "audio")]
+        let audio = Audio::new()
+            .inspect_err(|error| eprintln!("Sound effects are disabled: {error}"))
+            .ok()
+            .map(std::rc::Rc::new);
+
+        let state = if let Err(error) = result {
+            ApplicationState::ShowingError(error.to_string())
+        } else {
+            cc.storage
+                .and_then(|storage| Game::restore(storage, assets.clone()))
+                .map(|game| {
+                    let previous_best = high_scores.get(game.options());
+                    let game = game.with_previous_best(previous_best);
+                    #[cfg(feature = "audio")]
+                    let game = game.with_audio(audio.clone());
+                    ApplicationState::ShowingResumeGameModal(Box::new(game))
+                })
+                .unwrap_or(ApplicationState::ShowingNewGameModal)
+        };
 
         Application {
             assets,
             settings,
-            state: ApplicationState::ShowingNewGameModal,
-            new_game_modal: NewGameModal::new(Options::default()),
+            high_scores,
+            state,
+            new_game_modal,
+            title: Self::TITLE.to_string(),
+            #[cfg(feature = "audio")]
+            audio,
         }
     }
 
     fn start_new_game(&mut self, options: Options) {
         let puzzle = puzzle::Builder::new().with_options(options).build();
-        let game = Game::new(puzzle, self.assets.clone(), self.settings);
+        self.start_puzzle(puzzle);
+    }
+
+    fn start_puzzle(&mut self, puzzle: puzzle::Puzzle) {
+        let previous_best = self.high_scores.get(puzzle.options());
+        let game = Game::new(puzzle, self.assets.clone(), self.settings.clone())
+            .with_previous_best(previous_best);
+        #[cfg(feature = "audio")]
+        let game = game.with_audio(self.audio.clone());
         self.state = ApplicationState::RunningGame(Box::new(game));
     }
+
+    /// Load a custom tileset from `dir`, or the embedded default tileset if `dir` is `None`.
+    fn load_tileset(&mut self, dir: Option<std::path::PathBuf>, ctx: &egui::Context) {
+        let mut assets = Assets::new();
+        let result = match &dir {
+            Some(dir) => assets.load_from_dir(dir, ctx),
+            None => assets.load_all(ctx),
+        };
+        match result {
+            Ok(()) => {
+                self.assets = assets;
+                self.settings.set_tileset_dir(dir);
+                self.new_game_modal.set_tileset_error(None);
+            }
+            Err(error) => self.new_game_modal.set_tileset_error(Some(error.to_string())),
+        }
+    }
+
+    /// Return the window title for the current state. While a game is running, it includes the
+    /// board size and the difficulty, so several windows can be told apart.
+    fn window_title(&self) -> String {
+        match &self.state {
+            ApplicationState::RunningGame(game) => {
+                let options = game.options();
+                let size = options.board_size;
+                format!("{} — {size}×{size} {}", Self::TITLE, options.difficulty)
+            }
+            _ => Self::TITLE.to_string(),
+        }
+    }
+
+    fn resume_game(&mut self) {
+        let state = std::mem::replace(&mut self.state, ApplicationState::ShowingNewGameModal);
+        if let ApplicationState::ShowingResumeGameModal(game) = state {
+            self.state = ApplicationState::RunningGame(game);
+        }
+    }
 }
 
 impl eframe::App for Application {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| {
             match &mut self.state {
+                ApplicationState::ShowingError(message) => {
+                    if let Some(ErrorModalEvent::Quit) = ErrorModal::new(message.clone()).update(ui) {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                }
+                ApplicationState::ShowingResumeGameModal(_) => {
+                    match ResumeGameModal::new().update(ui) {
+                        Some(ResumeGameModalEvent::Resume) => self.resume_game(),
+                        Some(ResumeGameModalEvent::NewGame) => {
+                            self.state = ApplicationState::ShowingNewGameModal;
+                        }
+                        None => (),
+                    }
+                }
                 ApplicationState::ShowingNewGameModal => {
-                    if let Some(NewGameModalEvent::StartNewGame(options)) =
-                        self.new_game_modal.update(ui)
-                    {
-                        self.start_new_game(options);
+                    match self.new_game_modal.update(ui) {
+                        Some(NewGameModalEvent::StartDaily(options)) => {
+                            let today = chrono::Local::now().date_naive();
+                            self.start_puzzle(puzzle::Builder::daily(today, options).build());
+                        }
+                        Some(NewGameModalEvent::StartNewGame(options)) => {
+                            self.start_new_game(options);
+                        }
+                        Some(NewGameModalEvent::StartPuzzle(puzzle)) => self.start_puzzle(puzzle),
+                        Some(NewGameModalEvent::OpenEditor(options)) => {
+                            let editor = Editor::new(options, self.assets.clone(), self.settings.clone());
+                            self.state = ApplicationState::Editing(Box::new(editor));
+                        }
+                        Some(NewGameModalEvent::LoadTileset(dir)) => self.load_tileset(dir, ctx),
+                        None => (),
+                    }
+                }
+                ApplicationState::Editing(editor) => {
+                    for event in editor.update(ui) {
+                        match event {
+                            EditorEvent::Play(puzzle) => self.start_puzzle(*puzzle),
+                            EditorEvent::Close => self.state = ApplicationState::ShowingNewGameModal,
+                        }
                     }
                 }
                 ApplicationState::RunningGame(game) => {
+                    let options = *game.options();
                     for event in game.update(ui) {
                         match event {
                             GameEvent::Close => self.state = ApplicationState::ShowingNewGameModal,
                             GameEvent::NewGame => {
                                 self.state = ApplicationState::ShowingNewGameModal
                             }
+                            GameEvent::NewGameWithOptions(options) => self.start_new_game(options),
                             GameEvent::SettingsChanged(settings) => {
                                 self.settings = settings;
                             }
+                            GameEvent::Solved { assisted: true, .. } => (),
+                            GameEvent::Solved { .. } if options.practice => (),
+                            GameEvent::Solved { time, score, .. } => {
+                                self.high_scores.record(&options, time, score);
+                                if let Some(storage) = frame.storage_mut() {
+                                    self.high_scores.write(storage);
+                                }
+                            }
                             _ => (),
                         }
                     }
                 }
             };
         });
+
+        let title = self.window_title();
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
     }
 
     fn save(&mut self, storage: &mut dyn Storage) {
         self.settings.write(storage);
+        self.high_scores.write(storage);
+        eframe::set_value(storage, Self::OPTIONS_STORAGE_KEY, self.new_game_modal.options());
+        match &self.state {
+            ApplicationState::RunningGame(game) | ApplicationState::ShowingResumeGameModal(game) => {
+                game.save(storage)
+            }
+            ApplicationState::ShowingNewGameModal => Game::clear_saved(storage),
+            ApplicationState::ShowingError(_) | ApplicationState::Editing(_) => (),
+        }
     }
 }
 
 enum ApplicationState {
+    ShowingError(String),
+    ShowingResumeGameModal(Box<Game>),
     ShowingNewGameModal,
     RunningGame(Box<Game>),
+    Editing(Box<Editor>),
 }
@@ -1,19 +1,52 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use eframe::egui;
+use strum::IntoEnumIterator;
 
-use crate::puzzle::{Difficulty, Options};
+use crate::game::{ColorScheme, Settings};
+use crate::high_scores::Record;
+use crate::puzzle::{Builder, Difficulty, Kind, Options, Puzzle, WallMode};
 
 pub struct NewGameModal {
     options: Options,
+    code: String,
+    code_error: Option<String>,
+    tileset_dir: String,
+    tileset_error: Option<String>,
 }
 
 impl NewGameModal {
     pub fn new(options: Options) -> Self {
-        Self { options }
+        Self {
+            options,
+            code: String::new(),
+            code_error: None,
+            tileset_dir: String::new(),
+            tileset_error: None,
+        }
+    }
+
+    /// Return the options currently selected in the modal.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Set the directory of the custom tileset shown in the modal.
+    pub fn with_tileset_dir(mut self, dir: Option<&Path>) -> Self {
+        self.tileset_dir = dir.map(|dir| dir.to_string_lossy().into_owned()).unwrap_or_default();
+        self
+    }
+
+    /// Show an error after the tileset could not be loaded, or clear it with `None`.
+    pub fn set_tileset_error(&mut self, error: Option<String>) {
+        self.tileset_error = error;
     }
 
     pub fn update(&mut self, ui: &mut egui::Ui) -> Option<NewGameModalEvent> {
         egui::Modal::new(egui::Id::new("Modal New Game"))
             .show(ui.ctx(), |ui| {
+                let mut event = None;
                 ui.set_width(300.0);
                 ui.vertical_centered(|ui| {
                     ui.heading("New Game");
@@ -24,46 +57,221 @@ impl NewGameModal {
                         .spacing([20.0, 20.0])
                         .show(ui, |ui| {
                             ui.label("Size");
-                            ui.add(egui::Slider::new(&mut self.options.board_size, 3..=20));
+                            let sizes = Builder::MIN_BOARD_SIZE..=Builder::MAX_BOARD_SIZE;
+                            ui.add(egui::Slider::new(&mut self.options.board_size, sizes));
                             ui.end_row();
 
                             ui.label("Difficulty");
-                            egui::ComboBox::from_id_salt("Difficulty")
-                                .selected_text(self.options.difficulty.to_string())
+                            ui.vertical(|ui| {
+                                egui::ComboBox::from_id_salt("Difficulty")
+                                    .selected_text(self.options.difficulty.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for difficulty in Difficulty::iter() {
+                                            ui.selectable_value(
+                                                &mut self.options.difficulty,
+                                                difficulty,
+                                                difficulty.to_string(),
+                                            );
+                                        }
+                                    });
+                                // Only a hint, the player is free to ignore it.
+                                let suggested = suggested_difficulty(self.options.board_size);
+                                let difficulty = self.options.difficulty;
+                                if difficulty != suggested && difficulty != Difficulty::Custom {
+                                    let hint = format!("Suggested for this size: {suggested}");
+                                    ui.label(egui::RichText::new(hint).small().weak());
+                                }
+                            });
+                            ui.end_row();
+
+                            if self.options.difficulty == Difficulty::Custom {
+                                for kind in Kind::iter() {
+                                    ui.label(kind_label(kind));
+                                    ui.add(egui::Slider::new(
+                                        self.options.custom_weights.get_mut(kind), 0..=10));
+                                    ui.end_row();
+                                }
+                            }
+
+                            ui.label("Walls");
+                            egui::ComboBox::from_id_salt("Walls")
+                                .selected_text(self.options.walls.to_string())
                                 .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.options.difficulty,
-                                        Difficulty::Easy,
-                                        Difficulty::Easy.to_string(),
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.options.difficulty,
-                                        Difficulty::Medium,
-                                        Difficulty::Medium.to_string(),
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.options.difficulty,
-                                        Difficulty::Hard,
-                                        Difficulty::Hard.to_string(),
-                                    );
+                                    for walls in WallMode::iter() {
+                                        ui.selectable_value(&mut self.options.walls, walls, walls.to_string());
+                                    }
                                 });
                             ui.end_row();
 
                             ui.label("No boundary");
                             ui.add(egui::Checkbox::without_text(&mut self.options.wrapping));
                             ui.end_row();
+
+                            ui.label("Unique solution");
+                            ui.add(egui::Checkbox::without_text(&mut self.options.unique_solution));
+                            ui.end_row();
+
+                            ui.label("Practice")
+                                .on_hover_text("Play without timer, move counter and score");
+                            ui.add(egui::Checkbox::without_text(&mut self.options.practice));
+                            ui.end_row();
+
+                            ui.label("Time limit");
+                            ui.add_enabled_ui(!self.options.practice, |ui| {
+                                ui.horizontal(|ui| {
+                                    let mut limited = self.options.time_limit.is_some();
+                                    ui.add(egui::Checkbox::without_text(&mut limited));
+                                    let mut minutes = self.options.time_limit
+                                        .map_or(5, |limit| limit.as_secs().div_ceil(60).max(1));
+                                    ui.add_enabled(limited, egui::Slider::new(&mut minutes, 1..=30)
+                                        .suffix(" min"));
+                                    self.options.time_limit =
+                                        limited.then(|| Duration::from_secs(minutes * 60));
+                                });
+                            });
+                            ui.end_row();
+
+                            ui.label("Code");
+                            ui.add(egui::TextEdit::singleline(&mut self.code)
+                                .hint_text("Paste a puzzle code"));
+                            ui.end_row();
+
+                            ui.label("Tileset");
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut self.tileset_dir)
+                                    .hint_text("Default")
+                                    .desired_width(120.0));
+                                if ui.button("Load").clicked() {
+                                    let dir = self.tileset_dir.trim();
+                                    let dir = (!dir.is_empty()).then(|| PathBuf::from(dir));
+                                    event = Some(NewGameModalEvent::LoadTileset(dir));
+                                }
+                            });
+                            ui.end_row();
                         });
+                    for error in [&self.code_error, &self.tileset_error].into_iter().flatten() {
+                        ui.add_space(10.0);
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
                 });
 
                 ui.add_space(20.0);
 
-                ui.with_layout(egui::Layout::right_to_left(Default::default()), |ui| {
+                let start_event = ui.with_layout(egui::Layout::right_to_left(Default::default()), |ui| {
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Editor"))
+                        .on_hover_text("Design a puzzle of this size by hand")
+                        .clicked()
+                    {
+                        return Some(NewGameModalEvent::OpenEditor(self.options));
+                    }
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Daily"))
+                        .on_hover_text("Start today's puzzle, which is the same for everyone with these options")
+                        .clicked()
+                    {
+                        return Some(NewGameModalEvent::StartDaily(self.options));
+                    }
                     if ui
                         .add_sized([80., 30.], egui::Button::new("Start"))
                         .clicked()
                     {
-                        // Close the modal dialog and start a new game with the given options
-                        Some(NewGameModalEvent::StartNewGame(self.options))
+                        if self.code.trim().is_empty() {
+                            // Close the modal dialog and start a new game with the given options
+                            return Some(NewGameModalEvent::StartNewGame(self.options));
+                        }
+                        match Puzzle::from_code(&self.code) {
+                            Ok(puzzle) => {
+                                self.code.clear();
+                                self.code_error = None;
+                                return Some(NewGameModalEvent::StartPuzzle(puzzle));
+                            }
+                            Err(error) => self.code_error = Some(error.to_string()),
+                        }
+                    }
+                    None
+                })
+                .inner;
+                start_event.or(event)
+            })
+            .inner
+    }
+}
+
+/// Return the difficulty which suits a board of `board_size`: small boards are quickly solved
+/// even on the hardest difficulty, large boards are tedious on the easiest.
+fn suggested_difficulty(board_size: u8) -> Difficulty {
+    match board_size {
+        ..=5 => Difficulty::Easy,
+        6..=8 => Difficulty::Medium,
+        9..=12 => Difficulty::Hard,
+        _ => Difficulty::Expert,
+    }
+}
+
+/// The grade of a solve, comparing its time to the par time of the puzzle.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::Display)]
+enum Grade {
+    A, // within par
+    B, // within twice the par
+    C,
+}
+
+impl Grade {
+    fn new(time: Duration, par_time: Duration) -> Self {
+        if time <= par_time {
+            Grade::A
+        } else if time <= 2 * par_time {
+            Grade::B
+        } else {
+            Grade::C
+        }
+    }
+}
+
+/// Return the label of the weight of a kind of tile in the new game modal.
+fn kind_label(kind: Kind) -> &'static str {
+    match kind {
+        Kind::DeadEnd => "Dead ends",
+        Kind::Straight => "Straights",
+        Kind::Corner => "Corners",
+        Kind::TIntersection => "T-pieces",
+        Kind::CrossIntersection => "Crosses",
+    }
+}
+
+pub enum NewGameModalEvent {
+    StartNewGame(Options),
+    StartDaily(Options), // the daily puzzle of today, see `Builder::daily`
+    StartPuzzle(Puzzle),
+    OpenEditor(Options),
+    LoadTileset(Option<PathBuf>), // None for the embedded default tileset
+}
+
+pub struct ErrorModal {
+    message: String,
+}
+
+impl ErrorModal {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<ErrorModalEvent> {
+        egui::Modal::new(egui::Id::new("Error"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(300.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("Error");
+                    ui.separator();
+                    ui.add_space(15.0);
+                    ui.label(&self.message);
+                    ui.add_space(15.0);
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Quit"))
+                        .clicked()
+                    {
+                        Some(ErrorModalEvent::Quit)
                     } else {
                         None
                     }
@@ -74,19 +282,106 @@ impl NewGameModal {
     }
 }
 
-pub enum NewGameModalEvent {
-    StartNewGame(Options),
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ErrorModalEvent {
+    Quit,
 }
 
 #[derive(Default)]
-pub struct PauseModal {}
+pub struct ResumeGameModal {}
 
-impl PauseModal {
+impl ResumeGameModal {
     pub fn new() -> Self {
         Self {}
     }
 
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<ResumeGameModalEvent> {
+        egui::Modal::new(egui::Id::new("Resume Game"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(200.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("Resume Game?");
+                    ui.separator();
+                    ui.add_space(15.0);
+                    ui.label("A game from your last session is still in progress.");
+                    ui.add_space(15.0);
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Resume"))
+                        .clicked()
+                    {
+                        return Some(ResumeGameModalEvent::Resume);
+                    }
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("New Game"))
+                        .clicked()
+                    {
+                        return Some(ResumeGameModalEvent::NewGame);
+                    }
+                    ui.add_space(15.0);
+                    None
+                })
+                .inner
+            })
+            .inner
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ResumeGameModalEvent {
+    Resume,
+    NewGame,
+}
+
+pub struct PauseModal {
+    time: Duration,
+    moves: u32,
+    expected_moves: u32,
+    powered_fraction: f32,
+    export_power: bool,
+    message: Option<String>,
+    description: Option<String>,
+}
+
+impl PauseModal {
+    pub fn new(time: Duration, moves: u32, expected_moves: u32, powered_fraction: f32) -> Self {
+        Self {
+            time,
+            moves,
+            expected_moves,
+            powered_fraction,
+            export_power: true,
+            message: None,
+            description: None,
+        }
+    }
+
+    /// Describe the origin of the puzzle, e.g. the date of a daily puzzle.
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Set whether a saved image of the game board shows which tiles are powered.
+    pub fn with_export_power(mut self, export_power: bool) -> Self {
+        self.export_power = export_power;
+        self
+    }
+
+    /// Show a message, e.g. the result of saving an image.
+    pub fn with_message(mut self, message: Option<&str>) -> Self {
+        self.message = message.map(str::to_string);
+        self
+    }
+
     pub fn update(&mut self, ui: &mut egui::Ui) -> Option<PauseModalEvent> {
+        // The keys which pause the game also continue it.
+        if ui.input_mut(|i| {
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)
+                || i.consume_key(egui::Modifiers::NONE, egui::Key::P)
+        }) {
+            return Some(PauseModalEvent::Continue);
+        }
+
         egui::Modal::new(egui::Id::new("Game Paused"))
             .show(ui.ctx(), |ui| {
                 ui.set_width(200.0);
@@ -94,6 +389,16 @@ impl PauseModal {
                     ui.heading("Game Paused");
                     ui.separator();
                     ui.add_space(15.0);
+                    ui.vertical_centered(|ui| {
+                        ui.style_mut().spacing.item_spacing.y = 10.0;
+                        ui.label(format!("Time {}", format_duration(self.time)));
+                        ui.label(format!("Moves {}/{}", self.moves, self.expected_moves));
+                        ui.label(format!("Powered {:.0}%", self.powered_fraction * 100.));
+                        if let Some(description) = &self.description {
+                            ui.weak(description);
+                        }
+                    });
+                    ui.add_space(15.0);
                     ui.vertical_centered(|ui| {
                         if ui
                             .add_sized([80., 30.], egui::Button::new("Restart"))
@@ -107,8 +412,42 @@ impl PauseModal {
                         {
                             return Some(PauseModalEvent::NewGame);
                         }
+                        if ui
+                            .add_sized([80., 30.], egui::Button::new("Copy Code"))
+                            .on_hover_text("Copy a code to share this puzzle")
+                            .clicked()
+                        {
+                            return Some(PauseModalEvent::CopyCode);
+                        }
+                        if ui
+                            .add_sized([80., 30.], egui::Button::new("Save Image"))
+                            .on_hover_text("Save an image of the game board as PNG file")
+                            .clicked()
+                        {
+                            return Some(PauseModalEvent::SaveImage);
+                        }
+                        if ui
+                            .add_sized([80., 30.], egui::Button::new("Settings"))
+                            .clicked()
+                        {
+                            return Some(PauseModalEvent::Settings);
+                        }
+                        if ui
+                            .add_sized([80., 30.], egui::Button::new("Quit"))
+                            .on_hover_text("Quit to the menu")
+                            .clicked()
+                        {
+                            return Some(PauseModalEvent::Quit);
+                        }
+                        if ui.checkbox(&mut self.export_power, "Show energy flow").changed() {
+                            return Some(PauseModalEvent::ExportPowerChanged(self.export_power));
+                        }
+                        if let Some(message) = &self.message {
+                            ui.small(message);
+                        }
                         if ui
                             .add_sized([80., 30.], egui::Button::new("Continue"))
+                            .on_hover_text("Continue (Esc, P)")
                             .clicked()
                         {
                             return Some(PauseModalEvent::Continue);
@@ -127,8 +466,237 @@ impl PauseModal {
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PauseModalEvent {
     Continue,
+    CopyCode,
+    SaveImage,
+    ExportPowerChanged(bool),
     NewGame,
+    Quit,
     Restart,
+    Settings,
+}
+
+#[derive(Default)]
+pub struct ConfirmNewGameModal {
+    quit: bool, // the player quits to the menu instead of starting a new game
+    restart: bool, // the player restarts the current puzzle instead of starting a new game
+}
+
+impl ConfirmNewGameModal {
+    pub fn new() -> Self {
+        Self { quit: false, restart: false }
+    }
+
+    /// Set whether the game is abandoned to restart the same puzzle.
+    pub fn with_restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Set whether the game is abandoned to quit to the menu rather than to start a new game.
+    pub fn with_quit(mut self, quit: bool) -> Self {
+        self.quit = quit;
+        self
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<ConfirmNewGameModalEvent> {
+        egui::Modal::new(egui::Id::new("Confirm New Game"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(200.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("Abandon Game?");
+                    ui.separator();
+                    ui.add_space(15.0);
+                    ui.label("The progress of the current game will be lost.");
+                    ui.add_space(15.0);
+                    if ui
+                        .add_sized([160., 30.], egui::Button::new(if self.quit {
+                            "Discard and quit"
+                        } else if self.restart {
+                            "Discard and restart"
+                        } else {
+                            "Discard and start new"
+                        }))
+                        .clicked()
+                    {
+                        return Some(ConfirmNewGameModalEvent::Discard);
+                    }
+                    if ui
+                        .add_sized([160., 30.], egui::Button::new("Cancel"))
+                        .clicked()
+                    {
+                        return Some(ConfirmNewGameModalEvent::Cancel);
+                    }
+                    ui.add_space(15.0);
+                    None
+                })
+                .inner
+            })
+            .inner
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConfirmNewGameModalEvent {
+    Discard,
+    Cancel,
+}
+
+/// Edits a copy of the settings, which replaces the settings of the game on apply.
+///
+/// The tileset is chosen in the `NewGameModal`, since changing it requires loading textures.
+pub struct SettingsModal {
+    settings: Settings,
+}
+
+impl SettingsModal {
+    pub fn new(settings: Settings) -> Self {
+        Self { settings }
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<SettingsModalEvent> {
+        egui::Modal::new(egui::Id::new("Settings"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(300.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("Settings");
+                    ui.separator();
+                    ui.add_space(15.0);
+                });
+                let settings = &mut self.settings;
+                egui::Grid::new("Settings")
+                    .num_columns(2)
+                    .spacing([20.0, 10.0])
+                    .show(ui, |ui| {
+                        ui.label("Rotate clockwise");
+                        ui.add(egui::Checkbox::without_text(&mut settings.rotate_clockwise));
+                        ui.end_row();
+
+                        ui.label("Rotate with mouse wheel");
+                        ui.add(egui::Checkbox::without_text(&mut settings.wheel_rotation));
+                        ui.end_row();
+
+                        ui.label("Animation")
+                            .on_hover_text("Duration of a quarter rotation, 0 rotates without animation");
+                        ui.add(egui::Slider::new(&mut settings.animation_ms, 0..=300).suffix(" ms"));
+                        ui.end_row();
+
+                        ui.label("Tile size")
+                            .on_hover_text("The game board is fitted to the window within these sizes");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut settings.min_tile_size).range(10..=200));
+                            ui.label("to");
+                            ui.add(egui::DragValue::new(&mut settings.max_tile_size).range(10..=200));
+                        });
+                        ui.end_row();
+
+                        ui.label("Colors");
+                        egui::ComboBox::from_id_salt("Color scheme")
+                            .selected_text(settings.color_scheme.to_string())
+                            .show_ui(ui, |ui| {
+                                for color_scheme in ColorScheme::iter() {
+                                    ui.selectable_value(
+                                        &mut settings.color_scheme,
+                                        color_scheme,
+                                        color_scheme.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Start timer immediately")
+                            .on_hover_text("Start the timer when the game board is shown instead of on the first move");
+                        ui.add(egui::Checkbox::without_text(&mut settings.timer_starts_immediately));
+                        ui.end_row();
+
+                        ui.label("Show wrap marker")
+                            .on_hover_text("Mark the opposite edge of a board without boundary");
+                        ui.add(egui::Checkbox::without_text(&mut settings.show_wrap_marker));
+                        ui.end_row();
+
+                        ui.label("High contrast marks")
+                            .on_hover_text("Draw wrap markers and outlines of walls in the accent color");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Checkbox::without_text(&mut settings.high_contrast_marks));
+                            ui.add_enabled_ui(settings.high_contrast_marks, |ui| {
+                                egui::color_picker::color_edit_button_srgba(
+                                    ui,
+                                    &mut settings.accent_color,
+                                    egui::color_picker::Alpha::Opaque,
+                                );
+                            });
+                        });
+                        ui.end_row();
+
+                        ui.label("Power pulse")
+                            .on_hover_text("Let tiles which become powered blend briefly into their powered look");
+                        ui.add(egui::Checkbox::without_text(&mut settings.power_pulse));
+                        ui.end_row();
+
+                        ui.label("Highlight connections")
+                            .on_hover_text("Highlight all tiles connected to the tile under the mouse pointer");
+                        ui.add(egui::Checkbox::without_text(&mut settings.highlight_component));
+                        ui.end_row();
+
+                        ui.label("Highlight loops")
+                            .on_hover_text("Highlight tiles which form a closed loop, which is never part of the solution");
+                        ui.add(egui::Checkbox::without_text(&mut settings.highlight_loops));
+                        ui.end_row();
+
+                        ui.label("Show move history")
+                            .on_hover_text("List the moves beside the game board, click a move to undo up to it");
+                        ui.add(egui::Checkbox::without_text(&mut settings.show_move_history));
+                        ui.end_row();
+
+                        ui.label("Show mistakes")
+                            .on_hover_text("Highlight wrongly rotated tiles. This reduces the score.");
+                        ui.add(egui::Checkbox::without_text(&mut settings.show_mistakes));
+                        ui.end_row();
+
+                        #[cfg(feature = "audio")]
+                        {
+                            ui.label("Sound");
+                            ui.horizontal(|ui| {
+                                let icon = if settings.mute {
+                                    egui_phosphor::regular::SPEAKER_X
+                                } else {
+                                    egui_phosphor::regular::SPEAKER_HIGH
+                                };
+                                ui.toggle_value(&mut settings.mute, icon).on_hover_text("Mute");
+                                ui.add_enabled(
+                                    !settings.mute,
+                                    egui::Slider::new(&mut settings.volume_percent, 0..=100).suffix(" %"),
+                                );
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Energy flow in images")
+                            .on_hover_text("Show powered tiles in saved images of the game board");
+                        ui.add(egui::Checkbox::without_text(&mut settings.export_power));
+                        ui.end_row();
+                    });
+
+                ui.add_space(20.0);
+
+                ui.with_layout(egui::Layout::right_to_left(Default::default()), |ui| {
+                    if ui.add_sized([80., 30.], egui::Button::new("Apply")).clicked() {
+                        return Some(SettingsModalEvent::Apply(self.settings.clone()));
+                    }
+                    if ui.add_sized([80., 30.], egui::Button::new("Cancel")).clicked() {
+                        return Some(SettingsModalEvent::Cancel);
+                    }
+                    None
+                })
+                .inner
+            })
+            .inner
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsModalEvent {
+    Apply(Settings),
+    Cancel,
 }
 
 pub struct PuzzleSolvedModal {
@@ -136,18 +704,91 @@ pub struct PuzzleSolvedModal {
     moves: u32,
     expected_moves: u32,
     score: u32,
+    options: Options,
+    code: String, // the code of the puzzle, to share it with the result
+    previous_best: Option<Record>,
+    assisted: bool, // solved by the solver, the result does not count
+    practice: bool, // played without time, moves and score, none of them are shown
+    par_time: Option<Duration>, // the time is graded against it, see `Puzzle::par_time`
 }
 
 impl PuzzleSolvedModal {
-    pub fn new(time: std::time::Duration, moves: u32, expected_moves: u32, score: u32) -> Self {
+    pub fn new(
+        time: std::time::Duration,
+        moves: u32,
+        expected_moves: u32,
+        score: u32,
+        options: Options,
+        code: String,
+    ) -> Self {
         PuzzleSolvedModal {
             time,
             moves,
             expected_moves,
             score,
+            options,
+            code,
+            previous_best: None,
+            assisted: false,
+            practice: false,
+            par_time: None,
         }
     }
 
+    /// Return a summary of the result to share with others, including the puzzle code.
+    pub fn result_text(&self) -> String {
+        let size = self.options.board_size;
+        let boundary = if self.options.wrapping { " no boundary" } else { "" };
+        if self.practice {
+            return format!(
+                "Netwalk {size}×{size} {}{boundary} — solved in practice, code {}",
+                self.options.difficulty,
+                self.code,
+            );
+        }
+        let grade = self.grade().map(|grade| format!(", grade {grade}")).unwrap_or_default();
+        format!(
+            "Netwalk {size}×{size} {}{boundary} — {}{grade}, {}/{} moves, score {}, code {}",
+            self.options.difficulty,
+            format_duration(self.time),
+            self.moves,
+            self.expected_moves,
+            self.score,
+            self.code,
+        )
+    }
+
+    /// Return the grade of the time, if a par time was supplied and the puzzle was not solved by
+    /// the solver.
+    fn grade(&self) -> Option<Grade> {
+        self.par_time.filter(|_| !self.assisted).map(|par_time| Grade::new(self.time, par_time))
+    }
+
+    /// Show the best results achieved before this game with the same options.
+    pub fn with_previous_best(mut self, previous_best: Option<Record>) -> Self {
+        self.previous_best = previous_best;
+        self
+    }
+
+    /// Mark the puzzle as solved by the solver. The comparison with the best results is omitted.
+    pub fn with_assisted(mut self, assisted: bool) -> Self {
+        self.assisted = assisted;
+        self
+    }
+
+    /// Mark the puzzle as played in practice mode. Only a short message is shown instead of the
+    /// time, the moves and the score.
+    pub fn with_practice(mut self, practice: bool) -> Self {
+        self.practice = practice;
+        self
+    }
+
+    /// Grade the time against `par_time`, see [`Puzzle::par_time`].
+    pub fn with_par_time(mut self, par_time: Duration) -> Self {
+        self.par_time = Some(par_time);
+        self
+    }
+
     pub fn update(&mut self, ui: &mut egui::Ui) -> Option<PuzzleSolvedModalEvent> {
         egui::Modal::new(egui::Id::new("Puzzle Solved"))
             .show(ui.ctx(), |ui| {
@@ -157,23 +798,50 @@ impl PuzzleSolvedModal {
                     ui.separator();
                     ui.add_space(15.0);
                     ui.vertical_centered(|ui| {
+                        if self.practice {
+                            ui.strong("Solved!");
+                            return;
+                        }
                         ui.style_mut().spacing.item_spacing.y = 10.0;
-                        let seconds = self.time.as_secs();
-                        let minutes = seconds / 60;
-                        let rem_secs = seconds - minutes * 60;
-                        ui.label(format!("Time {minutes:02}:{rem_secs:02}"));
+                        ui.label(format!("Time {}", format_duration(self.time)));
+                        if let (Some(par_time), Some(grade)) = (self.par_time, self.grade()) {
+                            ui.label(format!("Par {} — Grade {grade}", format_duration(par_time)))
+                                .on_hover_text("A within par, B within twice the par, C beyond");
+                        }
                         ui.label(format!("Moves {}/{}", self.moves, self.expected_moves));
                         ui.label(format!("Score {}", self.score));
+                        match self.previous_best {
+                            _ if self.assisted => {
+                                ui.weak("Solved by assist");
+                            }
+                            Some(best) => {
+                                if best.is_beaten_by(self.time, self.score) {
+                                    ui.strong("New best!");
+                                }
+                                ui.weak(format!(
+                                    "Previous best {} / {}", format_duration(best.time), best.score
+                                ));
+                            }
+                            None => {
+                                ui.strong("New best!");
+                            }
+                        }
                     });
                     ui.add_space(15.0);
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Copy Result"))
+                        .on_hover_text("Copy the result and the puzzle code to share them")
+                        .clicked()
+                    {
+                        return Some(PuzzleSolvedModalEvent::CopyResult);
+                    }
                     if ui
                         .add_sized([80., 30.], egui::Button::new("New Game"))
                         .clicked()
                     {
-                        Some(PuzzleSolvedModalEvent::NewGame)
-                    } else {
-                        None
+                        return Some(PuzzleSolvedModalEvent::NewGame);
                     }
+                    None
                 })
                 .inner
             })
@@ -184,4 +852,167 @@ impl PuzzleSolvedModal {
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PuzzleSolvedModalEvent {
     NewGame,
+    CopyResult,
+}
+
+pub struct TimeUpModal {
+    time_limit: Duration,
+    powered_fraction: f32,
+}
+
+impl TimeUpModal {
+    pub fn new(time_limit: Duration, powered_fraction: f32) -> Self {
+        TimeUpModal { time_limit, powered_fraction }
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<TimeUpModalEvent> {
+        egui::Modal::new(egui::Id::new("Time Up"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(200.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("Time's Up");
+                    ui.separator();
+                    ui.add_space(15.0);
+                    ui.vertical_centered(|ui| {
+                        ui.style_mut().spacing.item_spacing.y = 10.0;
+                        ui.label(format!("Time limit {}", format_duration(self.time_limit)));
+                        ui.label(format!("Powered {:.0}%", self.powered_fraction * 100.));
+                        ui.label("Score 0");
+                    });
+                    ui.add_space(15.0);
+                    ui.horizontal(|ui| {
+                        if ui.add_sized([80., 30.], egui::Button::new("Try Again")).clicked() {
+                            return Some(TimeUpModalEvent::Restart);
+                        }
+                        if ui.add_sized([80., 30.], egui::Button::new("New Game")).clicked() {
+                            return Some(TimeUpModalEvent::NewGame);
+                        }
+                        None
+                    })
+                    .inner
+                })
+                .inner
+            })
+            .inner
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimeUpModalEvent {
+    NewGame,
+    Restart,
+}
+
+/// Explains the goal and the controls of the game. Shown on first run and from the status bar.
+#[derive(Default)]
+pub struct TutorialModal {}
+
+impl TutorialModal {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<TutorialModalEvent> {
+        egui::Modal::new(egui::Id::new("Modal Tutorial"))
+            .show(ui.ctx(), |ui| {
+                ui.set_width(360.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("How to Play");
+                    ui.separator();
+                });
+                ui.add_space(10.0);
+                for (title, text) in [
+                    ("Goal", "Rotate the tiles until every tile is connected to the source and \
+                        receives energy. Powered pipes light up."),
+                    ("Controls", "Click a tile to rotate it, or use the mouse wheel. Right-click \
+                        a tile to lock it once you are sure about it, locked tiles do not rotate. \
+                        Press Esc or P to pause."),
+                    ("Walls", "Energy does not flow through walls, even if two pipes meet \
+                        there."),
+                    ("No boundary", "On boards without boundary, pipes on the left edge connect \
+                        to the right edge, and pipes on the top edge to the bottom edge."),
+                ] {
+                    ui.strong(title);
+                    ui.label(text);
+                    ui.add_space(8.0);
+                }
+                ui.add_space(7.0);
+                ui.vertical_centered(|ui| {
+                    if ui
+                        .add_sized([80., 30.], egui::Button::new("Got it"))
+                        .clicked()
+                    {
+                        Some(TutorialModalEvent::Dismiss)
+                    } else {
+                        None
+                    }
+                })
+                .inner
+            })
+            .inner
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TutorialModalEvent {
+    Dismiss,
+}
+
+/// Format a duration as minutes and seconds, e.g. "03:07".
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    let minutes = seconds / 60;
+    let rem_secs = seconds - minutes * 60;
+    format!("{minutes:02}:{rem_secs:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_text() {
+        let options = Options {
+            board_size: 8,
+            difficulty: Difficulty::Hard,
+            ..Default::default()
+        };
+        let modal =
+            PuzzleSolvedModal::new(Duration::from_secs(83), 42, 40, 512, options, "ABC123".into());
+        assert_eq!(
+            modal.result_text(),
+            "Netwalk 8×8 Hard — 01:23, 42/40 moves, score 512, code ABC123"
+        );
+
+        let modal = modal.with_par_time(Duration::from_secs(160));
+        assert_eq!(
+            modal.result_text(),
+            "Netwalk 8×8 Hard — 01:23, grade A, 42/40 moves, score 512, code ABC123"
+        );
+        assert_eq!(modal.with_assisted(true).grade(), None);
+    }
+
+    #[test]
+    fn grading_thresholds() {
+        let par = Duration::from_secs(100);
+        assert_eq!(Grade::new(Duration::from_secs(30), par), Grade::A);
+        assert_eq!(Grade::new(par, par), Grade::A);
+        assert_eq!(Grade::new(Duration::from_secs(101), par), Grade::B);
+        assert_eq!(Grade::new(Duration::from_secs(200), par), Grade::B);
+        assert_eq!(Grade::new(Duration::from_secs(201), par), Grade::C);
+        assert_eq!(Grade::new(Duration::from_millis(500), Duration::ZERO), Grade::C);
+    }
+
+    #[test]
+    fn suggested_difficulty_grows_with_board_size() {
+        assert_eq!(suggested_difficulty(3), Difficulty::Easy);
+        assert_eq!(suggested_difficulty(8), Difficulty::Medium);
+        assert_eq!(suggested_difficulty(10), Difficulty::Hard);
+        assert_eq!(suggested_difficulty(15), Difficulty::Expert);
+
+        let suggestions = (3..=20).map(suggested_difficulty).collect::<Vec<_>>();
+        let rank = |difficulty| Difficulty::iter().position(|d| d == difficulty);
+        assert!(suggestions.windows(2).all(|pair| rank(pair[0]) <= rank(pair[1])));
+        assert!(!suggestions.contains(&Difficulty::Custom));
+    }
 }
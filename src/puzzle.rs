@@ -1,30 +1,90 @@
+mod ascii;
 mod builder;
+mod code;
+mod cycles;
+mod editing;
 mod links;
+mod solver;
 
 use std::cmp::PartialEq;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 
+use chrono::NaiveDate;
+use rand::Rng;
 use strum::IntoEnumIterator;
 
-pub use builder::Builder;
-use crate::grid::{Direction, Grid, Vec2};
-use crate::puzzle::links::{Links};
+pub use builder::{Builder, BuildError};
+pub use code::CodeError;
+pub use editing::{EditError, ValidationError};
+pub use links::{LinkError, Links};
+pub use crate::grid::{Direction, Vec2};
+use crate::grid::Grid;
 
 /// The puzzle, consisting of a grid of rotatable tiles, a source, multiple drains, walls, etc.
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "PuzzleData")]
 pub struct Puzzle {
     options: Options, // how the puzzle was generated
     tiles: Grid<Tile>,
     walls: Vec<Wall>,
+    #[serde(skip)]
+    wall_set: HashSet<(Vec2, Alignment)>, // the same walls as `walls` for a fast lookup
     source: Vec2,  // the tile containing the source is also marked as such
     expected_moves: u32, // expected number of moves required to solve the puzzle
+    seed: Option<u64>, // the seed of the generator, if one was supplied
+    date: Option<NaiveDate>, // the date of a daily puzzle
 }
 
 impl Puzzle {
+    #[doc(hidden)]
+    fn new(
+        options: Options,
+        tiles: Grid<Tile>,
+        walls: Vec<Wall>,
+        source: Vec2,
+        expected_moves: u32,
+    ) -> Self {
+        let wall_set = Self::create_wall_set(&walls);
+        Puzzle {
+            options,
+            tiles,
+            walls,
+            wall_set,
+            source,
+            expected_moves,
+            seed: None,
+            date: None,
+        }
+    }
+
+    #[doc(hidden)]
+    fn create_wall_set(walls: &[Wall]) -> HashSet<(Vec2, Alignment)> {
+        walls.iter().map(|wall| (wall.position, wall.alignment)).collect()
+    }
+
+    /// Return the seed the puzzle was generated from, see [`Builder::with_seed`]. Puzzles created
+    /// from a puzzle code have no seed.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Return the date of a daily puzzle, see [`Builder::daily`].
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
     /// Return the options which were applied during puzzle generation.
     pub fn options(&self) -> &Options {
         &self.options
     }
 
+    /// Return the time in which a practiced player solves the puzzle: one second per tile on
+    /// `Difficulty::Easy`, 1.5 s on `Medium` and `Custom`, 2.5 s on `Hard` and 4 s on `Expert`.
+    pub fn par_time(&self) -> Duration {
+        par_time(self.options.board_size, self.options.difficulty)
+    }
+
     /// Immutable access to the grid of tiles.
     pub fn grid(&self) -> &Grid<Tile> {
         &self.tiles
@@ -40,11 +100,40 @@ impl Puzzle {
         &self.walls
     }
 
+    /// Return the walls sorted by row, column and alignment (horizontal first). Unlike `walls`,
+    /// the order does not depend on how the puzzle was generated or edited.
+    pub fn walls_sorted(&self) -> Vec<Wall> {
+        let mut walls = self.walls.clone();
+        walls.sort_by_key(|wall| (wall.position.y, wall.position.x, wall.alignment as u8));
+        walls
+    }
+
+    /// Replace all walls. Only used to set up specific puzzles in tests.
+    #[cfg(test)]
+    pub(crate) fn set_walls(&mut self, walls: Vec<Wall>) {
+        self.wall_set = Self::create_wall_set(&walls);
+        self.walls = walls;
+    }
+
     /// Return the coordinates of the source tile.
     pub fn source(&self) -> &Vec2 {
         &self.source
     }
 
+    /// Return the coordinates of all drains, i.e. the dead ends other than the source, in
+    /// row-major order.
+    pub fn drains(&self) -> Vec<Vec2> {
+        self.tiles.indexed_iter()
+            .filter(|(_, tile)| tile.feature == Feature::Drain)
+            .map(|(coord, _)| coord)
+            .collect()
+    }
+
+    /// Return the number of drains which receive energy from the source.
+    pub fn drains_powered(&self) -> usize {
+        self.tiles.iter().filter(|tile| tile.feature == Feature::Drain && tile.powered).count()
+    }
+
     /// Return the number of moves expected to solve the puzzle.
     ///
     /// A move is a manipulation of a single tile (one or more rotations).
@@ -60,6 +149,22 @@ impl Puzzle {
         self.tiles.iter().all(|tile| tile.powered)
     }
 
+    /// Return the fraction of powered tiles, from 0 to 1. The puzzle is solved at 1.
+    pub fn powered_fraction(&self) -> f32 {
+        let powered = self.tiles.iter().filter(|tile| tile.powered).count();
+        powered as f32 / (self.tiles.rows() * self.tiles.cols()) as f32
+    }
+
+    /// Return true if the puzzle is well-formed: a square board of `Options::board_size` tiles
+    /// with one tile per position, and the source on the board. A puzzle read from a saved game
+    /// may be neither.
+    pub(crate) fn is_consistent(&self) -> bool {
+        let size = usize::from(self.options.board_size);
+        self.tiles.rows() == size && self.tiles.cols() == size &&
+            self.tiles.iter().count() == size * size &&
+            self.tiles.get(self.source).is_some_and(|tile| tile.feature == Feature::Source)
+    }
+
     /// Return the number of rows or columns of tiles on the game board.
     pub fn size(&self) -> u8 {
         assert_eq!(self.tiles.rows(), self.tiles.cols());
@@ -71,6 +176,221 @@ impl Puzzle {
         self.tiles.get(coord)
     }
 
+    /// Return true if the tile at `coord` is in the orientation of the generator's solution, or
+    /// `None` if `coord` is not on the grid. See [`Tile::is_correct`].
+    pub fn is_tile_correct(&self, coord: Vec2) -> Option<bool> {
+        self.tiles.get(coord).map(Tile::is_correct)
+    }
+
+    /// Rotate all tiles into the orientation of the generator's solution.
+    pub fn solve(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            tile.orientation = tile.solved_orientation;
+        }
+        self.calc_energy();
+    }
+
+    /// Rotate all tiles into the solution, e.g. to undo `scramble`. The same as `solve`.
+    pub fn solve_in_place(&mut self) {
+        self.solve();
+    }
+
+    /// Rotate all tiles into the solution, then rotate some of them away at random according to
+    /// the jumble options of the puzzle, like the generator does. The expected moves are those of
+    /// the new jumble.
+    ///
+    /// The puzzle no longer matches its seed or daily date, both are cleared. A puzzle of crosses
+    /// alone cannot be jumbled and stays solved.
+    pub fn scramble(&mut self, rng: &mut impl Rng) {
+        for tile in self.tiles.iter_mut() {
+            tile.orientation = tile.solved_orientation;
+        }
+        self.expected_moves = builder::rotate_tiles(
+            rng,
+            &mut self.tiles,
+            builder::clamp_fraction(self.options.jumble_fraction),
+            builder::clamp_fraction(self.options.jumble_stddev),
+        );
+        self.seed = None;
+        self.date = None;
+        self.calc_energy();
+    }
+
+    /// Return the rotations which turn the puzzle into the generator's solution, one per
+    /// incorrect tile, as pairs of coordinates and counter-clockwise quarter turns.
+    ///
+    /// The rotations are ordered by the distance of the tiles from the source along the
+    /// connections of the solution, so applying them one after another extends the powered area
+    /// step by step.
+    pub fn solution_moves(&self) -> Vec<(Vec2, u32)> {
+        let mut solved = self.clone();
+        solved.solve();
+
+        let mut moves = vec![];
+        let mut visited = HashSet::from([self.source]);
+        let mut queue = VecDeque::from([self.source]);
+        while let Some(current) = queue.pop_front() {
+            let quarters = self.tiles[current].quarters_to_solution();
+            if quarters > 0 {
+                moves.push((current, quarters));
+            }
+            for direction in Direction::iter() {
+                let neighbor = self.tiles.normalized_coord(current + direction.to_vec2());
+                if solved.connected(current, direction) && visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Return the coordinates of the tiles along the connections of the generator's solution from
+    /// the source to the tile at `target`, both included. Return `None` if `target` is not on the
+    /// grid or not connected to the source in the solution.
+    ///
+    /// The solution is a tree, so the path is unique.
+    pub fn solution_path(&self, target: Vec2) -> Option<Vec<Vec2>> {
+        if !self.tiles.contains_coord(target) {
+            return None;
+        }
+        let mut solved = self.clone();
+        solved.solve();
+
+        // Search from the source and remember where each tile was reached from.
+        let mut previous = HashMap::from([(self.source, self.source)]);
+        let mut queue = VecDeque::from([self.source]);
+        while let Some(current) = queue.pop_front() {
+            if current == target {
+                break;
+            }
+            for direction in Direction::iter() {
+                let neighbor = self.tiles.normalized_coord(current + direction.to_vec2());
+                if solved.connected(current, direction) && !previous.contains_key(&neighbor) {
+                    previous.insert(neighbor, current);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        previous.contains_key(&target).then(|| {
+            let mut path = vec![target];
+            while let Some(&last) = path.last() && last != self.source {
+                path.push(previous[&last]);
+            }
+            path.reverse();
+            path
+        })
+    }
+
+    /// Return true if the puzzle can be solved in exactly `expected_moves` moves, i.e. if exactly
+    /// that many tiles are not in the orientation of the generator's solution and rotating them
+    /// back solves the puzzle.
+    ///
+    /// This only holds for the starting position of a generated puzzle, not for a puzzle which
+    /// has been played.
+    pub fn verify_expected_moves(&self) -> bool {
+        let moves = self.solution_moves();
+        if moves.len() != self.expected_moves as usize {
+            return false;
+        }
+        let mut puzzle = self.clone();
+        for (coord, quarters) in moves {
+            for _ in 0..quarters {
+                puzzle.tiles[coord].rotate();
+            }
+        }
+        puzzle.calc_energy();
+        puzzle.solved()
+    }
+
+    /// Return a copy of the puzzle rotated clockwise by a quarter turn. The tiles, the source and
+    /// the walls move to their rotated positions, and every tile is rotated in place, both in its
+    /// current orientation and in the orientation of the solution.
+    ///
+    /// The rotated puzzle cannot be generated from the seed or the date of this puzzle, so both
+    /// are cleared.
+    pub fn rotated(&self) -> Puzzle {
+        let last = self.tiles.rows() as i32 - 1;
+        let rotate_coord = |coord: Vec2| Vec2::new(last - coord.y, coord.x);
+
+        let tiles = self.tiles.rotate_cw().map(|tile| Tile {
+            orientation: tile.orientation.next_cw(),
+            solved_orientation: tile.solved_orientation.next_cw(),
+            ..*tile
+        });
+        self.transformed(tiles, rotate_coord, Direction::rotate_cw)
+    }
+
+    /// Return a copy of the puzzle mirrored left to right. The tiles, the source and the walls
+    /// move to their mirrored positions, and the links of every tile to the left and to the right
+    /// are swapped, both in its current orientation and in the orientation of the solution.
+    ///
+    /// Like [`Puzzle::rotated`], the seed and the date are cleared.
+    pub fn mirror_horizontal(&self) -> Puzzle {
+        let last = self.tiles.cols() as i32 - 1;
+        let tiles = self.tiles.mirror_horizontal().map(|tile| Tile {
+            orientation: flipped_orientation(tile.kind, tile.orientation).next_ccw().next_ccw(),
+            solved_orientation: flipped_orientation(tile.kind, tile.solved_orientation)
+                .next_ccw()
+                .next_ccw(),
+            ..*tile
+        });
+        let mirror_dir = |dir: Direction| match dir {
+            Direction::Left | Direction::Right => -dir,
+            _ => dir,
+        };
+        self.transformed(tiles, |coord| Vec2::new(last - coord.x, coord.y), mirror_dir)
+    }
+
+    /// Return a copy of the puzzle mirrored top to bottom, with the links of every tile up and
+    /// down swapped. See [`Puzzle::mirror_horizontal`].
+    pub fn mirror_vertical(&self) -> Puzzle {
+        let last = self.tiles.rows() as i32 - 1;
+        let tiles = self.tiles.mirror_vertical().map(|tile| Tile {
+            orientation: flipped_orientation(tile.kind, tile.orientation),
+            solved_orientation: flipped_orientation(tile.kind, tile.solved_orientation),
+            ..*tile
+        });
+        let mirror_dir = |dir: Direction| match dir {
+            Direction::Up | Direction::Down => -dir,
+            _ => dir,
+        };
+        self.transformed(tiles, |coord| Vec2::new(coord.x, last - coord.y), mirror_dir)
+    }
+
+    /// Return a puzzle with the rearranged `tiles`, whose source and walls are moved like the
+    /// tiles. `move_coord` maps a coordinate of this puzzle to the new one, `move_dir` maps a
+    /// direction on this board to the direction on the new board.
+    #[doc(hidden)]
+    fn transformed(
+        &self,
+        tiles: Grid<Tile>,
+        move_coord: impl Fn(Vec2) -> Vec2,
+        move_dir: impl Fn(Direction) -> Direction,
+    ) -> Puzzle {
+        // A wall is stored with the tile below it or to the right of it.
+        let walls = self.walls.iter()
+            .map(|wall| {
+                let dir = match wall.alignment {
+                    Alignment::Horizontal => Direction::Up,
+                    Alignment::Vertical => Direction::Left,
+                };
+                let (position, alignment) = self.wall_key(move_coord(wall.position), move_dir(dir));
+                Wall::new(position, alignment)
+            })
+            .collect();
+
+        let mut puzzle = Puzzle::new(
+            self.options,
+            tiles,
+            walls,
+            move_coord(self.source),
+            self.expected_moves,
+        );
+        puzzle.calc_energy();
+        puzzle
+    }
+
     /// Recalculate which tiles are connected to the source and thus receive energy.
     pub fn calc_energy(&mut self) {
         assert!(self.tiles.contains_coord(self.source));
@@ -91,10 +411,93 @@ impl Puzzle {
         }
     }
 
-    /// Helper function for `calc_energy`. Return true if two tiles (one at `coord` and the
-    /// neighboring tile at `coord` + `dir`) have a connection (i.e. two links and no wall).
-    #[doc(hidden)]
-    fn connected(&self, coord: Vec2, dir: Direction) -> bool {
+    /// Recalculate which tiles receive energy after only the tile at `changed` has been rotated.
+    ///
+    /// This yields the same result as `calc_energy`, but only the connected components of the
+    /// changed tile and its neighbors are visited. All other tiles cannot have gained or lost
+    /// their connection to the source.
+    ///
+    /// Return the coordinates of the tiles which were unpowered before and are powered now, in no
+    /// particular order.
+    pub fn calc_energy_after(&mut self, changed: Vec2) -> Vec<Vec2> {
+        assert!(self.tiles.contains_coord(changed));
+
+        let starts = std::iter::once(changed)
+            .chain(self.tiles.wrapping_neighbors(changed).map(|(coord, _)| coord))
+            .collect::<Vec<_>>();
+
+        let mut visited = HashSet::new();
+        let mut newly_powered = vec![];
+        for start in starts {
+            if visited.contains(&start) {
+                continue;
+            }
+            let component = self.connected_component(start);
+            let powered = component.contains(&self.source);
+            for &coord in &component {
+                if powered && !self.tiles[coord].powered {
+                    newly_powered.push(coord);
+                }
+                self.tiles[coord].powered = powered;
+            }
+            visited.extend(component);
+        }
+        newly_powered
+    }
+
+    /// Return the coordinates of all tiles which are connected to the tile at `start`, including
+    /// `start` itself.
+    ///
+    /// The component of the source contains exactly the powered tiles.
+    pub fn connected_component(&self, start: Vec2) -> HashSet<Vec2> {
+        assert!(self.tiles.contains_coord(start));
+
+        let mut component = HashSet::from([start]);
+        let mut work_stack = vec![start];
+        while let Some(current) = work_stack.pop() {
+            for direction in Direction::iter() {
+                let neighbor = self.tiles.normalized_coord(current + direction.to_vec2());
+                if !component.contains(&neighbor) && self.connected(current, direction) {
+                    component.insert(neighbor);
+                    work_stack.push(neighbor);
+                }
+            }
+        }
+        component
+    }
+
+    /// Return the number of groups of tiles which are connected with each other. A tile without
+    /// any connection forms a group on its own, so a solved puzzle has exactly one group.
+    pub fn components(&self) -> usize {
+        let mut visited = HashSet::new();
+        let mut count = 0;
+        for coord in self.tiles.indices_iter() {
+            if !visited.contains(&coord) {
+                visited.extend(self.connected_component(coord));
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Return true if two tiles (one at `coord` and the neighboring tile at `coord` + `dir`) have
+    /// a connection in their current orientations, i.e. two links and no wall between them. On a
+    /// board without wrapping, tiles are never connected across the boundary.
+    ///
+    /// ```
+    /// use netwalk::puzzle::{Direction, Puzzle, Vec2};
+    ///
+    /// // The source in the center is connected to the tile below it, but a wall separates it
+    /// // from the tile to its right.
+    /// let puzzle = Puzzle::from_code("AgMAAAgBAXgqAHh7AXgDUgAaAOOO").unwrap();
+    /// let source = *puzzle.source();
+    /// assert_eq!(source, Vec2::new(1, 1));
+    /// assert!(puzzle.connected(source, Direction::Down));
+    /// assert!(puzzle.connected(Vec2::new(1, 2), Direction::Up));
+    /// assert!(!puzzle.connected(source, Direction::Right));
+    /// assert!(!puzzle.connected(Vec2::new(0, 0), Direction::Left));
+    /// ```
+    pub fn connected(&self, coord: Vec2, dir: Direction) -> bool {
         // If the grid is not wrapping, check the invisible walls around the game board.
         if !self.options.wrapping && !self.tiles.contains_coord(coord + dir.to_vec2()) {
             return false;
@@ -110,36 +513,168 @@ impl Puzzle {
         tile_a.has_link(dir) && tile_b.has_link(-dir)
     }
 
+    /// Return which edges of the tile at `coord` have a wall, indexed by `Direction as usize`.
+    ///
+    /// Walls are stored with the tile below (horizontal walls) or to the right (vertical walls)
+    /// of them, so a tile reports the walls stored with its neighbors, too. The boundary of a
+    /// board without wrapping does not count as a wall.
+    pub fn walls_around(&self, coord: Vec2) -> [bool; 4] {
+        Direction::all().map(|direction| self.wall_between(coord, direction))
+    }
+
     /// Helper function for `connected`. Return true if there is a wall between two tiles (one
-    /// at `coord` and the neighboring tile `coord` + `dir`).
+    /// at `coord` and the neighboring tile `coord` + `dir`). Without wrapping, there is no wall
+    /// on the boundary, even if one is stored with the tile on the opposite edge.
     #[doc(hidden)]
     fn wall_between(&self, coord: Vec2, dir: Direction) -> bool {
+        if !self.options.wrapping && !self.tiles.contains_coord(coord + dir.to_vec2()) {
+            return false;
+        }
+
+        self.wall_set.contains(&self.wall_key(coord, dir))
+    }
+
+    /// Return the position and alignment under which a wall between two tiles (one at `coord`
+    /// and the neighboring tile `coord` + `dir`) is stored.
+    #[doc(hidden)]
+    fn wall_key(&self, coord: Vec2, dir: Direction) -> (Vec2, Alignment) {
         let coord_a = coord;
         let coord_b = self.tiles.normalized_coord(coord + dir.to_vec2());
 
-        let (pos, alignment) = match dir {
+        match dir {
             Direction::Up => (coord_a, Alignment::Horizontal),
             Direction::Down => (coord_b, Alignment::Horizontal),
             Direction::Left => (coord_a, Alignment::Vertical),
             Direction::Right => (coord_b, Alignment::Vertical),
-        };
+        }
+    }
+}
+
+/// The serialized fields of a [`Puzzle`]. The lookup set of walls is rebuilt on deserialization.
+#[derive(serde::Deserialize)]
+struct PuzzleData {
+    options: Options,
+    tiles: Grid<Tile>,
+    walls: Vec<Wall>,
+    source: Vec2,
+    expected_moves: u32,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    date: Option<NaiveDate>,
+}
 
-        self.walls
-            .iter()
-            .any(|wall| wall.position == pos && wall.alignment == alignment)
+impl From<PuzzleData> for Puzzle {
+    fn from(data: PuzzleData) -> Self {
+        let mut puzzle =
+            Puzzle::new(data.options, data.tiles, data.walls, data.source, data.expected_moves);
+        puzzle.seed = data.seed;
+        puzzle.date = data.date;
+        puzzle
+    }
+}
+
+/// Two puzzles are equal if they have the same tiles (in the same orientations), walls, source,
+/// board size, difficulty and wrapping. The order of the walls does not matter.
+///
+/// The options which only affect the generation, the expected number of moves, the seed and the
+/// date are ignored, like in a puzzle code.
+impl PartialEq for Puzzle {
+    fn eq(&self, other: &Self) -> bool {
+        self.tiles == other.tiles
+            && self.wall_set == other.wall_set
+            && self.source == other.source
+            && self.options.board_size == other.options.board_size
+            && self.options.difficulty == other.options.difficulty
+            && self.options.wrapping == other.options.wrapping
+    }
+}
+
+impl Eq for Puzzle {}
+
+impl std::hash::Hash for Puzzle {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.canonical_hash());
+    }
+}
+
+impl Puzzle {
+    /// Return a hash of the puzzle which is equal for equal puzzles, independent of the order of
+    /// the walls and of walls listed twice. Unlike `std::hash::Hash`, the value is stable across
+    /// platforms and runs of the program, so it can be stored, e.g. as a key of high scores.
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the compared fields.
+    pub fn canonical_hash(&self) -> u64 {
+        let mut bytes = vec![
+            self.options.board_size,
+            self.options.difficulty as u8,
+            self.options.wrapping as u8,
+        ];
+        bytes.extend(self.source.x.to_le_bytes());
+        bytes.extend(self.source.y.to_le_bytes());
+        for tile in self.tiles.iter() {
+            bytes.extend([
+                tile.kind as u8,
+                tile.feature as u8,
+                tile.orientation as u8,
+                tile.solved_orientation as u8,
+                tile.powered as u8,
+            ]);
+        }
+        // Hash the set of walls like `eq` compares it, so a wall listed twice counts once.
+        let mut walls = self.wall_set.iter().collect::<Vec<_>>();
+        walls.sort_by_key(|(position, alignment)| (position.y, position.x, *alignment as u8));
+        for (position, alignment) in walls {
+            bytes.extend(position.x.to_le_bytes());
+            bytes.extend(position.y.to_le_bytes());
+            bytes.push(*alignment as u8);
+        }
+
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
     }
 }
 
 /// The game / puzzle options, e.g. difficulty and board size.
-#[derive(Copy, Clone, Debug, PartialEq)]
+///
+/// The densities, the custom weights, the wall mode and `unique_solution` only affect the
+/// generation of a puzzle. Like the time limit and practice mode, they are not contained in a
+/// puzzle code (see [`Puzzle::to_code`]), but the walls themselves are.
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Options {
     /// The number of rows and columns of the game board.
     pub board_size: u8,
     /// The difficulty of the puzzle.
     pub difficulty: Difficulty,
+    /// The weights of the kinds of tiles if the difficulty is `Difficulty::Custom`.
+    pub custom_weights: Weights,
     /// If true, the game board forms a torus, i.e. energy can flow from a tile on the left edge to
     /// a tile on the right edge, as well as from the top edge to the bottom edge.
     pub wrapping: bool,
+    /// Whether no walls, a random number of walls or all possible walls are placed.
+    pub walls: WallMode,
+    /// The mean fraction of possible walls which are placed with `WallMode::Random`, clamped to
+    /// `0.0..=1.0`. Walls are only possible where the solution has no connection.
+    pub wall_density: f32,
+    /// The standard deviation of the number of walls relative to its mean, clamped to
+    /// `0.0..=1.0`.
+    pub wall_density_stddev: f32,
+    /// The mean fraction of rotatable tiles which are rotated away from the solution, clamped
+    /// to `0.0..=1.0`. At least one tile is always rotated.
+    pub jumble_fraction: f32,
+    /// The standard deviation of the number of rotated tiles relative to its mean, clamped to
+    /// `0.0..=1.0`.
+    pub jumble_stddev: f32,
+    /// If true, the generator tries to create a puzzle with a single solution. See
+    /// [`Builder::build_with_attempts`].
+    pub unique_solution: bool,
+    /// If set, the game ends unsolved when the time runs out.
+    pub time_limit: Option<Duration>,
+    /// If true, the game is played without timer, move counter and score, and the result is not
+    /// recorded. The time limit is ignored.
+    pub practice: bool,
 }
 
 impl Default for Options {
@@ -147,16 +682,95 @@ impl Default for Options {
         Self {
             board_size: 3,
             difficulty: Difficulty::Easy,
+            custom_weights: Weights::default(),
             wrapping: false,
+            walls: WallMode::Random,
+            wall_density: 0.06,
+            wall_density_stddev: 0.2,
+            jumble_fraction: 0.8,
+            jumble_stddev: 0.1,
+            unique_solution: false,
+            time_limit: None,
+            practice: false,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, strum::Display)]
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Hash, strum::Display, strum::EnumIter, serde::Serialize,
+    serde::Deserialize,
+)]
 pub enum Difficulty {
     Easy,
     Medium,
     Hard,
+    Expert,
+    Custom, // the weights are taken from `Options::custom_weights`
+}
+
+/// How many walls the generator places, see [`Options::walls`]. Walls are only possible where the
+/// solution has no connection, so they never cut off a tile from the source in the solution.
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, Hash, strum::Display, strum::EnumIter,
+    serde::Serialize, serde::Deserialize,
+)]
+pub enum WallMode {
+    None,
+    #[default]
+    Random, // the number of walls depends on `Options::wall_density`
+    Max,
+}
+
+/// The weights of the kinds of tiles, see [`Builder::with_weights`]. The default weights are those
+/// of `Difficulty::Easy`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Weights {
+    pub dead_end: u32,
+    pub straight: u32,
+    pub corner: u32,
+    pub t_intersection: u32,
+    pub cross_intersection: u32,
+}
+
+impl Weights {
+    pub fn get(&self, kind: Kind) -> u32 {
+        match kind {
+            Kind::DeadEnd => self.dead_end,
+            Kind::Straight => self.straight,
+            Kind::Corner => self.corner,
+            Kind::TIntersection => self.t_intersection,
+            Kind::CrossIntersection => self.cross_intersection,
+        }
+    }
+
+    pub fn get_mut(&mut self, kind: Kind) -> &mut u32 {
+        match kind {
+            Kind::DeadEnd => &mut self.dead_end,
+            Kind::Straight => &mut self.straight,
+            Kind::Corner => &mut self.corner,
+            Kind::TIntersection => &mut self.t_intersection,
+            Kind::CrossIntersection => &mut self.cross_intersection,
+        }
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            dead_end: 1,
+            straight: 3,
+            corner: 4,
+            t_intersection: 1,
+            cross_intersection: 1,
+        }
+    }
+}
+
+impl From<Weights> for HashMap<Kind, u32> {
+    fn from(weights: Weights) -> Self {
+        Kind::iter().map(|kind| (kind, weights.get(kind))).collect()
+    }
 }
 
 /// A tile on the game board.
@@ -164,27 +778,62 @@ pub enum Difficulty {
 /// Tiles contain pipes of certain shapes and can also contain an energy source or drain. Tiles
 /// can be powered (if connected to an energy source) or unpowered. Tiles can be rotated which
 /// changes the connection of the pipes.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Tile {
     kind: Kind,
     feature: Feature,
     orientation: Orientation,
+    solved_orientation: Orientation, // the orientation in the generator's solution
     powered: bool,
 }
 
 impl Tile {
+    /// Create a tile from the links of the generator, see `try_from_links`.
+    ///
+    /// # Panics
+    /// This function panics if `links` has no active link.
     pub fn from_links(links: Links) -> Self {
-        let (kind, rotation): (Kind, Orientation) = links.into();
+        Self::try_from_links(links).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Create a tile in the orientation of `links`. Dead ends are drains.
+    ///
+    /// Return `LinkError::Empty` if `links` has no active link, such a tile does not exist.
+    pub fn try_from_links(links: Links) -> Result<Self, LinkError> {
+        let (kind, rotation): (Kind, Orientation) = links.try_into()?;
         let feature = match kind {
             Kind::DeadEnd => Feature::Drain,
             _ => Feature::None,
         };
 
-        Tile {
+        Ok(Tile {
             kind,
             feature,
             orientation: rotation,
+            solved_orientation: rotation,
             powered: false
+        })
+    }
+
+    /// Return the links of the tile in its current orientation, the inverse of `from_links`.
+    pub fn links(&self) -> Links {
+        let mut links = Links::default();
+        for direction in Direction::all() {
+            links[direction] = self.has_link(direction);
+        }
+        links
+    }
+
+    /// Create a tile for a hand-authored puzzle, see [`Puzzle::set_tile`]. The tile is in the
+    /// orientation of the solution. Dead ends are drains, the source is placed with
+    /// [`Puzzle::set_source`].
+    pub fn new(kind: Kind, orientation: Orientation) -> Self {
+        Tile {
+            kind,
+            feature: if kind == Kind::DeadEnd { Feature::Drain } else { Feature::None },
+            orientation,
+            solved_orientation: orientation,
+            powered: false,
         }
     }
 
@@ -196,8 +845,32 @@ impl Tile {
 
     pub fn powered(&self) -> bool { self.powered }
 
+    /// Return the orientation of the tile in the generator's solution.
+    pub fn solved_orientation(&self) -> Orientation { self.solved_orientation }
+
+    /// Return true if the tile is in the orientation of the generator's solution. Symmetric
+    /// orientations count as correct, e.g. a straight rotated by 180°.
+    ///
+    /// Note that a puzzle may have alternative solutions. A tile which is not correct according to
+    /// the generator's (canonical) solution might still be part of a valid solution.
+    pub fn is_correct(&self) -> bool { self.quarters_to_solution() == 0 }
+
     pub fn rotate(&mut self) { self.orientation = self.orientation.next_ccw(); }
 
+    pub fn rotate_cw(&mut self) { self.orientation = self.orientation.next_cw(); }
+
+    /// Return the number of counter-clockwise quarter turns required to rotate the tile into the
+    /// orientation of the generator's solution. Symmetric tiles (straights and crosses) need at
+    /// most one or zero turns, respectively.
+    pub(crate) fn quarters_to_solution(&self) -> u32 {
+        let quarters = (self.solved_orientation.quarters() + 4 - self.orientation.quarters()) as u32 % 4;
+        match self.kind {
+            Kind::Straight => quarters % 2,
+            Kind::CrossIntersection => 0,
+            _ => quarters,
+        }
+    }
+
     pub fn has_link(&self, direction: Direction) -> bool {
         let base_config = match self.kind {
             // east, north, west, south (right, up, left, down)
@@ -208,26 +881,22 @@ impl Tile {
             Kind::CrossIntersection => [true, true, true, true],
         };
 
-        let index: i8 = match direction {
+        let index: u8 = match direction {
             Direction::Up => 1,
             Direction::Down => 3,
             Direction::Left => 2,
             Direction::Right => 0,
         };
 
-        let offset: i8 = match self.orientation {
-            Orientation::Basic => 0,
-            Orientation::Ccw90 => 1,
-            Orientation::Ccw180 => 2,
-            Orientation::Ccw270 => 3,
-        };
-
-        base_config[(index - offset).rem_euclid(4) as usize]
+        base_config[((index + 4 - self.orientation.quarters()) % 4) as usize]
     }
 }
 
 /// The shape of the pipes on a tile, e.g. I, L or T.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, strum::EnumIs)]
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Hash, strum::EnumIs, strum::EnumIter, serde::Serialize,
+    serde::Deserialize,
+)]
 pub enum Kind {
     DeadEnd,
     Straight,
@@ -241,14 +910,42 @@ pub enum Kind {
 /// Most tiles have only pipes and no feature on them. There is only a single source tile,
 /// usually placed in the center of the game board. Tiles with a dead-end pipe are automatically
 /// considered drains.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Feature {
     None,
     Drain,
     Source,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, strum::EnumIter)]
+/// Return the par time of a puzzle: the number of tiles times a time per tile, which is longer
+/// for harder difficulties. Custom weights count like `Difficulty::Medium`.
+///
+/// The values are a heuristic, they are meant to grade a solve, not to be met by every player.
+fn par_time(board_size: u8, difficulty: Difficulty) -> Duration {
+    let millis_per_tile = match difficulty {
+        Difficulty::Easy => 1000,
+        Difficulty::Medium | Difficulty::Custom => 1500,
+        Difficulty::Hard => 2500,
+        Difficulty::Expert => 4000,
+    };
+    let tiles = u64::from(board_size) * u64::from(board_size);
+    Duration::from_secs((tiles * millis_per_tile).div_ceil(1000))
+}
+
+/// Return the orientation of a tile of `kind` in `orientation` after flipping it upside down,
+/// i.e. after swapping its links up and down. Flipping twice restores the orientation.
+fn flipped_orientation(kind: Kind, orientation: Orientation) -> Orientation {
+    // Flipping a tile in the basic orientation yields the basic orientation, except for a corner
+    // (right and down is Ccw270) and a T-intersection (right, down and left is Ccw180).
+    let flipped_basic = match kind {
+        Kind::Corner => 3,
+        Kind::TIntersection => 2,
+        _ => 0,
+    };
+    Orientation::from_quarters(flipped_basic + 4 - orientation.quarters())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::EnumIter, serde::Serialize, serde::Deserialize)]
 pub enum Orientation {
     Basic,   // fundamental, not rotated, facing right
     Ccw90,   // rotated 90° counter-clockwise, facing up
@@ -257,6 +954,30 @@ pub enum Orientation {
 }
 
 impl Orientation {
+    /// Return the orientation after `quarters` counter-clockwise quarter turns from
+    /// `Orientation::Basic`. Full turns are ignored, e.g. five quarter turns yield `Ccw90`.
+    pub fn from_quarters(quarters: u8) -> Self {
+        match quarters % 4 {
+            0 => Orientation::Basic,
+            1 => Orientation::Ccw90,
+            2 => Orientation::Ccw180,
+            _ => Orientation::Ccw270,
+        }
+    }
+
+    /// Return the number of counter-clockwise quarter turns from `Orientation::Basic`, from 0 to
+    /// 3.
+    pub fn quarters(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Return the orientation closest to `angle` in radian, measured counter-clockwise like
+    /// `to_angle`. Angles beyond a full turn and negative angles wrap around.
+    pub fn from_angle(angle: f32) -> Self {
+        let quarters = (angle / std::f32::consts::FRAC_PI_2).round().rem_euclid(4.);
+        Self::from_quarters(quarters as u8)
+    }
+
     /// Transform the orientation into an angle in radian.
     pub fn to_angle(&self) -> f32 {
         match self {
@@ -276,22 +997,35 @@ impl Orientation {
             Orientation::Ccw270 => Orientation::Basic,
         }
     }
+
+    /// Get the next orientation in clockwise order.
+    pub fn next_cw(&self) -> Self {
+        match self {
+            Orientation::Basic => Orientation::Ccw270,
+            Orientation::Ccw90 => Orientation::Basic,
+            Orientation::Ccw180 => Orientation::Ccw90,
+            Orientation::Ccw270 => Orientation::Ccw180,
+        }
+    }
 }
 
-// TODO Maybe create special data structure to quickly lookup if there is a wall between two tiles.
 // A horizontal wall is associated with the tile below it. In other words, a tile can have a wall along the top edge.
 // A vertical wall is associated with the tile to the right of it. In other words, a tile can have a wall along the left edge.
 
 /// A wall between two tiles.
 ///
 /// A wall can be aligned horizontally or vertically.
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Wall {
     position: Vec2,         // top or left tile
     alignment: Alignment, // maybe use two Vec2 instead
 }
 
 impl Wall {
+    pub fn new(position: Vec2, alignment: Alignment) -> Self {
+        Wall { position, alignment }
+    }
+
     pub fn position(&self) -> Vec2 {
         self.position
     }
@@ -302,7 +1036,7 @@ impl Wall {
 }
 
 /// The horizontal or vertical alignment of a wall.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::EnumIter, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::EnumIter, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Alignment {
     Horizontal,
     Vertical,
@@ -310,6 +1044,9 @@ pub enum Alignment {
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
     use super::*;
 
     #[allow(dead_code)]
@@ -318,6 +1055,7 @@ mod tests {
             board_size: 3,
             difficulty: Difficulty::Easy,
             wrapping: false,
+            ..Default::default()
         };
 
         let mut grid = Grid::<Tile>::with_size(
@@ -327,6 +1065,7 @@ mod tests {
                 kind: Kind::DeadEnd,
                 feature: Feature::None,
                 orientation: Orientation::Basic,
+                solved_orientation: Orientation::Basic,
                 powered: false,
             },
         );
@@ -334,6 +1073,7 @@ mod tests {
             kind: Kind::DeadEnd,
             feature: Feature::Drain,
             orientation: Orientation::Ccw270,
+            solved_orientation: Orientation::Ccw270,
             powered: false,
         };
 
@@ -341,6 +1081,7 @@ mod tests {
             kind: Kind::TIntersection,
             feature: Feature::None,
             orientation: Orientation::Basic,
+            solved_orientation: Orientation::Basic,
             powered: false,
         };
 
@@ -348,42 +1089,49 @@ mod tests {
             kind: Kind::Corner,
             feature: Feature::None,
             orientation: Orientation::Ccw180,
+            solved_orientation: Orientation::Ccw180,
             powered: false,
         };
         *grid.get_mut((0, 1).into()).unwrap() = Tile {
             kind: Kind::DeadEnd,
             feature: Feature::Drain,
             orientation: Orientation::Ccw270,
+            solved_orientation: Orientation::Ccw270,
             powered: false,
         };
         *grid.get_mut((1, 1).into()).unwrap() = Tile {
             kind: Kind::TIntersection,
             feature: Feature::Source,
             orientation: Orientation::Ccw270,
+            solved_orientation: Orientation::Ccw270,
             powered: false,
         };
         *grid.get_mut((2, 1).into()).unwrap() = Tile {
             kind: Kind::Straight,
             feature: Feature::None,
             orientation: Orientation::Basic,
+            solved_orientation: Orientation::Basic,
             powered: false,
         };
         *grid.get_mut((0, 0).into()).unwrap() = Tile {
             kind: Kind::DeadEnd,
             feature: Feature::Drain,
             orientation: Orientation::Ccw270,
+            solved_orientation: Orientation::Ccw270,
             powered: false,
         };
         *grid.get_mut((1, 0).into()).unwrap() = Tile {
             kind: Kind::Corner,
             feature: Feature::None,
             orientation: Orientation::Ccw90,
+            solved_orientation: Orientation::Ccw90,
             powered: false,
         };
         *grid.get_mut((2, 0).into()).unwrap() = Tile {
             kind: Kind::DeadEnd,
             feature: Feature::Drain,
             orientation: Orientation::Basic,
+            solved_orientation: Orientation::Basic,
             powered: false,
         };
 
@@ -405,18 +1153,517 @@ mod tests {
         let source = Vec2::new(1, 1);
         let expected_moves = 8;
 
-        let mut puzzle = Puzzle {
-            options,
-            tiles: grid,
-            walls,
-            source,
-            expected_moves,
-        };
+        let mut puzzle = Puzzle::new(options, grid, walls, source, expected_moves);
         puzzle.calc_energy();
         puzzle
     }
 
+    pub(super) fn tile_with_links(directions: &[Direction]) -> Tile {
+        let mut links = Links::default();
+        for &direction in directions {
+            links[direction] = true;
+        }
+        Tile::from_links(links)
+    }
+
+    #[test]
+    fn par_time_grows_with_size_and_difficulty() {
+        assert_eq!(par_time(8, Difficulty::Easy), Duration::from_secs(64));
+        assert_eq!(par_time(8, Difficulty::Medium), Duration::from_secs(96));
+        assert_eq!(par_time(3, Difficulty::Medium), Duration::from_secs(14));
+        assert_eq!(par_time(8, Difficulty::Custom), par_time(8, Difficulty::Medium));
+        assert!(par_time(8, Difficulty::Hard) < par_time(8, Difficulty::Expert));
+        assert!(par_time(7, Difficulty::Expert) < par_time(8, Difficulty::Expert));
+
+        let options = Options { board_size: 5, difficulty: Difficulty::Hard, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        assert_eq!(puzzle.par_time(), Duration::from_secs(63));
+    }
+
+    #[test]
+    fn orientation_quarters_and_angles() {
+        use std::f32::consts::FRAC_PI_2;
+
+        for (quarters, orientation) in Orientation::iter().enumerate() {
+            let quarters = quarters as u8;
+            assert_eq!(orientation.quarters(), quarters);
+            assert_eq!(Orientation::from_quarters(quarters), orientation);
+            assert_eq!(Orientation::from_quarters(quarters + 4), orientation);
+            assert_eq!(Orientation::from_angle(orientation.to_angle()), orientation);
+            // Slightly off angles and angles beyond a full turn in either direction.
+            assert_eq!(Orientation::from_angle(orientation.to_angle() + 0.4), orientation);
+            assert_eq!(Orientation::from_angle(orientation.to_angle() - 0.4), orientation);
+            assert_eq!(Orientation::from_angle(orientation.to_angle() + 8. * FRAC_PI_2), orientation);
+            assert_eq!(Orientation::from_angle(orientation.to_angle() - 4. * FRAC_PI_2), orientation);
+        }
+        assert_eq!(Orientation::from_quarters(255), Orientation::Ccw270);
+        assert_eq!(Orientation::from_angle(-FRAC_PI_2), Orientation::Ccw270);
+        assert_eq!(Orientation::Ccw90.next_ccw().quarters(), 2);
+    }
+
+    #[test]
+    fn links_round_trip() {
+        for kind in Kind::iter() {
+            for orientation in Orientation::iter() {
+                let tile = Tile::new(kind, orientation);
+                let links = tile.links();
+                for direction in Direction::all() {
+                    assert_eq!(links[direction], tile.has_link(direction));
+                }
+
+                let restored = Tile::from_links(links);
+                assert_eq!(restored.kind(), kind);
+                assert_eq!(restored.links(), links);
+                // Straights and crosses look the same after a half or quarter turn, `from_links`
+                // returns the first of the equivalent orientations.
+                let equivalent = match kind {
+                    Kind::Straight => Orientation::from_quarters(orientation.quarters() % 2),
+                    Kind::CrossIntersection => Orientation::Basic,
+                    _ => orientation,
+                };
+                assert_eq!(restored.orientation(), equivalent);
+                assert_eq!(Tile::try_from_links(links), Ok(restored));
+            }
+        }
+        assert_eq!(Tile::try_from_links(Links::default()), Err(LinkError::Empty));
+    }
+
+    #[test]
+    #[should_panic(expected = "encountered an empty tile with no links")]
+    fn tile_from_empty_links() {
+        Tile::from_links(Links::default());
+    }
+
+    #[test]
+    fn rotate_clockwise() {
+        for orientation in Orientation::iter() {
+            assert_eq!(orientation.next_ccw().next_cw(), orientation);
+        }
+        assert_eq!(Orientation::Basic.next_cw(), Orientation::Ccw270);
+
+        let mut tile = tile_with_links(&[Direction::Right, Direction::Up]);
+        tile.rotate_cw();
+        assert!(!tile.has_link(Direction::Up));
+        assert!(tile.has_link(Direction::Right));
+        assert!(tile.has_link(Direction::Down));
+        assert_eq!(tile.quarters_to_solution(), 1);
+    }
+
+    #[test]
+    fn rotate_puzzle() {
+        for wrapping in [false, true] {
+            let options = Options { board_size: 6, wrapping, wall_density: 0.5, ..Default::default() };
+            let puzzle = Builder::new().with_options(options).build();
+            assert!(!puzzle.walls().is_empty());
+
+            // Links and walls turn with the board, so every connection is kept.
+            let rotated = puzzle.rotated();
+            let rotate_coord = |coord: Vec2| Vec2::new(5 - coord.y, coord.x);
+            assert_eq!(*rotated.source(), rotate_coord(*puzzle.source()));
+            for coord in puzzle.grid().indices_iter() {
+                for dir in Direction::iter() {
+                    let tile = puzzle.get_tile(coord).unwrap();
+                    let rotated_tile = rotated.get_tile(rotate_coord(coord)).unwrap();
+                    assert_eq!(rotated_tile.has_link(dir.rotate_cw()), tile.has_link(dir));
+                    assert_eq!(
+                        rotated.connected(rotate_coord(coord), dir.rotate_cw()),
+                        puzzle.connected(coord, dir),
+                    );
+                }
+            }
+
+            let mut rotated = puzzle.clone();
+            for _ in 0..4 {
+                rotated = rotated.rotated();
+                assert_eq!(rotated.walls().len(), puzzle.walls().len());
+                assert_eq!(rotated.powered_fraction(), puzzle.powered_fraction());
+                assert!(rotated.verify_expected_moves());
+                assert_eq!(rotated.validate(), Ok(()));
+            }
+            assert!(rotated == puzzle);
+        }
+    }
+
+    #[test]
+    fn mirror_puzzle() {
+        for wrapping in [false, true] {
+            let options = Options { board_size: 5, wrapping, wall_density: 0.5, ..Default::default() };
+            let puzzle = Builder::new().with_options(options).build();
+            let mut solved = puzzle.clone();
+            solved.solve();
+
+            for horizontal in [true, false] {
+                let mirror = if horizontal { Puzzle::mirror_horizontal } else { Puzzle::mirror_vertical };
+                let mirror_coord = |coord: Vec2| match horizontal {
+                    true => Vec2::new(4 - coord.x, coord.y),
+                    false => Vec2::new(coord.x, 4 - coord.y),
+                };
+                let mirror_dir = |dir: Direction| match (horizontal, dir) {
+                    (true, Direction::Left | Direction::Right) => -dir,
+                    (false, Direction::Up | Direction::Down) => -dir,
+                    _ => dir,
+                };
+
+                let mirrored = mirror(&puzzle);
+                assert!(mirror(&mirrored) == puzzle);
+                assert!(mirror(&solved).solved());
+                assert!(!mirrored.solved());
+                assert!(mirrored.verify_expected_moves());
+
+                // The energy flows along the mirrored links and walls.
+                assert_eq!(*mirrored.source(), mirror_coord(*puzzle.source()));
+                for coord in puzzle.grid().indices_iter() {
+                    let tile = puzzle.get_tile(coord).unwrap();
+                    let mirrored_tile = mirrored.get_tile(mirror_coord(coord)).unwrap();
+                    assert_eq!(mirrored_tile.powered(), tile.powered());
+                    assert_eq!(mirrored_tile.is_correct(), tile.is_correct());
+                    for dir in Direction::iter() {
+                        assert_eq!(mirrored_tile.has_link(mirror_dir(dir)), tile.has_link(dir));
+                        assert_eq!(
+                            mirrored.connected(mirror_coord(coord), mirror_dir(dir)),
+                            puzzle.connected(coord, dir),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quarters_to_solution() {
+        let mut tile = tile_with_links(&[Direction::Right, Direction::Up]);
+        assert_eq!(tile.quarters_to_solution(), 0);
+        tile.rotate();
+        assert_eq!(tile.quarters_to_solution(), 3);
+        tile.rotate();
+        tile.rotate();
+        assert_eq!(tile.quarters_to_solution(), 1);
+
+        let mut tile = tile_with_links(&[Direction::Left, Direction::Right]);
+        tile.rotate();
+        assert_eq!(tile.quarters_to_solution(), 1);
+        tile.rotate();
+        assert_eq!(tile.quarters_to_solution(), 0);
+        assert!(tile.is_correct());
+        assert_ne!(tile.orientation(), tile.solved_orientation());
+    }
+
+    #[test]
+    fn incremental_energy_matches_full_recompute() {
+        for wrapping in [false, true] {
+            let options = Options {
+                board_size: 9,
+                difficulty: Difficulty::Medium,
+                wrapping,
+                ..Default::default()
+            };
+            let mut puzzle = Builder::new().with_options(options).build();
+            let mut rng = rand::rng();
+            for _ in 0..200 {
+                let coord = Vec2::new(rng.random_range(0..9), rng.random_range(0..9));
+                let before = puzzle.tiles.map(|tile| tile.powered);
+                puzzle.tiles[coord].rotate();
+                let mut newly_powered = puzzle.calc_energy_after(coord);
+
+                let mut expected = puzzle.clone();
+                expected.calc_energy();
+                assert!(puzzle.tiles.iter().eq(expected.tiles.iter()));
+
+                let mut gained = expected.tiles.indexed_iter()
+                    .filter(|&(coord, tile)| tile.powered && !before[coord])
+                    .map(|(coord, _)| coord)
+                    .collect::<Vec<_>>();
+                newly_powered.sort_by_key(|coord| (coord.y, coord.x));
+                gained.sort_by_key(|coord| (coord.y, coord.x));
+                assert_eq!(newly_powered, gained);
+            }
+        }
+    }
+
+    #[test]
+    fn connected_component_of_source_is_powered() {
+        let options = Options {
+            board_size: 6,
+            difficulty: Difficulty::Easy,
+            wrapping: true,
+            ..Default::default()
+        };
+        let mut puzzle = Builder::new().with_options(options).build();
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            let coord = Vec2::new(rng.random_range(0..6), rng.random_range(0..6));
+            puzzle.tiles[coord].rotate();
+            puzzle.calc_energy();
+
+            let component = puzzle.connected_component(puzzle.source);
+            for (index, tile) in puzzle.tiles.indexed_iter() {
+                assert_eq!(component.contains(&index), tile.powered);
+            }
+            let expected = component.len() as f32 / 36.;
+            assert_eq!(puzzle.powered_fraction(), expected);
+        }
+
+        puzzle.solve();
+        assert_eq!(puzzle.powered_fraction(), 1.);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let options = Options {
+            board_size: 6,
+            difficulty: Difficulty::Hard,
+            wrapping: true,
+            ..Default::default()
+        };
+        let puzzle = Builder::new().with_options(options).build();
+        let json = serde_json::to_string(&puzzle).unwrap();
+        let decoded: Puzzle = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.options, puzzle.options);
+        assert_eq!(decoded.source, puzzle.source);
+        assert_eq!(decoded.expected_moves, puzzle.expected_moves);
+        assert_eq!(decoded.walls, puzzle.walls);
+        assert_eq!(decoded.wall_set, puzzle.wall_set);
+        assert_eq!(decoded.tiles, puzzle.tiles);
+
+        // The tiles are stored in row-major order.
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let data = value["tiles"]["data"].as_array().unwrap();
+        for (index, tile) in puzzle.tiles.indexed_iter() {
+            let position = index.y as usize * puzzle.tiles.cols() + index.x as usize;
+            assert_eq!(serde_json::from_value::<Tile>(data[position].clone()).unwrap(), *tile);
+        }
+    }
+
+    #[test]
+    fn wall_set_consistent_with_walls() {
+        let mut puzzle = Builder::new().build();
+        let check = |puzzle: &Puzzle| {
+            assert_eq!(puzzle.wall_set.len(), puzzle.walls.len());
+            assert!(puzzle.walls.iter()
+                .all(|wall| puzzle.wall_set.contains(&(wall.position, wall.alignment))));
+        };
+        check(&puzzle);
+
+        puzzle.set_walls(vec![Wall::new(Vec2::new(1, 0), Alignment::Vertical)]);
+        check(&puzzle);
+        assert!(puzzle.wall_between(Vec2::new(0, 0), Direction::Right));
+        assert!(puzzle.wall_between(Vec2::new(1, 0), Direction::Left));
+        assert!(!puzzle.wall_between(Vec2::new(1, 0), Direction::Right));
+
+        check(&example_puzzle());
+    }
+
+    #[test]
+    fn walls_sorted_by_row_column_and_alignment() {
+        let mut puzzle = example_puzzle();
+        puzzle.set_walls(vec![
+            Wall::new(Vec2::new(0, 2), Alignment::Horizontal),
+            Wall::new(Vec2::new(2, 1), Alignment::Vertical),
+            Wall::new(Vec2::new(1, 1), Alignment::Vertical),
+            Wall::new(Vec2::new(2, 1), Alignment::Horizontal),
+        ]);
+        assert_eq!(puzzle.walls_sorted(), [
+            Wall::new(Vec2::new(1, 1), Alignment::Vertical),
+            Wall::new(Vec2::new(2, 1), Alignment::Horizontal),
+            Wall::new(Vec2::new(2, 1), Alignment::Vertical),
+            Wall::new(Vec2::new(0, 2), Alignment::Horizontal),
+        ]);
+    }
+
+    #[test]
+    fn drains_of_example_puzzle() {
+        let mut puzzle = example_puzzle();
+        assert_eq!(
+            puzzle.drains(),
+            [Vec2::new(0, 0), Vec2::new(2, 0), Vec2::new(0, 1), Vec2::new(0, 2)],
+        );
+        // None of the drains is connected to the source.
+        assert_eq!(puzzle.drains_powered(), 0);
+
+        // Only drains count, not other powered tiles.
+        puzzle.tiles[Vec2::new(2, 0)].powered = true;
+        puzzle.tiles[Vec2::new(0, 2)].powered = true;
+        puzzle.tiles[Vec2::new(1, 0)].powered = true;
+        assert_eq!(puzzle.drains_powered(), 2);
+        assert_eq!(puzzle.drains().len(), 4);
+    }
+
+    #[test]
+    fn components_of_example_puzzle() {
+        let mut puzzle = example_puzzle();
+        // The source is connected to the two tiles below and to its right; all other tiles are
+        // on their own.
+        assert_eq!(puzzle.components(), 7);
+
+        // Turning the upper left drain towards the corner tile joins the two.
+        puzzle.tiles[Vec2::new(0, 0)].rotate();
+        assert_eq!(puzzle.components(), 6);
+
+        // Turning the source away from the tile below splits off the bottom right pair.
+        puzzle.tiles[Vec2::new(1, 1)].rotate();
+        assert_eq!(puzzle.components(), 7);
+    }
+
+    #[test]
+    fn solution_moves_solve_puzzle() {
+        let options = Options { board_size: 7, wrapping: true, ..Default::default() };
+        let mut puzzle = Builder::new().with_options(options).build();
+        let moves = puzzle.solution_moves();
+        let incorrect = puzzle.grid().iter().filter(|tile| !tile.is_correct()).count();
+        assert_eq!(moves.len(), incorrect);
+
+        // Each rotated tile is powered, since the preceding moves connect it to the source.
+        for (coord, quarters) in moves {
+            for _ in 0..quarters {
+                puzzle.grid_mut()[coord].rotate();
+            }
+            puzzle.calc_energy_after(coord);
+            assert!(puzzle.grid()[coord].powered());
+        }
+        assert!(puzzle.solved());
+        assert!(puzzle.solution_moves().is_empty());
+    }
+
+    #[test]
+    fn solution_path_leads_from_source() {
+        let options = Options { board_size: 7, wrapping: true, ..Default::default() };
+        let puzzle = Builder::new().with_options(options).build();
+        let mut solved = puzzle.clone();
+        solved.solve();
+
+        for (drain, _) in puzzle.grid().indexed_iter().filter(|(_, tile)| tile.feature() == Feature::Drain) {
+            let path = puzzle.solution_path(drain).unwrap();
+            assert_eq!(path.first(), Some(puzzle.source()));
+            assert_eq!(path.last(), Some(&drain));
+            // Each step follows a connection of the solution.
+            for step in path.windows(2) {
+                let direction = Direction::iter()
+                    .find(|&direction| puzzle.grid().normalized_coord(step[0] + direction.to_vec2()) == step[1])
+                    .unwrap();
+                assert!(solved.connected(step[0], direction));
+            }
+        }
+        assert_eq!(puzzle.solution_path(*puzzle.source()), Some(vec![*puzzle.source()]));
+        assert_eq!(puzzle.solution_path(Vec2::new(7, 0)), None);
+    }
+
+    #[test]
+    fn equality_and_canonical_hash() {
+        let options = Options { board_size: 7, wrapping: true, ..Default::default() };
+        let build = |seed| Builder::new().with_options(options).with_seed(seed).build();
+        let puzzle = build(7);
+        assert!(puzzle == build(7));
+        assert_eq!(puzzle.canonical_hash(), build(7).canonical_hash());
+        assert!(puzzle != build(8));
+        assert_ne!(puzzle.canonical_hash(), build(8).canonical_hash());
+
+        // The order of the walls is irrelevant.
+        let mut reordered = puzzle.clone();
+        let mut walls = puzzle.walls().to_vec();
+        walls.reverse();
+        reordered.set_walls(walls);
+        assert!(reordered == puzzle);
+        assert_eq!(reordered.canonical_hash(), puzzle.canonical_hash());
+        assert_eq!(reordered.walls_sorted(), puzzle.walls_sorted());
+
+        // A rotated tile makes a different puzzle.
+        let mut rotated = puzzle.clone();
+        rotated.tiles[Vec2::new(0, 0)].rotate();
+        assert!(rotated != puzzle);
+
+        // A wall listed twice is the same puzzle.
+        let puzzle = example_puzzle();
+        let mut duplicated = puzzle.clone();
+        let mut walls = puzzle.walls().to_vec();
+        walls.push(walls[0]);
+        duplicated.set_walls(walls);
+        assert!(duplicated == puzzle);
+        assert_eq!(duplicated.canonical_hash(), puzzle.canonical_hash());
+
+        // Pin the hash function, stored hashes must stay valid.
+        assert_eq!(example_puzzle().canonical_hash(), 0x2b21_d54e_799f_e651);
+    }
+
+    #[test]
+    fn walls_around_tiles() {
+        let puzzle = example_puzzle();
+        // [Right, Up, Left, Down]
+        assert_eq!(puzzle.walls_around(Vec2::new(1, 1)), [true, false, true, false]);
+        assert_eq!(puzzle.walls_around(Vec2::new(0, 1)), [true, false, false, true]);
+        assert_eq!(puzzle.walls_around(Vec2::new(2, 1)), [false, false, true, false]);
+        assert_eq!(puzzle.walls_around(Vec2::new(0, 2)), [false, true, false, false]);
+        assert_eq!(puzzle.walls_around(Vec2::new(0, 0)), [false; 4]);
+
+        // Without wrapping, walls stored on the top and left edges lie on the boundary and are
+        // not on the opposite edges either.
+        let mut puzzle = puzzle;
+        puzzle.set_walls(vec![
+            Wall::new(Vec2::new(1, 0), Alignment::Horizontal),
+            Wall::new(Vec2::new(0, 2), Alignment::Vertical),
+        ]);
+        assert_eq!(puzzle.walls_around(Vec2::new(1, 2)), [false; 4]);
+        assert_eq!(puzzle.walls_around(Vec2::new(2, 2)), [false; 4]);
+        assert_eq!(puzzle.walls_around(Vec2::new(1, 0)), [false; 4]);
+        assert_eq!(puzzle.walls_around(Vec2::new(0, 2)), [false; 4]);
+
+        // On a wrapping board, the walls of the left column are on the right edge of the right
+        // column, too.
+        puzzle.options.wrapping = true;
+        puzzle.set_walls(vec![Wall::new(Vec2::new(0, 2), Alignment::Vertical)]);
+        assert_eq!(puzzle.walls_around(Vec2::new(2, 2)), [true, false, false, false]);
+        assert_eq!(puzzle.walls_around(Vec2::new(0, 2)), [false, false, true, false]);
+    }
+
+    #[test]
+    fn scramble_and_solve() {
+        let options = Options { board_size: 7, ..Default::default() };
+        let mut puzzle = Builder::new().with_options(options).with_seed(3).build();
+        let mut solution = puzzle.clone();
+        solution.solve_in_place();
+        assert!(solution.solved());
+
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        for _ in 0..5 {
+            puzzle.scramble(&mut rng);
+            assert!(!puzzle.solved());
+            assert_eq!(puzzle.seed(), None);
+            assert!(puzzle.verify_expected_moves());
+
+            puzzle.solve_in_place();
+            assert!(puzzle.solved());
+            assert!(puzzle == solution);
+        }
+    }
+
+    #[test]
+    fn solved_puzzle_has_one_component() {
+        let options = Options { board_size: 6, ..Default::default() };
+        let mut puzzle = Builder::new().with_options(options).build();
+        puzzle.solve();
+        assert_eq!(puzzle.components(), 1);
+    }
+
+    #[test]
+    fn consistent_puzzle() {
+        let puzzle = example_puzzle();
+        assert!(puzzle.is_consistent());
+
+        let mut reshaped = puzzle.clone();
+        reshaped.tiles = Grid::from_data(2, 4, reshaped.tiles.iter().copied().take(8).collect());
+        assert!(!reshaped.is_consistent());
+
+        let mut off_board = puzzle.clone();
+        off_board.source = Vec2::new(3, 0);
+        assert!(!off_board.is_consistent());
+
+        let mut moved = puzzle;
+        moved.source = Vec2::new(0, 0);
+        assert!(!moved.is_consistent());
+    }
+
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn verify_example_puzzle() {
         let puzzle = example_puzzle();
         assert_eq!(puzzle.solved(), false);
@@ -425,6 +1672,7 @@ mod tests {
             kind: Kind::DeadEnd,
             feature: Feature::Drain,
             orientation: Orientation::Ccw270,
+            solved_orientation: Orientation::Ccw270,
             powered: false
         }));
     }
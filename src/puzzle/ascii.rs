@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::grid::{Direction, Vec2};
+
+use super::{Feature, Puzzle, Tile};
+
+/// Render the puzzle as text, e.g. for debugging.
+///
+/// Each tile is drawn with a box-drawing character showing its links, using heavy lines if the
+/// tile is powered. The character is followed by `S` for the source and `D` for a drain. Walls
+/// are drawn with `|` and `-`; on a non-wrapping board the boundary is drawn as walls, too.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.tiles.rows() as i32;
+        let cols = self.tiles.cols() as i32;
+
+        let separator = |y: i32, direction: Direction| {
+            let line = (0..cols)
+                .map(|x| if self.closed(Vec2::new(x, y), direction) { " - " } else { "   " })
+                .collect::<String>();
+            line.trim_end().to_string()
+        };
+
+        for y in 0..rows {
+            writeln!(f, "{}", separator(y, Direction::Up))?;
+
+            let mut line = String::new();
+            for x in 0..cols {
+                let coord = Vec2::new(x, y);
+                line.push(if self.closed(coord, Direction::Left) { '|' } else { ' ' });
+                line.push(glyph(&self.tiles[coord]));
+                line.push(match self.tiles[coord].feature {
+                    Feature::Source => 'S',
+                    Feature::Drain => 'D',
+                    Feature::None => ' ',
+                });
+            }
+            if self.closed(Vec2::new(cols - 1, y), Direction::Right) {
+                line.push('|');
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        writeln!(f, "{}", separator(rows - 1, Direction::Down))
+    }
+}
+
+impl Puzzle {
+    /// Return true if energy can never flow from the tile at `coord` in `direction`, because of a
+    /// wall or the boundary of a non-wrapping board.
+    fn closed(&self, coord: Vec2, direction: Direction) -> bool {
+        (!self.options.wrapping && !self.tiles.contains_coord(coord + direction.to_vec2()))
+            || self.wall_between(coord, direction)
+    }
+}
+
+/// Return the box-drawing character of a tile.
+fn glyph(tile: &Tile) -> char {
+    // Light and heavy characters, indexed by the links as bits (right, up, left, down).
+    const LIGHT: [char; 16] = [
+        ' ', '╶', '╵', '└', '╴', '─', '┘', '┴', '╷', '┌', '│', '├', '┐', '┬', '┤', '┼',
+    ];
+    const HEAVY: [char; 16] = [
+        ' ', '╺', '╹', '┗', '╸', '━', '┛', '┻', '╻', '┏', '┃', '┣', '┓', '┳', '┫', '╋',
+    ];
+
+    let index = tile.links().directions()
+        .fold(0, |index, direction| index | 1 << direction as usize);
+    if tile.powered { HEAVY[index] } else { LIGHT[index] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::puzzle::tests::tile_with_links as tile;
+    use crate::puzzle::{Alignment, Options, Wall};
+
+    #[test]
+    fn render_ascii() {
+        use Direction::*;
+
+        let tiles = vec![
+            tile(&[Right]), tile(&[Left, Down]), tile(&[Down]),
+            tile(&[Down, Right]), tile(&[Right, Up, Left, Down]), tile(&[Up, Left]),
+            tile(&[Up]), tile(&[Right, Up]), tile(&[Left]),
+        ];
+        let mut tiles = Grid::from_data(3, 3, tiles);
+        let source = Vec2::new(1, 1);
+        tiles[source].feature = Feature::Source;
+        let walls = vec![Wall::new(Vec2::new(2, 1), Alignment::Horizontal)];
+        let mut puzzle = Puzzle::new(Options::default(), tiles, walls, source, 0);
+        puzzle.tiles[Vec2::new(2, 0)].rotate();
+        puzzle.calc_energy();
+
+        let expected = [
+            " -  -  -",
+            "|╺D ┓  ╶D|",
+            "       -",
+            "|┏  ╋S ┛ |",
+            "",
+            "|╹D ┗  ╸D|",
+            " -  -  -",
+            "",
+        ].join("\n");
+        assert_eq!(puzzle.to_string(), expected);
+    }
+}
@@ -1,14 +1,17 @@
 use std::collections::{HashMap, HashSet};
 
+use chrono::{Datelike, NaiveDate};
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use rand_distr::{Distribution, Normal};
 use strum::IntoEnumIterator;
+use thiserror::Error;
 
 use crate::direction::Direction;
 use crate::grid::{Grid, Vec2};
 use crate::puzzle::links::Links;
 
-use super::{Difficulty, Feature, Kind, Options, Alignment, Puzzle, Tile, Wall};
+use super::{Difficulty, Feature, Kind, Options, Alignment, Puzzle, Tile, Wall, WallMode};
 
 
 /// A builder capable of creating a random puzzle.
@@ -17,37 +20,318 @@ use super::{Difficulty, Feature, Kind, Options, Alignment, Puzzle, Tile, Wall};
 #[derive(Default)]
 pub struct Builder {
     options: Options,
+    weights: Option<HashMap<Kind, u32>>,
+    source: Option<Vec2>,
+    seed: Option<u64>,
+    date: Option<NaiveDate>,
+    branching_source: bool,
 }
 
 impl Builder {
+    /// The maximum number of puzzles generated when looking for a puzzle with a unique solution.
+    pub const MAX_ATTEMPTS: u32 = 50;
+
+    /// The maximum number of spanning trees grown when looking for one where the source is not a
+    /// dead end, see `with_branching_source`.
+    pub const MAX_SOURCE_REGROWS: u32 = 100;
+
+    /// The smallest supported number of rows and columns of the game board.
+    pub const MIN_BOARD_SIZE: u8 = 3;
+
+    /// The largest supported number of rows and columns of the game board. Generating and
+    /// checking large puzzles is slow, particularly with the option `unique_solution`.
+    pub const MAX_BOARD_SIZE: u8 = 64;
+
     pub fn new() -> Self {
         Builder {
             options: Default::default(),
+            weights: None,
+            source: None,
+            seed: None,
+            date: None,
+            branching_source: false,
         }
     }
 
+    /// Create a builder for the daily puzzle of `date`, i.e. a builder with `options` and the
+    /// seed `daily_seed(date)`. Everyone building the daily puzzle of a date with the same
+    /// options gets the same puzzle.
+    ///
+    /// # Panics
+    /// This function panics if the options are invalid, see `with_options`.
+    pub fn daily(date: NaiveDate, options: Options) -> Self {
+        let mut builder = Builder::new().with_options(options).with_seed(Self::daily_seed(date));
+        builder.date = Some(date);
+        builder
+    }
+
+    /// Return the seed of the daily puzzle of `date`.
+    ///
+    /// The seed is the SplitMix64 output function applied to the number of days since January 1
+    /// of the year 1 (as returned by `chrono::Datelike::num_days_from_ce`, which is 1 for that
+    /// day), plus the golden ratio increment `0x9E3779B97F4A7C15`.
+    pub fn daily_seed(date: NaiveDate) -> u64 {
+        let mut z = (date.num_days_from_ce() as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Supply a seed for the random number generator. Building with the same seed, options,
+    /// weights and source always creates the same puzzle, also on other platforms. Without a
+    /// seed, every puzzle is different.
+    ///
+    /// The sequence of random numbers is not stable across versions of this crate.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
     /// Supply options to the builder.
     ///
     /// # Panics
-    /// This function panics if the option `board_size` is smaller than 3 or larger than 20.
-    pub fn with_options(mut self, options: Options) -> Self {
-        if options.board_size < 3 {
-            panic!("board size must be at least 3");
+    /// This function panics if the options are invalid, see `try_with_options`.
+    pub fn with_options(self, options: Options) -> Self {
+        self.try_with_options(options).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Supply options to the builder. Return an error if the option `board_size` is smaller than
+    /// `MIN_BOARD_SIZE` or larger than `MAX_BOARD_SIZE`, or if a source was supplied with
+    /// `with_source` which is not on the game board.
+    pub fn try_with_options(mut self, options: Options) -> Result<Self, BuildError> {
+        Self::check(&options, self.source)?;
+        self.options = options;
+        Ok(self)
+    }
+
+    /// Supply options to the builder without checking them. Invalid options are reported by
+    /// `build_checked`, which allows to validate user input in one place.
+    pub fn with_unchecked_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Supply the position of the source. The spanning tree of the puzzle grows from there. By
+    /// default, the source is placed in the center of the game board.
+    ///
+    /// # Panics
+    /// This function panics if `source` is not on the game board of the current options.
+    pub fn with_source(mut self, source: Vec2) -> Self {
+        if !Self::source_on_board(source, self.options.board_size) {
+            panic!("{}", BuildError::InvalidSource(source));
         }
-        if options.board_size > 20 {
-            panic!("board size must not be greater than 20");
+        self.source = Some(source);
+        self
+    }
+
+    fn source_on_board(source: Vec2, board_size: u8) -> bool {
+        let range = 0..board_size as i32;
+        range.contains(&source.x) && range.contains(&source.y)
+    }
+
+    fn check(options: &Options, source: Option<Vec2>) -> Result<(), BuildError> {
+        if options.board_size < Self::MIN_BOARD_SIZE {
+            return Err(BuildError::BoardTooSmall(options.board_size));
         }
+        if options.board_size > Self::MAX_BOARD_SIZE {
+            return Err(BuildError::BoardTooLarge(options.board_size));
+        }
+        if let Some(source) = source && !Self::source_on_board(source, options.board_size) {
+            return Err(BuildError::InvalidSource(source));
+        }
+        Ok(())
+    }
 
-        self.options = options;
+    /// Avoid placing the source on a dead end. By default, the source is an arbitrary tile of
+    /// the spanning tree and ends up on a dead end from time to time, which makes for puzzles
+    /// with a single long branch.
+    ///
+    /// If enabled, the spanning tree is grown again until the source has at least two links, but
+    /// at most `MAX_SOURCE_REGROWS` times. The kinds of all other tiles still follow the weights.
+    pub fn with_branching_source(mut self, branching_source: bool) -> Self {
+        self.branching_source = branching_source;
+        self
+    }
+
+    /// Supply custom weights for the kinds of tiles, overriding the preset of the difficulty and
+    /// the option `custom_weights`.
+    ///
+    /// While the spanning tree of the puzzle is grown, each possible extension is chosen with a
+    /// probability proportional to the weight of the kind of tile it creates. Kinds missing from
+    /// `weights` have a weight of zero. If all possible extensions have a weight of zero, one of
+    /// them is chosen uniformly at random.
+    pub fn with_weights(mut self, weights: HashMap<Kind, u32>) -> Self {
+        self.weights = Some(weights);
         self
     }
 
     /// Create a new puzzle.
+    ///
+    /// If the option `unique_solution` is set, see `build_with_attempts`.
+    ///
+    /// # Panics
+    /// This function panics if the options are invalid, see `build_checked`.
     pub fn build(&self) -> Puzzle {
-        // Place the source in the center
+        self.build_checked().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Create a new puzzle. Return an error if the options supplied with
+    /// `with_unchecked_options` are invalid, see `try_with_options`.
+    ///
+    /// Generation itself is expected to always succeed; should it ever leave a tile unconnected,
+    /// `BuildError::IncompleteGeneration` is returned instead of an unplayable puzzle.
+    pub fn build_checked(&self) -> Result<Puzzle, BuildError> {
+        self.try_build_with_attempts().map(|(puzzle, _)| puzzle)
+    }
+
+    /// Create a new puzzle and return the number of puzzles generated in the process.
+    ///
+    /// Without the option `unique_solution`, exactly one puzzle is generated. Otherwise puzzles
+    /// are generated until the solver confirms that a puzzle has a unique solution, but at most
+    /// `MAX_ATTEMPTS` puzzles. If no puzzle has a unique solution, the one with the fewest
+    /// solutions is returned.
+    ///
+    /// Checking a puzzle for uniqueness takes a few milliseconds on large boards, and only some of
+    /// the generated puzzles have a unique solution. Hence building a puzzle with a unique
+    /// solution may take up to a hundred times longer than building an arbitrary puzzle. With the
+    /// feature `rayon`, as many attempts as there are threads are generated at once. The result
+    /// is the same as without the feature, also for a seeded builder, but more puzzles may be
+    /// generated in the process than the returned number of attempts.
+    ///
+    /// # Panics
+    /// This function panics if the options are invalid or the generation fails, see
+    /// `build_checked`.
+    pub fn build_with_attempts(&self) -> (Puzzle, u32) {
+        self.try_build_with_attempts().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_build_with_attempts(&self) -> Result<(Puzzle, u32), BuildError> {
+        Self::check(&self.options, self.source)?;
+        let (mut puzzle, attempts) = self.build_with_rng(&mut self.rng())?;
+        puzzle.seed = self.seed;
+        puzzle.date = self.date;
+        Ok((puzzle, attempts))
+    }
+
+    /// Create a new puzzle, drawing all random choices from `rng` instead of a generator of the
+    /// builder's own. This allows to supply any random number generator, e.g. a fake one in
+    /// tests. The seed and the date of the builder are ignored, the puzzle has neither: it is not
+    /// the daily puzzle of a [`Builder::daily`] builder.
+    ///
+    /// A builder with seed `s` yields the same puzzle as this function with
+    /// `ChaCha8Rng::seed_from_u64(s)`.
+    ///
+    /// # Panics
+    /// This function panics if the options are invalid or the generation fails, see
+    /// `build_checked`.
+    pub fn build_from_rng(&self, rng: &mut impl Rng) -> Puzzle {
+        let result = Self::check(&self.options, self.source)
+            .and_then(|()| self.build_with_rng(rng));
+        result.map(|(puzzle, _)| puzzle).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Create a playable puzzle from a hand-authored puzzle, see [`Puzzle::set_tile`]. The
+    /// orientations of the tiles of `solution` are taken as the solution, then tiles are rotated
+    /// away from it according to the jumble options of the builder, like `build` does.
+    ///
+    /// The board, the walls and the source are those of `solution`, which should pass
+    /// [`Puzzle::validate`].
+    pub fn jumble(&self, solution: &Puzzle) -> Puzzle {
+        let mut puzzle = solution.clone();
+        for tile in puzzle.tiles.iter_mut() {
+            tile.solved_orientation = tile.orientation;
+        }
+        puzzle.expected_moves = rotate_tiles(
+            &mut self.rng(),
+            &mut puzzle.tiles,
+            clamp_fraction(self.options.jumble_fraction),
+            clamp_fraction(self.options.jumble_stddev),
+        );
+        puzzle.seed = None;
+        puzzle.date = None;
+        puzzle.calc_energy();
+        puzzle
+    }
+
+    fn rng(&self) -> ChaCha8Rng {
+        match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_rng(&mut rand::rng()),
+        }
+    }
+
+    fn build_with_rng(&self, rng: &mut impl Rng) -> Result<(Puzzle, u32), BuildError> {
+        if !self.options.unique_solution {
+            return Ok((self.build_once(rng)?, 1));
+        }
+
+        // Each attempt has a random number generator of its own, so the attempts can be run in
+        // parallel. The attempts are evaluated in order, which makes the result independent of
+        // the number of threads.
+        let seeds = (0..Self::MAX_ATTEMPTS).map(|_| rng.random()).collect::<Vec<u64>>();
+        let batch_size = Self::parallel_attempts();
+        let mut best: Option<(Puzzle, usize)> = None;
+        for (batch, seeds) in seeds.chunks(batch_size).enumerate() {
+            for (i, attempt) in self.attempts(seeds).into_iter().enumerate() {
+                let (puzzle, solutions) = attempt?;
+                if solutions == 1 {
+                    return Ok((puzzle, (batch * batch_size + i + 1) as u32));
+                }
+                if best.as_ref().is_none_or(|(_, fewest)| solutions < *fewest) {
+                    best = Some((puzzle, solutions));
+                }
+            }
+        }
+        let (puzzle, _) = best.expect("at least one puzzle was generated");
+        Ok((puzzle, Self::MAX_ATTEMPTS))
+    }
+
+    /// Generate a puzzle with the random number generator seeded by `seed` and return it with its
+    /// number of solutions.
+    fn attempt(&self, seed: u64) -> Result<(Puzzle, usize), BuildError> {
+        // Counting more solutions than necessary distinguishes better attempts from worse ones.
+        const COUNT_LIMIT: usize = 8;
+        let puzzle = self.build_once(&mut ChaCha8Rng::seed_from_u64(seed))?;
+        // An aborted search counts as the worst result.
+        let solutions = puzzle.count_solutions(COUNT_LIMIT).unwrap_or(usize::MAX);
+        Ok((puzzle, solutions))
+    }
+
+    /// Run an attempt for each of `seeds`, on all threads of the feature `rayon`.
+    #[cfg(feature = "rayon")]
+    fn attempts(&self, seeds: &[u64]) -> Vec<Result<(Puzzle, usize), BuildError>> {
+        use rayon::prelude::*;
+        seeds.par_iter().map(|&seed| self.attempt(seed)).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn attempts(&self, seeds: &[u64]) -> Vec<Result<(Puzzle, usize), BuildError>> {
+        seeds.iter().map(|&seed| self.attempt(seed)).collect()
+    }
+
+    /// Return the number of attempts run at once. Without the feature `rayon`, attempts are run
+    /// one by one, so no attempt is generated in vain.
+    fn parallel_attempts() -> usize {
+        #[cfg(feature = "rayon")]
+        return rayon::current_num_threads();
+        #[cfg(not(feature = "rayon"))]
+        return 1;
+    }
+
+    /// Create a single random puzzle.
+    fn build_once(&self, rng: &mut impl Rng) -> Result<Puzzle, BuildError> {
+        // Place the source in the center unless another position was supplied
         let center = self.options.board_size / 2;
-        let source = Vec2::splat(center as i32);
-        let links = self.create_grid_of_links(source);
+        let source = self.source.unwrap_or(Vec2::splat(center as i32));
+        let mut links = self.create_grid_of_links(source, rng)?;
+        if self.branching_source {
+            for _ in 1..Self::MAX_SOURCE_REGROWS {
+                if Tile::from_links(links[source]).kind() != Kind::DeadEnd {
+                    break;
+                }
+                links = self.create_grid_of_links(source, rng)?;
+            }
+        }
 
         // Transform the grid of links into a grid of tiles
         let mut tiles = Grid::<Tile>::from_data(
@@ -59,27 +343,42 @@ impl Builder {
         );
         tiles[source].feature = Feature::Source;
 
-        let walls = self.create_walls(&tiles, 0.06, 0.2);
+        let walls = match self.options.walls {
+            WallMode::None => vec![],
+            WallMode::Random => self.create_walls(
+                rng,
+                &tiles,
+                clamp_fraction(self.options.wall_density),
+                clamp_fraction(self.options.wall_density_stddev),
+            ),
+            WallMode::Max => self.possible_walls(&tiles),
+        };
 
-        let expected_moves = self.rotate_tiles(&mut tiles, 0.8, 0.1);
+        let expected_moves = rotate_tiles(
+            rng,
+            &mut tiles,
+            clamp_fraction(self.options.jumble_fraction),
+            clamp_fraction(self.options.jumble_stddev),
+        );
 
-        let mut puzzle = Puzzle {
-            options: self.options,
-            tiles,
-            walls,
-            source,
-            expected_moves,
-        };
+        let mut puzzle = Puzzle::new(self.options, tiles, walls, source, expected_moves);
 
         puzzle.calc_energy();
-        puzzle
+        Ok(puzzle)
     }
 
     ///Create the underlying spanning tree of the grid graph.
     ///
     /// The algorithm starts with a source in the center and chooses an already visited tile at
     /// random to extend the tree to a random unvisited tile.
-    fn create_grid_of_links(&self, source: Vec2) -> Grid<Links> {
+    ///
+    /// Return `BuildError::IncompleteGeneration` if a tile was left without links, which would
+    /// not be a valid tile.
+    fn create_grid_of_links(
+        &self,
+        source: Vec2,
+        rng: &mut impl Rng,
+    ) -> Result<Grid<Links>, BuildError> {
         let size = self.options.board_size as usize;
         let mut proto_tiles = Grid::<Tile>::with_size(size, size, Links::default());
 
@@ -89,6 +388,13 @@ impl Builder {
         // The set of boundary nodes.
         let mut boundary = HashSet::from([source]);
 
+        let presets = difficulties();
+        let custom = self.options.custom_weights.into();
+        let weights = self.weights.as_ref().unwrap_or(match self.options.difficulty {
+            Difficulty::Custom => &custom,
+            difficulty => &presets[&difficulty],
+        });
+
         #[derive(Copy, Clone, Debug)]
         struct Connection {
             parent: Vec2,
@@ -123,16 +429,18 @@ impl Builder {
             if connections.is_empty() {
                 break;
             }
+            // The iteration order of the boundary is random, fix it for seeded generation.
+            connections.sort_by_key(|c| (c.parent.y, c.parent.x, c.direction as u8));
 
             let weighted_connections: Vec<_> = connections.iter().map(|connection| {
                 proto_tiles[connection.parent][connection.direction] = true;
                 let kind = Tile::from_links(proto_tiles[connection.parent]).kind();
                 proto_tiles[connection.parent][connection.direction] = false;
 
-                (connection, difficulties()[&self.options.difficulty][&kind])
+                (connection, weights.get(&kind).copied().unwrap_or(0))
             }).collect();
 
-            let connection = weighted_choice(&weighted_connections);
+            let connection = weighted_choice(rng, &weighted_connections);
 
             new_boundary.insert(connection.child);
             visited[connection.child] = true;
@@ -142,7 +450,8 @@ impl Builder {
             boundary = new_boundary;
         }
 
-        proto_tiles
+        check_links(&proto_tiles)?;
+        Ok(proto_tiles)
     }
 
     ///Randomly place some walls
@@ -153,7 +462,28 @@ impl Builder {
     ///
     /// The actual number of walls is drawn from a normal distribution with parameters `mean`
     /// (percentage of total number of possible walls) and `std_dev` (standard deviation).
-    fn create_walls(&self, tiles: &Grid<Tile>, mean_percent: f32, std_dev: f32) -> Vec<Wall> {
+    fn create_walls(
+        &self,
+        rng: &mut impl Rng,
+        tiles: &Grid<Tile>,
+        mean_percent: f32,
+        std_dev: f32,
+    ) -> Vec<Wall> {
+        let walls = self.possible_walls(tiles);
+        let mean = mean_percent * walls.len() as f32;
+        let normal = Normal::new(mean, std_dev * mean).unwrap();
+        let count = normal
+            .sample(rng)
+            .clamp(0.0, walls.len() as f32) as usize;
+        walls
+            .choose_multiple(rng, count)
+            .copied()
+            .collect()
+    }
+
+    /// Return all positions where a wall can be placed on the solved grid of tiles, i.e. between
+    /// each pair of neighboring tiles which are not connected in the solution.
+    fn possible_walls(&self, tiles: &Grid<Tile>) -> Vec<Wall> {
         let mut walls = vec![];
         for index in tiles.indices_iter() {
             // Top of tile
@@ -165,54 +495,81 @@ impl Builder {
                 walls.push(Wall { position: index, alignment: Alignment::Vertical })
             }
         }
-        let mean = mean_percent * walls.len() as f32;
-        let normal = Normal::new(mean, std_dev * mean).unwrap();
-        let count = normal
-            .sample(&mut rand::rng())
-            .clamp(0.0, walls.len() as f32) as usize;
         walls
-            .choose_multiple(&mut rand::rng(), count)
-            .copied()
-            .collect()
     }
+}
 
-    /// Randomly rotate some tiles.
-    ///
-    /// Must be called on the solved grid of tiles in order to jumble the puzzle.
-    fn rotate_tiles(&self, tiles: &mut Grid<Tile>, mean_percent: f32, std_dev: f32) -> u32 {
-        let indices_rotatable_tiles = tiles.indexed_iter().filter_map(|(index, tile)| {
-            match tile.kind {
-                Kind::CrossIntersection => None,
-                _ => Some(index),
-            }
-        }).collect::<Vec<_>>();
+/// Randomly rotate some tiles.
+///
+/// Must be called on the solved grid of tiles in order to jumble the puzzle. The solved
+/// orientation of each tile is kept, so the solution can be looked up later.
+///
+/// At least one tile is rotated, so the puzzle never starts solved. Since the solution is a
+/// spanning tree, any tile rotated away from its solution breaks the connection of some tiles
+/// to the source.
+pub(super) fn rotate_tiles(
+    rng: &mut impl Rng,
+    tiles: &mut Grid<Tile>,
+    mean_percent: f32,
+    std_dev: f32,
+) -> u32 {
+    let indices_rotatable_tiles = tiles.indexed_iter().filter_map(|(index, tile)| {
+        match tile.kind {
+            Kind::CrossIntersection => None,
+            _ => Some(index),
+        }
+    }).collect::<Vec<_>>();
+    // Only hand-authored puzzles may consist of crosses alone.
+    if indices_rotatable_tiles.is_empty() {
+        return 0;
+    }
 
-        let mean = mean_percent * indices_rotatable_tiles.len() as f32;
-        let normal = Normal::new(mean, std_dev * mean).unwrap();
-        let count = normal
-            .sample(&mut rand::rng())
-            .clamp(0.0, indices_rotatable_tiles.len() as f32) as usize;
-        let mut rng = rand::rng();
-        let rotate_indices = indices_rotatable_tiles
-            .choose_multiple(&mut rng, count)
-            .copied()
-            .collect::<Vec<_>>();
-        let expected_moves = rotate_indices.len();
+    let mean = mean_percent * indices_rotatable_tiles.len() as f32;
+    let normal = Normal::new(mean, std_dev * mean).unwrap();
+    let count = normal
+        .sample(rng)
+        .clamp(1.0, indices_rotatable_tiles.len() as f32) as usize;
+    let rotate_indices = indices_rotatable_tiles
+        .choose_multiple(rng, count)
+        .copied()
+        .collect::<Vec<_>>();
+    let expected_moves = rotate_indices.len();
 
-        // Apply
-        for index in rotate_indices {
-            let tile = tiles.get_mut(index).unwrap();
-            if tile.kind == Kind::Straight {
+    // Apply
+    for index in rotate_indices {
+        let tile = tiles.get_mut(index).unwrap();
+        if tile.kind == Kind::Straight {
+            tile.rotate();
+        } else {
+            let rotation_count = rng.random_range(1..4);
+            for _ in 0..rotation_count {
                 tile.rotate();
-            } else {
-                let rotation_count = rng.random_range(1..4);
-                for _ in 0..rotation_count {
-                    tile.rotate();
-                }
             }
         }
+    }
+
+    expected_moves as u32
+}
 
-        expected_moves as u32
+/// An error building a puzzle with a [`Builder`], usually caused by invalid options.
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq)]
+pub enum BuildError {
+    #[error("board size {0} is smaller than the minimum of {min}", min = Builder::MIN_BOARD_SIZE)]
+    BoardTooSmall(u8),
+    #[error("board size {0} is larger than the maximum of {max}", max = Builder::MAX_BOARD_SIZE)]
+    BoardTooLarge(u8),
+    #[error("source '{0:?}' is not on the game board")]
+    InvalidSource(Vec2),
+    #[error("the tile at '{0:?}' was not connected while generating the puzzle")]
+    IncompleteGeneration(Vec2),
+}
+
+/// Check that every tile of the spanning tree has at least one link. A tile without links was
+/// never reached from the source and cannot be turned into a tile.
+fn check_links(links: &Grid<Links>) -> Result<(), BuildError> {
+    match links.indexed_iter().find(|(_, links)| links.directions().next().is_none()) {
+        Some((coord, _)) => Err(BuildError::IncompleteGeneration(coord)),
+        None => Ok(()),
     }
 }
 
@@ -238,25 +595,40 @@ fn difficulties() -> HashMap<Difficulty, HashMap<Kind, u32>> {
         (Kind::Straight, 0),
         (Kind::DeadEnd, 1),
     ]);
+    let expert = HashMap::from([
+        (Kind::CrossIntersection, 2),
+        (Kind::TIntersection, 5),
+        (Kind::Corner, 3),
+        (Kind::Straight, 0),
+        (Kind::DeadEnd, 1),
+    ]);
     HashMap::from([
         (Difficulty::Easy, easy),
         (Difficulty::Medium, medium),
         (Difficulty::Hard, hard),
+        (Difficulty::Expert, expert),
     ])
 }
 
-fn weighted_choice<T>(slice: &[(T, u32)]) -> &T {
-    let mut rng = rand::rng();
+/// Clamp a fraction to `0.0..=1.0`. NaN is treated as zero.
+pub(super) fn clamp_fraction(value: f32) -> f32 {
+    if value.is_nan() {
+        0.
+    } else {
+        value.clamp(0., 1.)
+    }
+}
 
+fn weighted_choice<'a, T>(rng: &mut impl Rng, slice: &'a [(T, u32)]) -> &'a T {
     // Special case: if all weights are zero, rand::choose_weighted cannot be used.
     if slice.iter().all(|&(_, weight)| weight == 0) {
         &slice
-            .choose(&mut rng)
+            .choose(rng)
             .expect("slice must not be empty")
             .0
     } else {
         &slice
-            .choose_weighted(&mut rng, |s| s.1)
+            .choose_weighted(rng, |s| s.1)
             .expect("correct weights")
             .0
     }
@@ -273,6 +645,7 @@ mod tests {
             board_size: 2,
             difficulty: Difficulty::Easy,
             wrapping: false,
+            ..Default::default()
         };
         let _builder = Builder::default().with_options(options);
     }
@@ -281,22 +654,454 @@ mod tests {
     #[should_panic]
     fn build_options_board_size_above_max() {
         let options = Options {
-            board_size: 21,
+            board_size: Builder::MAX_BOARD_SIZE + 1,
             difficulty: Difficulty::Hard,
             wrapping: true,
+            ..Default::default()
         };
         let _builder = Builder::default().with_options(options);
     }
 
+    #[test]
+    fn try_options_board_size_limits() {
+        let options = |board_size| Options { board_size, ..Default::default() };
+        let try_build = |board_size| Builder::new().try_with_options(options(board_size)).map(|_| ());
+        assert_eq!(try_build(0), Err(BuildError::BoardTooSmall(0)));
+        assert_eq!(try_build(2), Err(BuildError::BoardTooSmall(2)));
+        assert_eq!(try_build(3), Ok(()));
+        assert_eq!(try_build(64), Ok(()));
+        assert_eq!(try_build(65), Err(BuildError::BoardTooLarge(65)));
+        assert_eq!(try_build(u8::MAX), Err(BuildError::BoardTooLarge(u8::MAX)));
+
+        let builder = Builder::new().with_options(options(10)).with_source(Vec2::new(9, 9));
+        assert_eq!(
+            builder.try_with_options(options(9)).map(|_| ()),
+            Err(BuildError::InvalidSource(Vec2::new(9, 9)))
+        );
+    }
+
+    #[test]
+    fn build_largest_board() {
+        let options = Options {
+            board_size: Builder::MAX_BOARD_SIZE,
+            wrapping: true,
+            ..Default::default()
+        };
+        let mut puzzle = Builder::new().with_options(options).build();
+        assert_eq!(puzzle.size(), Builder::MAX_BOARD_SIZE);
+        assert!(puzzle.verify_expected_moves());
+        assert_eq!(Puzzle::from_code(&puzzle.to_code()).unwrap().to_code(), puzzle.to_code());
+        puzzle.solve();
+        assert!(puzzle.solved());
+    }
+
+    #[test]
+    fn build_checked_reports_invalid_options() {
+        let options = |board_size| Options { board_size, ..Default::default() };
+        let build = |builder: Builder| builder.build_checked().map(|puzzle| puzzle.size());
+
+        assert_eq!(build(Builder::new().with_unchecked_options(options(4))), Ok(4));
+        assert_eq!(
+            build(Builder::new().with_unchecked_options(options(2))),
+            Err(BuildError::BoardTooSmall(2))
+        );
+        assert_eq!(
+            build(Builder::new().with_unchecked_options(options(Builder::MAX_BOARD_SIZE + 1))),
+            Err(BuildError::BoardTooLarge(Builder::MAX_BOARD_SIZE + 1))
+        );
+        let builder = Builder::new()
+            .with_options(options(8))
+            .with_source(Vec2::new(6, 2))
+            .with_unchecked_options(options(5));
+        assert_eq!(build(builder), Err(BuildError::InvalidSource(Vec2::new(6, 2))));
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_invalid_unchecked_options() {
+        let options = Options { board_size: 1, ..Default::default() };
+        Builder::new().with_unchecked_options(options).build();
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_source_outside_board() {
+        let options = Options {
+            board_size: 5,
+            ..Default::default()
+        };
+        let _builder = Builder::default()
+            .with_options(options)
+            .with_source(Vec2::new(2, 4))
+            .with_options(Options::default());
+    }
+
+    #[test]
+    fn build_with_source() {
+        let options = Options {
+            board_size: 5,
+            ..Default::default()
+        };
+        let source = Vec2::new(0, 3);
+        let mut puzzle = Builder::default().with_options(options).with_source(source).build();
+        assert_eq!(*puzzle.source(), source);
+        assert_eq!(puzzle.grid()[source].feature(), Feature::Source);
+
+        for tile in puzzle.grid_mut().iter_mut() {
+            while !tile.is_correct() {
+                tile.rotate();
+            }
+        }
+        puzzle.calc_energy();
+        assert!(puzzle.solved());
+    }
+
     #[test]
     fn build_random_puzzle() {
         let options = Options {
             board_size: 3,
             difficulty: Difficulty::Easy,
             wrapping: false,
+            ..Default::default()
         };
         let builder = Builder::default().with_options(options);
         let puzzle = builder.build();
         assert_eq!(*puzzle.options(), options);
     }
+
+    #[test]
+    fn build_puzzles_of_all_difficulties() {
+        for difficulty in Difficulty::iter() {
+            let options = Options {
+                board_size: 10,
+                difficulty,
+                wrapping: false,
+                ..Default::default()
+            };
+            let puzzle = Builder::default().with_options(options).build();
+            assert_eq!(puzzle.options().difficulty, difficulty);
+            assert!(puzzle.grid()[*puzzle.source()].powered());
+        }
+    }
+
+    #[test]
+    fn build_puzzle_with_custom_weights() {
+        let options = Options {
+            board_size: 8,
+            difficulty: Difficulty::Easy,
+            wrapping: false,
+            ..Default::default()
+        };
+        let weights = HashMap::from([(Kind::Corner, 1)]);
+        let puzzle = Builder::default().with_options(options).with_weights(weights).build();
+        // Straights are only created if no corner can be created.
+        let count = |kind| puzzle.grid().iter().filter(|tile| tile.kind() == kind).count();
+        assert!(count(Kind::Corner) > count(Kind::Straight));
+
+        // The weights of the options apply to the difficulty Custom.
+        let custom_weights = crate::puzzle::Weights {
+            dead_end: 0,
+            straight: 0,
+            corner: 1,
+            t_intersection: 0,
+            cross_intersection: 0,
+        };
+        let custom = Options { difficulty: Difficulty::Custom, custom_weights, ..options };
+        let puzzle = Builder::default().with_options(custom).build();
+        let count = |kind| puzzle.grid().iter().filter(|tile| tile.kind() == kind).count();
+        assert!(count(Kind::Corner) > count(Kind::Straight));
+
+        // All weights zero falls back to a uniform choice.
+        let puzzle = Builder::default().with_options(options).with_weights(HashMap::new()).build();
+        assert_eq!(puzzle.grid().iter().count(), 64);
+    }
+
+    #[test]
+    fn build_with_densities() {
+        let options = Options {
+            board_size: 6,
+            wall_density: 0.,
+            jumble_fraction: 0.,
+            ..Default::default()
+        };
+        let puzzle = Builder::default().with_options(options).build();
+        assert!(puzzle.walls().is_empty());
+        assert_eq!(puzzle.expected_moves(), 1);
+
+        let options = Options {
+            board_size: 6,
+            wall_density: 2.,
+            wall_density_stddev: f32::NAN,
+            jumble_fraction: -1.,
+            jumble_stddev: 5.,
+            ..Default::default()
+        };
+        let puzzle = Builder::default().with_options(options).build();
+        // 2 * 6 * 6 possible positions for walls, minus the 6 * 6 - 1 connections of the solution
+        // and the 2 * 6 positions on the boundary.
+        assert_eq!(puzzle.walls().len(), 2 * 6 * 6 - (6 * 6 - 1) - 2 * 6);
+        assert_eq!(puzzle.expected_moves(), 1);
+    }
+
+    #[test]
+    fn build_with_wall_modes() {
+        for wrapping in [false, true] {
+            let options = Options { board_size: 6, wrapping, wall_density: 0.5, ..Default::default() };
+            let build = |walls| {
+                let mut puzzle = Builder::default().with_options(Options { walls, ..options }).build();
+                puzzle.solve();
+                puzzle
+            };
+
+            assert!(build(WallMode::None).walls().is_empty());
+
+            // 2 * 6 * 6 possible positions for walls, minus the 6 * 6 - 1 connections of the
+            // solution and the 2 * 6 positions on the boundary without wrapping.
+            let puzzle = build(WallMode::Max);
+            let connections = 6 * 6 - 1;
+            let boundary = if wrapping { 0 } else { 2 * 6 };
+            assert_eq!(puzzle.walls().len(), 2 * 6 * 6 - connections - boundary);
+            // The walls never cut a connection of the solution.
+            assert!(puzzle.solved());
+
+            let puzzle = build(WallMode::Random);
+            assert!(puzzle.walls().len() < 2 * 6 * 6 - connections - boundary);
+            assert!(puzzle.solved());
+        }
+    }
+
+    #[test]
+    fn puzzle_never_starts_solved() {
+        for wrapping in [false, true] {
+            let options = Options {
+                board_size: 3,
+                wrapping,
+                jumble_fraction: 0.1,
+                ..Default::default()
+            };
+            for _ in 0..500 {
+                let puzzle = Builder::default().with_options(options).build();
+                assert!(puzzle.expected_moves() >= 1);
+                assert!(!puzzle.solved());
+            }
+        }
+    }
+
+    #[test]
+    fn solved_orientation_solves_puzzle() {
+        let options = Options {
+            board_size: 8,
+            difficulty: Difficulty::Hard,
+            wrapping: true,
+            ..Default::default()
+        };
+        let mut puzzle = Builder::default().with_options(options).build();
+        let incorrect = puzzle.grid().iter().filter(|tile| !tile.is_correct()).count();
+        assert!(incorrect as u32 <= puzzle.expected_moves());
+
+        for tile in puzzle.grid_mut().iter_mut() {
+            while !tile.is_correct() {
+                tile.rotate();
+            }
+        }
+        puzzle.calc_energy();
+        assert!(puzzle.solved());
+        assert!(puzzle.grid().indices_iter().all(|index| puzzle.is_tile_correct(index) == Some(true)));
+        assert_eq!(puzzle.is_tile_correct(Vec2::new(8, 0)), None);
+    }
+
+    #[test]
+    fn expected_moves_are_achievable() {
+        // Every rotated tile leaves its solved orientation: straights are turned by a quarter and
+        // other tiles by one to three quarters, crosses are never rotated.
+        for difficulty in Difficulty::iter() {
+            for wrapping in [false, true] {
+                let options = Options {
+                    board_size: 6,
+                    difficulty,
+                    wrapping,
+                    ..Default::default()
+                };
+                for _ in 0..50 {
+                    let puzzle = Builder::new().with_options(options).build();
+                    assert!(puzzle.verify_expected_moves());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn build_with_branching_source() {
+        // A corner of a board without wrapping has at most two possible links.
+        let cases = [(false, Vec2::new(0, 0)), (false, Vec2::new(2, 1)), (true, Vec2::new(1, 1))];
+        for (wrapping, source) in cases {
+            let options = Options {
+                board_size: 4,
+                wrapping,
+                difficulty: Difficulty::Hard,
+                ..Default::default()
+            };
+            let builder = Builder::new()
+                .with_options(options)
+                .with_source(source)
+                .with_branching_source(true);
+            let dead_ends = (0..200)
+                .map(|_| builder.build())
+                .filter(|puzzle| puzzle.grid()[source].kind() == Kind::DeadEnd)
+                .count();
+            assert_eq!(dead_ends, 0);
+        }
+    }
+
+    #[test]
+    fn seeded_build_is_deterministic() {
+        let options = Options {
+            board_size: 9,
+            difficulty: Difficulty::Medium,
+            wrapping: true,
+            unique_solution: true,
+            ..Default::default()
+        };
+        let build = |seed| Builder::new().with_options(options).with_seed(seed).build();
+        let puzzle = build(42);
+        assert_eq!(puzzle.seed(), Some(42));
+        assert_eq!(puzzle.date(), None);
+        assert_eq!(build(42).to_code(), puzzle.to_code());
+        assert_ne!(build(43).to_code(), puzzle.to_code());
+        assert_eq!(Builder::new().with_options(options).build().seed(), None);
+    }
+
+    #[test]
+    fn build_from_supplied_rng() {
+        /// Counts the random numbers drawn from the wrapped generator.
+        struct CountingRng {
+            rng: ChaCha8Rng,
+            draws: usize,
+        }
+
+        impl RngCore for CountingRng {
+            fn next_u32(&mut self) -> u32 {
+                self.draws += 1;
+                self.rng.next_u32()
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                self.draws += 1;
+                self.rng.next_u64()
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                self.draws += 1;
+                self.rng.fill_bytes(dest);
+            }
+        }
+
+        let options = Options { board_size: 6, wrapping: true, ..Default::default() };
+        let builder = Builder::new().with_options(options);
+        let mut rng = CountingRng { rng: ChaCha8Rng::seed_from_u64(5), draws: 0 };
+        let puzzle = builder.build_from_rng(&mut rng);
+        assert!(rng.draws > 0);
+        assert_eq!(puzzle.seed(), None);
+        assert_eq!(puzzle.to_code(), builder.with_seed(5).build().to_code());
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let puzzle = Builder::daily(date, options).build_from_rng(&mut rng);
+        assert_eq!(puzzle.date(), None);
+    }
+
+    #[test]
+    fn attempts_are_evaluated_in_order() {
+        let options = Options {
+            board_size: 8,
+            difficulty: Difficulty::Hard,
+            unique_solution: true,
+            ..Default::default()
+        };
+        let builder = Builder::new().with_options(options).with_seed(7);
+        let (puzzle, attempts) = builder.build_with_attempts();
+
+        // Rerun the attempts one by one, like without the feature `rayon`.
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let seeds = (0..Builder::MAX_ATTEMPTS).map(|_| rng.random()).collect::<Vec<u64>>();
+        let unique = seeds.iter().position(|&seed| builder.attempt(seed).unwrap().1 == 1);
+        if let Some(index) = unique {
+            assert_eq!(attempts, index as u32 + 1);
+            assert_eq!(builder.attempt(seeds[index]).unwrap().0.to_code(), puzzle.to_code());
+        } else {
+            assert_eq!(attempts, Builder::MAX_ATTEMPTS);
+        }
+    }
+
+    #[test]
+    fn unconnected_links_are_rejected() {
+        let mut links = Grid::<Links>::with_size(2, 2, Links::default());
+        assert_eq!(check_links(&links), Err(BuildError::IncompleteGeneration(Vec2::new(0, 0))));
+
+        // Connect three tiles in an L, the bottom right tile is left out.
+        links[Vec2::new(0, 0)][Direction::Right] = true;
+        links[Vec2::new(1, 0)][Direction::Left] = true;
+        links[Vec2::new(0, 0)][Direction::Down] = true;
+        links[Vec2::new(0, 1)][Direction::Up] = true;
+        assert_eq!(check_links(&links), Err(BuildError::IncompleteGeneration(Vec2::new(1, 1))));
+
+        links[Vec2::new(0, 1)][Direction::Right] = true;
+        links[Vec2::new(1, 1)][Direction::Left] = true;
+        assert_eq!(check_links(&links), Ok(()));
+    }
+
+    #[test]
+    fn generation_succeeds_for_extreme_options() {
+        use crate::puzzle::Weights;
+
+        let weights = |dead_end, straight, corner, t_intersection, cross_intersection| {
+            Weights { dead_end, straight, corner, t_intersection, cross_intersection }
+        };
+        // All presets, and custom weights which allow only few kinds of tiles or none at all.
+        let mut cases = Difficulty::iter()
+            .map(|difficulty| (difficulty, Weights::default()))
+            .collect::<Vec<_>>();
+        for custom_weights in [
+            weights(0, 0, 0, 0, 0),
+            weights(1, 0, 0, 0, 0),
+            weights(0, 1, 0, 0, 0),
+            weights(0, 0, 0, 0, 1),
+            weights(1, 0, 0, 0, 1),
+        ] {
+            cases.push((Difficulty::Custom, custom_weights));
+        }
+
+        for seed in 0..4 {
+            for board_size in [Builder::MIN_BOARD_SIZE, 5, 9] {
+                for wrapping in [false, true] {
+                    for &(difficulty, custom_weights) in &cases {
+                        let options = Options {
+                            board_size,
+                            wrapping,
+                            difficulty,
+                            custom_weights,
+                            ..Default::default()
+                        };
+                        let builder = Builder::new().with_options(options).with_seed(seed);
+                        let mut puzzle = builder.build_checked().unwrap();
+                        puzzle.solve();
+                        assert!(puzzle.solved(), "{options:?} with seed {seed}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn daily_puzzle() {
+        // Pin the documented hashing scheme, clients must agree on it.
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(Builder::daily_seed(date), 0xb8a0_7cae_04a5_1ced);
+        assert_ne!(Builder::daily_seed(date.succ_opt().unwrap()), Builder::daily_seed(date));
+
+        let options = Options { board_size: 7, ..Default::default() };
+        let puzzle = Builder::daily(date, options).build();
+        assert_eq!(puzzle.date(), Some(date));
+        assert_eq!(puzzle.seed(), Some(Builder::daily_seed(date)));
+        assert_eq!(Builder::daily(date, options).build().to_code(), puzzle.to_code());
+    }
 }
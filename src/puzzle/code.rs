@@ -0,0 +1,324 @@
+use thiserror::Error;
+
+use crate::grid::{Grid, Vec2};
+
+use super::{Alignment, Builder, Difficulty, Feature, Kind, Options, Orientation, Puzzle, Tile, Wall};
+
+// Layout of a decoded code (all numbers are unsigned bytes unless noted otherwise):
+//
+// version | board_size | flags | expected_moves (u16, big-endian) | source.x | source.y |
+// one byte per tile in row-major order | two bits per tile for the walls | checksum (u16, big-endian)
+//
+// The flags contain the wrapping flag (bit 0) and the difficulty (bits 1-3). A tile byte contains
+// the kind (bits 0-2), the orientation (bits 3-4) and the solved orientation (bits 5-6). The wall
+// bits of a tile mark a wall along the top edge and a wall along the left edge, respectively.
+//
+// Version 1 had no solved orientation, stored the walls in bits 5-6 of the tile bytes and the
+// difficulty in bits 1-2 of the flags, which leaves no room for `Difficulty::Custom`. Such codes
+// are refused, as the solved orientation cannot be restored.
+
+const VERSION: u8 = 2;
+const HEADER_LEN: usize = 7;
+const CHECKSUM_LEN: usize = 2;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl Puzzle {
+    /// Encode the puzzle into a short string which can be shared and decoded with `from_code`.
+    ///
+    /// The code contains the board size, the options, the tiles (including their current
+    /// orientation), the walls, the source and the number of expected moves.
+    pub fn to_code(&self) -> String {
+        let size = self.size();
+        let flags = self.options.wrapping as u8 | difficulty_to_bits(self.options.difficulty) << 1;
+        let expected_moves = u16::try_from(self.expected_moves).unwrap_or(u16::MAX);
+
+        let mut bytes = vec![VERSION, size, flags];
+        bytes.extend_from_slice(&expected_moves.to_be_bytes());
+        bytes.extend_from_slice(&[self.source.x as u8, self.source.y as u8]);
+        bytes.extend(self.tiles.iter().map(|tile| {
+            kind_to_bits(tile.kind)
+                | tile.orientation.quarters() << 3
+                | tile.solved_orientation.quarters() << 5
+        }));
+
+        let mut wall_bytes = vec![0u8; wall_bytes_len(size)];
+        for (i, index) in self.tiles.indices_iter().enumerate() {
+            for (bit, alignment) in [(0, Alignment::Horizontal), (1, Alignment::Vertical)] {
+                if self.wall_set.contains(&(index, alignment)) {
+                    let n = 2 * i + bit;
+                    wall_bytes[n / 8] |= 1 << (n % 8);
+                }
+            }
+        }
+        bytes.extend(wall_bytes);
+        bytes.extend_from_slice(&checksum(&bytes).to_be_bytes());
+
+        encode_base64(&bytes)
+    }
+
+    /// Decode a puzzle from a code created by `to_code`.
+    pub fn from_code(code: &str) -> Result<Puzzle, CodeError> {
+        let bytes = decode_base64(code.trim())?;
+        if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(CodeError::InvalidLength);
+        }
+
+        let (payload, check) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if checksum(payload).to_be_bytes() != check {
+            return Err(CodeError::ChecksumMismatch);
+        }
+
+        let (header, body) = payload.split_at(HEADER_LEN);
+        if header[0] != VERSION {
+            return Err(CodeError::UnsupportedVersion(header[0]));
+        }
+
+        let size = header[1];
+        if !(Builder::MIN_BOARD_SIZE..=Builder::MAX_BOARD_SIZE).contains(&size) {
+            return Err(CodeError::InvalidBoardSize(size));
+        }
+        let tile_count = size as usize * size as usize;
+        if body.len() != tile_count + wall_bytes_len(size) {
+            return Err(CodeError::InvalidLength);
+        }
+        let (tile_bytes, wall_bytes) = body.split_at(tile_count);
+
+        let flags = header[2];
+        let options = Options {
+            board_size: size,
+            difficulty: difficulty_from_bits(flags >> 1 & 0b111).ok_or(CodeError::InvalidOptions)?,
+            wrapping: flags & 1 == 1,
+            ..Default::default()
+        };
+        let expected_moves = u16::from_be_bytes([header[3], header[4]]) as u32;
+
+        let source = Vec2::new(header[5] as i32, header[6] as i32);
+        if source.x >= size as i32 || source.y >= size as i32 {
+            return Err(CodeError::InvalidSource(source));
+        }
+
+        let mut tiles = Vec::with_capacity(tile_bytes.len());
+        let mut walls = vec![];
+        for (i, &byte) in tile_bytes.iter().enumerate() {
+            let index = Vec2::new((i % size as usize) as i32, (i / size as usize) as i32);
+            let kind = kind_from_bits(byte & 0b111).ok_or(CodeError::InvalidTile(index))?;
+            let orientation = Orientation::from_quarters(byte >> 3 & 0b11);
+            let solved_orientation = Orientation::from_quarters(byte >> 5 & 0b11);
+            if byte >> 7 != 0 {
+                return Err(CodeError::InvalidTile(index));
+            }
+            for (bit, alignment) in [(0, Alignment::Horizontal), (1, Alignment::Vertical)] {
+                let n = 2 * i + bit;
+                if wall_bytes[n / 8] & 1 << (n % 8) != 0 {
+                    walls.push(Wall::new(index, alignment));
+                }
+            }
+
+            let feature = if index == source {
+                Feature::Source
+            } else if kind == Kind::DeadEnd {
+                Feature::Drain
+            } else {
+                Feature::None
+            };
+            tiles.push(Tile { kind, feature, orientation, solved_orientation, powered: false });
+        }
+
+        let tiles = Grid::from_data(size as usize, size as usize, tiles);
+        let mut puzzle = Puzzle::new(options, tiles, walls, source, expected_moves);
+        puzzle.calc_energy();
+        Ok(puzzle)
+    }
+}
+
+/// An error decoding a puzzle code.
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq)]
+pub enum CodeError {
+    #[error("invalid character '{0}' in puzzle code")]
+    InvalidCharacter(char),
+    #[error("puzzle code has an invalid length")]
+    InvalidLength,
+    #[error("puzzle code is corrupted (checksum mismatch)")]
+    ChecksumMismatch,
+    #[error("unsupported puzzle code version {0}")]
+    UnsupportedVersion(u8),
+    #[error("invalid board size {0} in puzzle code")]
+    InvalidBoardSize(u8),
+    #[error("invalid options in puzzle code")]
+    InvalidOptions,
+    #[error("invalid source position '{0:?}' in puzzle code")]
+    InvalidSource(Vec2),
+    #[error("invalid tile at '{0:?}' in puzzle code")]
+    InvalidTile(Vec2),
+}
+
+fn wall_bytes_len(size: u8) -> usize {
+    (2 * size as usize * size as usize).div_ceil(8)
+}
+
+fn kind_to_bits(kind: Kind) -> u8 {
+    match kind {
+        Kind::DeadEnd => 0,
+        Kind::Straight => 1,
+        Kind::Corner => 2,
+        Kind::TIntersection => 3,
+        Kind::CrossIntersection => 4,
+    }
+}
+
+fn kind_from_bits(bits: u8) -> Option<Kind> {
+    match bits {
+        0 => Some(Kind::DeadEnd),
+        1 => Some(Kind::Straight),
+        2 => Some(Kind::Corner),
+        3 => Some(Kind::TIntersection),
+        4 => Some(Kind::CrossIntersection),
+        _ => None,
+    }
+}
+
+fn difficulty_to_bits(difficulty: Difficulty) -> u8 {
+    match difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Medium => 1,
+        Difficulty::Hard => 2,
+        Difficulty::Expert => 3,
+        Difficulty::Custom => 4,
+    }
+}
+
+fn difficulty_from_bits(bits: u8) -> Option<Difficulty> {
+    match bits {
+        0 => Some(Difficulty::Easy),
+        1 => Some(Difficulty::Medium),
+        2 => Some(Difficulty::Hard),
+        3 => Some(Difficulty::Expert),
+        4 => Some(Difficulty::Custom),
+        _ => None,
+    }
+}
+
+/// Fletcher-16 checksum
+fn checksum(bytes: &[u8]) -> u16 {
+    let (mut a, mut b) = (0u16, 0u16);
+    for &byte in bytes {
+        a = (a + byte as u16) % 255;
+        b = (b + a) % 255;
+    }
+    b << 8 | a
+}
+
+/// Encode bytes with the URL-safe base64 alphabet, without padding.
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut code = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter()
+            .enumerate()
+            .fold(0u32, |value, (i, &byte)| value | (byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            code.push(ALPHABET[(value >> (18 - 6 * i) & 0b11_1111) as usize] as char);
+        }
+    }
+    code
+}
+
+fn decode_base64(code: &str) -> Result<Vec<u8>, CodeError> {
+    let sextets = code.chars()
+        .map(|c| {
+            ALPHABET.iter()
+                .position(|&a| a as char == c)
+                .map(|p| p as u32)
+                .ok_or(CodeError::InvalidCharacter(c))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut bytes = Vec::with_capacity(sextets.len() * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(CodeError::InvalidLength);
+        }
+        let value = chunk.iter()
+            .enumerate()
+            .fold(0u32, |value, (i, &sextet)| value | sextet << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((value >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::puzzle::Builder;
+
+    #[test]
+    fn base64_round_trip() {
+        for len in 0..8 {
+            let bytes = (0..len).map(|i| (i * 97 + 13) as u8).collect::<Vec<_>>();
+            assert_eq!(decode_base64(&encode_base64(&bytes)), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let options = Options {
+            board_size: 7,
+            difficulty: Difficulty::Medium,
+            wrapping: true,
+            ..Default::default()
+        };
+        let puzzle = Builder::new().with_options(options).build();
+        let code = puzzle.to_code();
+        let decoded = Puzzle::from_code(&code).unwrap();
+
+        assert_eq!(decoded.options, puzzle.options);
+        assert_eq!(decoded.source, puzzle.source);
+        assert_eq!(decoded.expected_moves, puzzle.expected_moves);
+        assert!(decoded.tiles.iter().eq(puzzle.tiles.iter()));
+        assert_eq!(
+            decoded.walls.iter().collect::<HashSet<_>>(),
+            puzzle.walls.iter().collect::<HashSet<_>>()
+        );
+        assert_eq!(decoded.to_code(), code);
+    }
+
+    #[test]
+    fn difficulty_round_trip() {
+        for difficulty in Difficulty::iter() {
+            let options = Options { difficulty, ..Default::default() };
+            let puzzle = Builder::new().with_options(options).build();
+            let decoded = Puzzle::from_code(&puzzle.to_code()).unwrap();
+            assert_eq!(decoded.options.difficulty, difficulty);
+        }
+    }
+
+    #[test]
+    fn older_version_is_refused() {
+        let code = Builder::new().build().to_code();
+        let mut bytes = decode_base64(&code).unwrap();
+        bytes.truncate(bytes.len() - CHECKSUM_LEN);
+        bytes[0] = 1;
+        bytes.extend_from_slice(&checksum(&bytes).to_be_bytes());
+        assert_eq!(Puzzle::from_code(&encode_base64(&bytes)).err(), Some(CodeError::UnsupportedVersion(1)));
+    }
+
+    #[test]
+    fn malformed_codes() {
+        let code = Builder::new().build().to_code();
+
+        assert_eq!(Puzzle::from_code("").err(), Some(CodeError::InvalidLength));
+        assert_eq!(Puzzle::from_code("AB*D").err(), Some(CodeError::InvalidCharacter('*')));
+        assert_eq!(Puzzle::from_code(&code[..code.len() - 4]).err(), Some(CodeError::ChecksumMismatch));
+
+        let mut corrupted = code.clone().into_bytes();
+        corrupted[5] = if corrupted[5] == b'A' { b'B' } else { b'A' };
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert_eq!(Puzzle::from_code(&corrupted).err(), Some(CodeError::ChecksumMismatch));
+    }
+}
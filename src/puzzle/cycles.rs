@@ -0,0 +1,144 @@
+use strum::IntoEnumIterator;
+
+use crate::grid::{Direction, Vec2};
+
+use super::Puzzle;
+
+impl Puzzle {
+    /// Return true if the connected tiles form a closed loop somewhere on the board.
+    ///
+    /// The solution of a puzzle is a tree, so a loop is always a mistake.
+    pub fn has_cycle(&self) -> bool {
+        !self.cycle_tiles().is_empty()
+    }
+
+    /// Return the coordinates of all tiles which are part of a closed loop, in no particular
+    /// order.
+    ///
+    /// A tile is part of a loop if one of its connections is not a bridge, i.e. if the two tiles
+    /// stay connected when the connection is cut. On a wrapping board, connections across the
+    /// edges of the board count like all others; two connections between the same pair of tiles
+    /// (across the edge of a board with two columns, say) form a loop, too.
+    pub fn cycle_tiles(&self) -> Vec<Vec2> {
+        let cols = self.tiles.cols();
+        let linear_index = |coord: Vec2| coord.y as usize * cols + coord.x as usize;
+        let len = self.tiles.rows() * cols;
+
+        // Find the bridges with Tarjan's algorithm: a DFS which tracks for each tile the earliest
+        // discovered tile reachable through its subtree and one further connection.
+        let mut discovery = vec![usize::MAX; len];
+        let mut low = vec![0; len];
+        let mut on_cycle = vec![false; len];
+        let mut time = 0;
+
+        for start in self.tiles.indices_iter() {
+            if discovery[linear_index(start)] != usize::MAX {
+                continue;
+            }
+            discovery[linear_index(start)] = time;
+            low[linear_index(start)] = time;
+            time += 1;
+
+            // Each entry holds a tile, the direction in which it was entered and the directions
+            // which still have to be visited.
+            let mut work_stack = vec![(start, None, Direction::iter())];
+            while let Some((current, entered, directions)) = work_stack.last_mut() {
+                let current = *current;
+                let i = linear_index(current);
+                if let Some(direction) = directions.next() {
+                    // Do not go back along the connection the tile was entered by.
+                    if Some(-direction) == *entered || !self.connected(current, direction) {
+                        continue;
+                    }
+                    let neighbor = self.tiles.normalized_coord(current + direction.to_vec2());
+                    let n = linear_index(neighbor);
+                    if discovery[n] == usize::MAX {
+                        discovery[n] = time;
+                        low[n] = time;
+                        time += 1;
+                        work_stack.push((neighbor, Some(direction), Direction::iter()));
+                    } else {
+                        // A connection to a tile which was discovered before closes a loop.
+                        low[i] = low[i].min(discovery[n]);
+                        on_cycle[i] = true;
+                        on_cycle[n] = true;
+                    }
+                } else {
+                    work_stack.pop();
+                    if let Some((parent, _, _)) = work_stack.last() {
+                        let p = linear_index(*parent);
+                        low[p] = low[p].min(low[i]);
+                        if low[i] <= discovery[p] {
+                            on_cycle[i] = true;
+                            on_cycle[p] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.tiles.indices_iter().filter(|&coord| on_cycle[linear_index(coord)]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::puzzle::{Builder, Feature, Options};
+    use crate::puzzle::tests::tile_with_links as tile;
+
+    #[test]
+    fn solution_has_no_cycle() {
+        for wrapping in [false, true] {
+            let options = Options { board_size: 8, wrapping, ..Default::default() };
+            let mut puzzle = Builder::new().with_options(options).build();
+            puzzle.solve();
+            assert!(!puzzle.has_cycle());
+        }
+    }
+
+    #[test]
+    fn square_with_tail() {
+        use Direction::*;
+
+        // A square of corners in the top left, with a dead end hanging off its bottom right
+        // corner. The right column is unconnected.
+        let tiles = vec![
+            tile(&[Right, Down]), tile(&[Left, Down]), tile(&[Down]),
+            tile(&[Up, Right]), tile(&[Up, Left, Down]), tile(&[Up]),
+            tile(&[Right]), tile(&[Up, Left]), tile(&[Left]),
+        ];
+        let mut tiles = Grid::from_data(3, 3, tiles);
+        tiles[Vec2::new(0, 0)].feature = Feature::Source;
+        let puzzle = Puzzle::new(Options::default(), tiles, vec![], Vec2::new(0, 0), 0);
+
+        let mut cycle = puzzle.cycle_tiles();
+        cycle.sort_by_key(|coord| (coord.y, coord.x));
+        assert_eq!(cycle, [Vec2::new(0, 0), Vec2::new(1, 0), Vec2::new(0, 1), Vec2::new(1, 1)]);
+    }
+
+    #[test]
+    fn cycle_around_torus() {
+        use Direction::*;
+
+        // The middle row is a straight line which wraps around the board.
+        let tiles = vec![
+            tile(&[Down]), tile(&[Down]), tile(&[Down]),
+            tile(&[Left, Right, Up]), tile(&[Left, Right, Up, Down]), tile(&[Left, Right, Up]),
+            tile(&[Right]), tile(&[Left, Up]), tile(&[Left]),
+        ];
+        let mut tiles = Grid::from_data(3, 3, tiles);
+        tiles[Vec2::new(1, 1)].feature = Feature::Source;
+        let options = Options { wrapping: true, ..Default::default() };
+        let puzzle = Puzzle::new(options, tiles.clone(), vec![], Vec2::new(1, 1), 0);
+
+        let mut cycle = puzzle.cycle_tiles();
+        cycle.sort_by_key(|coord| coord.x);
+        assert_eq!(cycle, [Vec2::new(0, 1), Vec2::new(1, 1), Vec2::new(2, 1)]);
+
+        // Without wrapping, the line ends at the boundary of the board.
+        let puzzle = Puzzle::new(Options::default(), tiles, vec![], Vec2::new(1, 1), 0);
+        assert!(!puzzle.has_cycle());
+    }
+}
@@ -0,0 +1,262 @@
+use thiserror::Error;
+
+use crate::grid::{Direction, Vec2};
+
+use super::{Alignment, Feature, Kind, Puzzle, Tile, Wall};
+
+impl Puzzle {
+    /// Replace the tile at `coord`, recalculate the power of all tiles and return the previous
+    /// tile.
+    ///
+    /// The feature of `tile` is ignored: the tile becomes the source if it replaces the source,
+    /// otherwise a dead end becomes a drain and all other tiles have no feature.
+    pub fn set_tile(&mut self, coord: Vec2, tile: Tile) -> Result<Tile, EditError> {
+        let feature = if coord == self.source {
+            Feature::Source
+        } else {
+            plain_feature(tile.kind)
+        };
+        let current = self.tiles.get_mut(coord).ok_or(EditError::OutOfBounds(coord))?;
+        let previous = std::mem::replace(current, Tile { feature, ..tile });
+        self.calc_energy();
+        Ok(previous)
+    }
+
+    /// Move the source to the tile at `coord` and recalculate the power of all tiles. The
+    /// previous source becomes a plain tile again, or a drain if it is a dead end.
+    pub fn set_source(&mut self, coord: Vec2) -> Result<(), EditError> {
+        if !self.tiles.contains_coord(coord) {
+            return Err(EditError::OutOfBounds(coord));
+        }
+
+        for tile in self.tiles.iter_mut().filter(|tile| tile.feature == Feature::Source) {
+            tile.feature = plain_feature(tile.kind);
+        }
+        self.tiles[coord].feature = Feature::Source;
+        self.source = coord;
+        self.calc_energy();
+        Ok(())
+    }
+
+    /// Add the wall along the top edge (`Alignment::Horizontal`) or the left edge
+    /// (`Alignment::Vertical`) of the tile at `position`, or remove it if it exists. Recalculate
+    /// the power of all tiles and return true if the wall exists afterwards.
+    ///
+    /// The boundary of a board without wrapping cannot have walls, toggling a wall there has no
+    /// effect.
+    pub fn toggle_wall(&mut self, position: Vec2, alignment: Alignment) -> Result<bool, EditError> {
+        if !self.tiles.contains_coord(position) {
+            return Err(EditError::OutOfBounds(position));
+        }
+        let on_boundary = match alignment {
+            Alignment::Horizontal => position.y == 0,
+            Alignment::Vertical => position.x == 0,
+        };
+        if on_boundary && !self.options.wrapping {
+            return Ok(false);
+        }
+
+        let exists = if self.wall_set.remove(&(position, alignment)) {
+            self.walls.retain(|wall| *wall != Wall::new(position, alignment));
+            false
+        } else {
+            self.wall_set.insert((position, alignment));
+            self.walls.push(Wall::new(position, alignment));
+            true
+        };
+        self.calc_energy();
+        Ok(exists)
+    }
+
+    /// Toggle the wall between the tile at `coord` and its neighbor in direction `dir`, see
+    /// `toggle_wall`.
+    pub fn toggle_wall_between(&mut self, coord: Vec2, dir: Direction) -> Result<bool, EditError> {
+        if !self.tiles.contains_coord(coord) {
+            return Err(EditError::OutOfBounds(coord));
+        }
+        if !self.options.wrapping && !self.tiles.contains_coord(coord + dir.to_vec2()) {
+            return Ok(false);
+        }
+        let (position, alignment) = self.wall_key(coord, dir);
+        self.toggle_wall(position, alignment)
+    }
+
+    /// Check that a hand-authored puzzle can be played: it has exactly one source, and every
+    /// tile is connected to the source when all tiles are in the orientation of the solution.
+    ///
+    /// The generator only creates valid puzzles, and the functions above keep the source intact.
+    /// A puzzle whose tiles were changed with `grid_mut` may have no source or several sources.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let sources = self.tiles.iter().filter(|tile| tile.feature == Feature::Source).count();
+        if sources > 1 {
+            return Err(ValidationError::MultipleSources(sources));
+        }
+        if self.tiles.get(self.source).is_none_or(|tile| tile.feature != Feature::Source) {
+            return Err(ValidationError::NoSource);
+        }
+
+        let mut solved = self.clone();
+        solved.solve();
+        match solved.tiles.indexed_iter().find(|(_, tile)| !tile.powered) {
+            Some((coord, _)) => Err(ValidationError::UnconnectedTile(coord)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Return the feature of a tile of `kind` which is not the source.
+fn plain_feature(kind: Kind) -> Feature {
+    match kind {
+        Kind::DeadEnd => Feature::Drain,
+        _ => Feature::None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Error)]
+pub enum EditError {
+    #[error("position '{0:?}' is not on the game board")]
+    OutOfBounds(Vec2),
+}
+
+/// The reason why a hand-authored puzzle cannot be played, see [`Puzzle::validate`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Error)]
+pub enum ValidationError {
+    #[error("the puzzle has no source")]
+    NoSource,
+    #[error("the puzzle has {0} sources instead of one")]
+    MultipleSources(usize),
+    #[error("the tile at '{0:?}' is not connected to the source in the solution")]
+    UnconnectedTile(Vec2),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::puzzle::{Builder, Options, Orientation};
+    use crate::puzzle::tests::tile_with_links as tile;
+
+    fn solved_puzzle() -> Puzzle {
+        let options = Options { board_size: 5, ..Default::default() };
+        let mut puzzle = Builder::new().with_options(options).build();
+        puzzle.solve();
+        puzzle
+    }
+
+    fn powered_count(puzzle: &Puzzle) -> usize {
+        puzzle.grid().iter().filter(|tile| tile.powered()).count()
+    }
+
+    #[test]
+    fn generated_puzzle_is_valid() {
+        let puzzle = Builder::new().with_options(Options { board_size: 6, ..Default::default() }).build();
+        assert_eq!(puzzle.validate(), Ok(()));
+    }
+
+    #[test]
+    fn move_source() {
+        let mut puzzle = solved_puzzle();
+        let previous = *puzzle.source();
+        let corner = Vec2::new(0, 0);
+        puzzle.set_source(corner).unwrap();
+
+        assert_eq!(*puzzle.source(), corner);
+        assert_eq!(puzzle.get_tile(corner).unwrap().feature(), Feature::Source);
+        assert_ne!(puzzle.get_tile(previous).unwrap().feature(), Feature::Source);
+        // Moving the source along the tree of the solution keeps all tiles powered.
+        assert!(puzzle.solved());
+        assert_eq!(puzzle.validate(), Ok(()));
+
+        // Behind a wall on all sides, only the source is powered.
+        puzzle.set_source(Vec2::new(2, 2)).unwrap();
+        for dir in Direction::all() {
+            if puzzle.connected(Vec2::new(2, 2), dir) {
+                puzzle.toggle_wall_between(Vec2::new(2, 2), dir).unwrap();
+            }
+        }
+        assert_eq!(powered_count(&puzzle), 1);
+
+        assert_eq!(puzzle.set_source(Vec2::new(5, 0)), Err(EditError::OutOfBounds(Vec2::new(5, 0))));
+    }
+
+    #[test]
+    fn replace_tiles() {
+        use Direction::*;
+
+        // Three columns hanging off the middle row, with the source in the center.
+        let tiles = vec![
+            tile(&[Down]), tile(&[Down]), tile(&[Down]),
+            tile(&[Up, Right, Down]), tile(&[Left, Up, Right, Down]), tile(&[Left, Up, Down]),
+            tile(&[Up]), tile(&[Up]), tile(&[Up]),
+        ];
+        let mut tiles = Grid::from_data(3, 3, tiles);
+        let source = Vec2::new(1, 1);
+        tiles[source].feature = Feature::Source;
+        let mut puzzle = Puzzle::new(Options::default(), tiles, vec![], source, 0);
+        puzzle.calc_energy();
+        assert!(puzzle.solved());
+
+        // A dead end on the left cuts off the tiles above and below it.
+        let left = Vec2::new(0, 1);
+        let previous = puzzle.set_tile(left, Tile::new(Kind::DeadEnd, Orientation::Basic)).unwrap();
+        assert_eq!(puzzle.get_tile(left).unwrap().feature(), Feature::Drain);
+        assert_eq!(powered_count(&puzzle), 7);
+        assert!(!puzzle.get_tile(Vec2::new(0, 0)).unwrap().powered());
+        puzzle.set_tile(left, previous).unwrap();
+        assert!(puzzle.solved());
+
+        // A straight in the center remains the source, but the middle column is cut off.
+        let previous = puzzle.set_tile(source, Tile::new(Kind::Straight, Orientation::Basic));
+        assert_eq!(previous.map(|tile| tile.feature()), Ok(Feature::Source));
+        assert_eq!(puzzle.get_tile(source).unwrap().feature(), Feature::Source);
+        assert_eq!(powered_count(&puzzle), 7);
+        assert!(!puzzle.get_tile(Vec2::new(1, 2)).unwrap().powered());
+
+        assert!(puzzle.set_tile(Vec2::new(0, -1), previous.unwrap()).is_err());
+        assert_eq!(puzzle.validate(), Err(ValidationError::UnconnectedTile(Vec2::new(1, 0))));
+    }
+
+    #[test]
+    fn several_sources_are_invalid() {
+        let mut puzzle = solved_puzzle();
+        let source = *puzzle.source();
+        let copy = *puzzle.get_tile(source).unwrap();
+        puzzle.grid_mut()[Vec2::new(0, 0)] = copy;
+        assert_eq!(puzzle.validate(), Err(ValidationError::MultipleSources(2)));
+
+        puzzle.grid_mut()[source] = Tile::new(Kind::CrossIntersection, Orientation::Basic);
+        puzzle.grid_mut()[Vec2::new(0, 0)] = Tile::new(Kind::CrossIntersection, Orientation::Basic);
+        assert_eq!(puzzle.validate(), Err(ValidationError::NoSource));
+    }
+
+    #[test]
+    fn wall_cuts_off_tiles() {
+        let mut puzzle = solved_puzzle();
+        let source = *puzzle.source();
+        let walls = puzzle.walls().len();
+
+        // The solution is a tree, a wall across one of its connections cuts off a branch.
+        let dir = Direction::all().into_iter().find(|&dir| puzzle.connected(source, dir)).unwrap();
+        let neighbor = source + dir.to_vec2();
+        assert_eq!(puzzle.toggle_wall_between(source, dir), Ok(true));
+        assert!(puzzle.walls_around(neighbor)[(-dir) as usize]);
+        assert!(!puzzle.get_tile(neighbor).unwrap().powered());
+        assert_eq!(powered_count(&puzzle) + puzzle.connected_component(neighbor).len(), 25);
+        assert!(matches!(puzzle.validate(), Err(ValidationError::UnconnectedTile(_))));
+
+        assert_eq!(puzzle.toggle_wall_between(neighbor, -dir), Ok(false));
+        assert!(puzzle.solved());
+        assert_eq!(puzzle.walls().len(), walls);
+        assert_eq!(puzzle.wall_set.len(), walls);
+        assert_eq!(puzzle.validate(), Ok(()));
+
+        // No walls along the boundary of a board without wrapping.
+        assert_eq!(puzzle.toggle_wall(Vec2::new(0, 3), Alignment::Vertical), Ok(false));
+        assert_eq!(puzzle.toggle_wall_between(Vec2::new(4, 4), Direction::Down), Ok(false));
+        assert_eq!(puzzle.walls().len(), walls);
+        assert_eq!(
+            puzzle.toggle_wall(Vec2::new(0, 5), Alignment::Horizontal),
+            Err(EditError::OutOfBounds(Vec2::new(0, 5))),
+        );
+    }
+}
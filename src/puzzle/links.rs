@@ -1,3 +1,5 @@
+use thiserror::Error;
+
 use crate::grid::Direction;
 use crate::puzzle::{Kind, Orientation};
 
@@ -5,11 +7,24 @@ use crate::puzzle::{Kind, Orientation};
 ///
 /// It consists of four links from its center to its four edges. The links can be active or inactive.
 /// It does not know its form (I, L, T, etc.) and rotation.
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct Links {
     links: [bool; 4],
 }
 
+impl Links {
+    /// Return which links are active, indexed by `Direction as usize`.
+    pub fn to_array(self) -> [bool; 4] {
+        self.links
+    }
+
+    /// Return the directions of the active links in counter-clockwise order, starting with
+    /// `Direction::Right`.
+    pub fn directions(&self) -> impl Iterator<Item = Direction> + '_ {
+        Direction::all().into_iter().filter(|&direction| self[direction])
+    }
+}
+
 impl std::ops::Index<Direction> for Links {
     type Output = bool;
 
@@ -24,9 +39,11 @@ impl std::ops::IndexMut<Direction> for Links {
     }
 }
 
-impl From<Links> for (Kind, Orientation) {
-    fn from(links: Links) -> Self {
-        match links.links {
+impl TryFrom<Links> for (Kind, Orientation) {
+    type Error = LinkError;
+
+    fn try_from(links: Links) -> Result<Self, Self::Error> {
+        let tile = match links.links {
             [true, false, false, false] => (Kind::DeadEnd, Orientation::Basic),
             [false, true, false, false] => (Kind::DeadEnd, Orientation::Ccw90),
             [false, false, true, false] => (Kind::DeadEnd, Orientation::Ccw180),
@@ -42,11 +59,19 @@ impl From<Links> for (Kind, Orientation) {
             [true, false, true, true] => (Kind::TIntersection, Orientation::Ccw180),
             [true, true, false, true] => (Kind::TIntersection, Orientation::Ccw270),
             [true, true, true, true] => (Kind::CrossIntersection, Orientation::Basic),
-            _ => unreachable!("encountered an empty tile with no links"),
-        }
+            [false, false, false, false] => return Err(LinkError::Empty),
+        };
+        Ok(tile)
     }
 }
 
+/// The reason why links cannot be turned into a tile.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Error)]
+pub enum LinkError {
+    #[error("encountered an empty tile with no links")]
+    Empty,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,15 +82,24 @@ mod tests {
         let mut links = Links::default();
         links[Direction::Up] = true;
         links[Direction::Left] = true;
-        let (kind, orientation) = links.into();
+        let (kind, orientation) = links.try_into().unwrap();
         assert_eq!(kind, Kind::Corner);
         assert_eq!(orientation, Orientation::Ccw90);
     }
 
     #[test]
-    #[should_panic(expected = "encountered an empty tile with no links")]
+    fn active_directions() {
+        let mut links = Links::default();
+        links[Direction::Down] = true;
+        links[Direction::Right] = true;
+        assert_eq!(links.to_array(), [true, false, false, true]);
+        assert!(links.directions().eq([Direction::Right, Direction::Down]));
+        assert_eq!(Links::default().directions().count(), 0);
+    }
+
+    #[test]
     fn empty_tile() {
-        let links = Links::default();
-        let _: (Kind, Orientation) = links.into();
+        let tile: Result<(Kind, Orientation), _> = Links::default().try_into();
+        assert_eq!(tile, Err(LinkError::Empty));
     }
 }
\ No newline at end of file
@@ -0,0 +1,289 @@
+use strum::IntoEnumIterator;
+
+use crate::grid::{Direction, Vec2};
+
+use super::{Kind, Orientation, Puzzle, Tile};
+
+/// The maximum number of branches the search explores before it gives up.
+const SEARCH_BUDGET: usize = 50_000;
+
+impl Puzzle {
+    /// Count the configurations of tile orientations in which all tiles are powered, stopping
+    /// once `limit` solutions have been found. The current orientations of the tiles are
+    /// irrelevant; the generator's solution is always one of the counted configurations.
+    ///
+    /// Return `None` if the search was aborted because the puzzle is too ambiguous to be
+    /// searched exhaustively in reasonable time.
+    ///
+    /// The search propagates the constraint that links of neighboring tiles must match and
+    /// branches on the tile with the fewest remaining orientations. It is fast for most puzzles,
+    /// but large boards with many ambiguous tiles may take up to a few hundred milliseconds.
+    pub fn count_solutions(&self, limit: usize) -> Option<usize> {
+        let mut solver = Solver::new(self);
+        let mut domains = solver.initial_domains();
+        let all = (0..domains.len()).collect();
+        if !solver.propagate(&mut domains, all) || !solver.feasible(&domains) {
+            return Some(0);
+        }
+        solver.search(domains, limit);
+        (solver.branches <= SEARCH_BUDGET).then_some(solver.solutions.min(limit))
+    }
+}
+
+/// The links of a tile in one orientation, indexed by `Direction`.
+type LinkSet = [bool; 4];
+
+/// A set of candidate orientations of a tile, as a bitmask over the tile's distinct link sets.
+type Domain = u8;
+
+struct Solver<'a> {
+    puzzle: &'a Puzzle,
+    candidates: Vec<Vec<LinkSet>>, // the distinct link sets of each tile, indexed linearly
+    branches: usize,
+    solutions: usize,
+}
+
+impl<'a> Solver<'a> {
+    fn new(puzzle: &'a Puzzle) -> Self {
+        let candidates = puzzle.tiles.iter().map(|tile| {
+            let orientations: &[Orientation] = match tile.kind {
+                Kind::CrossIntersection => &[Orientation::Basic],
+                Kind::Straight => &[Orientation::Basic, Orientation::Ccw90],
+                _ => &[Orientation::Basic, Orientation::Ccw90, Orientation::Ccw180, Orientation::Ccw270],
+            };
+            orientations.iter().map(|&orientation| {
+                Tile { orientation, ..*tile }.links().to_array()
+            }).collect()
+        }).collect();
+
+        Solver { puzzle, candidates, branches: 0, solutions: 0 }
+    }
+
+    /// All orientations which do not link into a wall or, on a non-wrapping board, off the board.
+    fn initial_domains(&self) -> Vec<Domain> {
+        self.puzzle.tiles.indices_iter().enumerate().map(|(i, index)| {
+            let mut domain = 0;
+            for (c, links) in self.candidates[i].iter().enumerate() {
+                let blocked = Direction::iter()
+                    .any(|direction| links[direction as usize] && self.neighbor(index, direction).is_none());
+                if !blocked {
+                    domain |= 1 << c;
+                }
+            }
+            domain
+        }).collect()
+    }
+
+    /// Return the linear index of the tile across the edge of `index` in `direction`, or `None`
+    /// if the edge is closed by a wall or the boundary of a non-wrapping board.
+    fn neighbor(&self, index: Vec2, direction: Direction) -> Option<usize> {
+        let tiles = &self.puzzle.tiles;
+        let neighbor = index + direction.to_vec2();
+        if !self.puzzle.options.wrapping && !tiles.contains_coord(neighbor) {
+            return None;
+        }
+        if self.puzzle.wall_between(index, direction) {
+            return None;
+        }
+        Some(self.linear_index(tiles.normalized_coord(neighbor)))
+    }
+
+    fn linear_index(&self, coord: Vec2) -> usize {
+        coord.y as usize * self.puzzle.tiles.cols() + coord.x as usize
+    }
+
+    fn coord(&self, i: usize) -> Vec2 {
+        let cols = self.puzzle.tiles.cols();
+        Vec2::new((i % cols) as i32, (i / cols) as i32)
+    }
+
+    /// Remove orientations whose links cannot be matched by the neighboring tiles, starting with
+    /// the tiles in `queue`. Return false if a tile is left without any orientation.
+    fn propagate(&self, domains: &mut [Domain], mut queue: Vec<usize>) -> bool {
+        while let Some(i) = queue.pop() {
+            if domains[i] == 0 {
+                return false;
+            }
+            let index = self.coord(i);
+            for direction in Direction::iter() {
+                let Some(n) = self.neighbor(index, direction) else {
+                    continue;
+                };
+                let (may_link, may_not_link) = self.possible_links(i, domains[i], direction);
+                let mut domain = domains[n];
+                for (c, links) in self.candidates[n].iter().enumerate() {
+                    let link = links[(-direction) as usize];
+                    if (link && !may_link) || (!link && !may_not_link) {
+                        domain &= !(1 << c);
+                    }
+                }
+                if domain != domains[n] {
+                    domains[n] = domain;
+                    if domain == 0 {
+                        return false;
+                    }
+                    queue.push(n);
+                }
+            }
+        }
+        true
+    }
+
+    /// Return false if the decided links already form a loop or if some tile cannot be connected
+    /// to the source anymore. The solution of a puzzle is a spanning tree: it has exactly as many
+    /// links as a tree on all tiles, so a fully powered configuration cannot contain a loop.
+    fn feasible(&self, domains: &[Domain]) -> bool {
+        // Detect loops of certain links with a union-find structure.
+        let mut parents = (0..domains.len()).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for (i, &domain) in domains.iter().enumerate() {
+            let index = self.coord(i);
+            // Each edge is visited from both sides, only consider it once.
+            for direction in [Direction::Right, Direction::Down] {
+                let (may_link, may_not_link) = self.possible_links(i, domain, direction);
+                if may_link && !may_not_link && let Some(n) = self.neighbor(index, direction) {
+                    let (a, b) = (root(&mut parents, i), root(&mut parents, n));
+                    if a == b {
+                        return false;
+                    }
+                    parents[a] = b;
+                }
+            }
+        }
+
+        // Check that all tiles can be reached from the source through possible links.
+        let source = self.linear_index(self.puzzle.source);
+        let mut visited = vec![false; domains.len()];
+        visited[source] = true;
+        let mut work_stack = vec![source];
+        let mut count = 1;
+        while let Some(i) = work_stack.pop() {
+            let index = self.coord(i);
+            for direction in Direction::iter() {
+                if self.possible_links(i, domains[i], direction).0
+                    && let Some(n) = self.neighbor(index, direction)
+                    && !visited[n]
+                {
+                    visited[n] = true;
+                    count += 1;
+                    work_stack.push(n);
+                }
+            }
+        }
+        count == domains.len()
+    }
+
+    /// Return whether some orientation in `domain` of tile `i` links in `direction`, and whether
+    /// some orientation does not.
+    fn possible_links(&self, i: usize, domain: Domain, direction: Direction) -> (bool, bool) {
+        let mut may_link = false;
+        let mut may_not_link = false;
+        for (c, links) in self.candidates[i].iter().enumerate() {
+            if domain & 1 << c != 0 {
+                if links[direction as usize] {
+                    may_link = true;
+                } else {
+                    may_not_link = true;
+                }
+            }
+        }
+        (may_link, may_not_link)
+    }
+
+    fn search(&mut self, domains: Vec<Domain>, limit: usize) {
+        if self.solutions >= limit || self.branches > SEARCH_BUDGET {
+            return;
+        }
+
+        // Branch on the undecided tile with the fewest candidates.
+        let undecided = domains.iter()
+            .enumerate()
+            .filter(|(_, domain)| domain.count_ones() > 1)
+            .min_by_key(|(_, domain)| domain.count_ones())
+            .map(|(i, _)| i);
+
+        // All tiles are decided. `feasible` has checked that they are connected to the source.
+        let Some(i) = undecided else {
+            self.solutions += 1;
+            return;
+        };
+
+        for c in 0..self.candidates[i].len() {
+            if domains[i] & 1 << c == 0 {
+                continue;
+            }
+            self.branches += 1;
+            let mut branch = domains.clone();
+            branch[i] = 1 << c;
+            if self.propagate(&mut branch, vec![i]) && self.feasible(&branch) {
+                self.search(branch, limit);
+            }
+            if self.solutions >= limit || self.branches > SEARCH_BUDGET {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::puzzle::{Builder, Feature, Options};
+    use crate::puzzle::tests::tile_with_links as tile;
+
+    #[test]
+    fn generator_solution_is_found() {
+        for wrapping in [false, true] {
+            let options = Options {
+                board_size: 7,
+                wrapping,
+                ..Default::default()
+            };
+            for _ in 0..20 {
+                let puzzle = Builder::new().with_options(options).build();
+                assert_eq!(puzzle.count_solutions(1), Some(1));
+            }
+        }
+    }
+
+    #[test]
+    fn pinwheel_has_two_solutions() {
+        use Direction::*;
+
+        // A cross in the center, surrounded by corners which are connected to the dead ends in
+        // the corners of the board. The mirrored pinwheel is a second solution.
+        let tiles = vec![
+            tile(&[Right]), tile(&[Left, Down]), tile(&[Down]),
+            tile(&[Down, Right]), tile(&[Right, Up, Left, Down]), tile(&[Up, Left]),
+            tile(&[Up]), tile(&[Right, Up]), tile(&[Left]),
+        ];
+        let mut tiles = Grid::from_data(3, 3, tiles);
+        let source = Vec2::new(1, 1);
+        tiles[source].feature = Feature::Source;
+        let puzzle = Puzzle::new(Options::default(), tiles, vec![], source, 0);
+
+        assert_eq!(puzzle.count_solutions(1), Some(1));
+        assert_eq!(puzzle.count_solutions(10), Some(2));
+    }
+
+    #[test]
+    fn build_unique_solution() {
+        let options = Options {
+            board_size: 6,
+            unique_solution: true,
+            ..Default::default()
+        };
+        for _ in 0..5 {
+            let (puzzle, attempts) = Builder::new().with_options(options).build_with_attempts();
+            assert!((1..=Builder::MAX_ATTEMPTS).contains(&attempts));
+            assert_eq!(puzzle.count_solutions(2), Some(1));
+        }
+    }
+}
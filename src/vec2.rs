@@ -1,5 +1,5 @@
 /// 2d discrete vector for navigating on a grid of squares
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Vec2 {
     pub x: i32,
     pub y: i32,
@@ -13,6 +13,25 @@ impl Vec2 {
     pub fn splat(value: i32) -> Self {
         Vec2 { x: value, y: value }
     }
+
+    /// Return the vector with the absolute values of both components.
+    pub fn abs(self) -> Self {
+        Vec2 { x: self.x.abs(), y: self.y.abs() }
+    }
+
+    /// Return the number of steps between two grid squares if only horizontal and vertical steps
+    /// are allowed. Wrapping is not taken into account.
+    pub fn manhattan(self, other: Self) -> i32 {
+        let d = (self - other).abs();
+        d.x + d.y
+    }
+
+    /// Return the number of steps between two grid squares if diagonal steps are allowed, too.
+    /// Wrapping is not taken into account.
+    pub fn chebyshev(self, other: Self) -> i32 {
+        let d = (self - other).abs();
+        d.x.max(d.y)
+    }
 }
 
 impl From<(i32, i32)> for Vec2 {
@@ -27,6 +46,37 @@ impl From<&(i32, i32)> for Vec2 {
     }
 }
 
+/// Grid coordinates on screen, in units of tiles. Multiply by the size of a tile to get points.
+#[cfg(feature = "gui")]
+impl From<Vec2> for eframe::egui::Vec2 {
+    fn from(v: Vec2) -> Self {
+        eframe::egui::Vec2::new(v.x as f32, v.y as f32)
+    }
+}
+
+#[cfg(feature = "gui")]
+impl From<Vec2> for eframe::egui::Pos2 {
+    fn from(v: Vec2) -> Self {
+        eframe::egui::Pos2::new(v.x as f32, v.y as f32)
+    }
+}
+
+/// Round both components to the nearest integer, halves away from zero.
+#[cfg(feature = "gui")]
+impl From<eframe::egui::Vec2> for Vec2 {
+    fn from(v: eframe::egui::Vec2) -> Self {
+        Vec2 { x: v.x.round() as i32, y: v.y.round() as i32 }
+    }
+}
+
+/// Round both components to the nearest integer, halves away from zero.
+#[cfg(feature = "gui")]
+impl From<eframe::egui::Pos2> for Vec2 {
+    fn from(p: eframe::egui::Pos2) -> Self {
+        Vec2::from(p.to_vec2())
+    }
+}
+
 impl std::ops::Add for Vec2 {
     type Output = Self;
 
@@ -49,6 +99,31 @@ impl std::ops::Sub for Vec2 {
     }
 }
 
+impl std::ops::AddAssign for Vec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl std::ops::SubAssign for Vec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl std::ops::Neg for Vec2 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Vec2 {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
 impl std::ops::Mul<i32> for Vec2 {
     type Output = Self;
 
@@ -99,5 +174,44 @@ mod tests {
         let c = a * 2;
         assert_eq!(c.x, 2);
         assert_eq!(c.y, 4);
+
+        let c = -a;
+        assert_eq!(c.x, -1);
+        assert_eq!(c.y, -2);
+
+        let mut c = a;
+        c += b;
+        assert_eq!(c, Vec2::new(4, 6));
+        c -= b;
+        assert_eq!(c, a);
+    }
+
+    #[test]
+    #[cfg(feature = "gui")]
+    fn egui_conversions() {
+        use eframe::egui;
+
+        let v = Vec2::new(3, -2);
+        assert_eq!(egui::Vec2::from(v), egui::Vec2::new(3., -2.));
+        assert_eq!(egui::Pos2::from(v) * 40., egui::Pos2::new(120., -80.));
+        assert_eq!(Vec2::from(egui::Vec2::from(v)), v);
+
+        // Halves are rounded away from zero, everything else to the nearest integer.
+        assert_eq!(Vec2::from(egui::Vec2::new(1.5, -1.5)), Vec2::new(2, -2));
+        assert_eq!(Vec2::from(egui::Vec2::new(2.49, -2.51)), Vec2::new(2, -3));
+        assert_eq!(Vec2::from(egui::Pos2::new(0.4999, -0.4999)), Vec2::new(0, 0));
+        assert_eq!(Vec2::from(egui::Pos2::new(119.6, 80.2)), Vec2::new(120, 80));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Vec2::new(1, 2);
+        let b = Vec2::new(4, -2);
+        assert_eq!((a - b).abs(), Vec2::new(3, 4));
+        assert_eq!(a.manhattan(b), 7);
+        assert_eq!(b.manhattan(a), 7);
+        assert_eq!(a.chebyshev(b), 4);
+        assert_eq!(a.manhattan(a), 0);
+        assert_eq!(a.chebyshev(a), 0);
     }
 }